@@ -0,0 +1,147 @@
+//! Command-line tool for building a binary opening book from game records
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use shogi_core::opening_book::*;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Build a binary opening book from KIF/CSA/USI game records"
+)]
+struct Args {
+    /// Game record file, or directory searched recursively for .kif, .kifu,
+    /// .csa and .usi files
+    #[clap(short, long)]
+    input: PathBuf,
+
+    /// Output binary file path
+    #[clap(short, long)]
+    output: PathBuf,
+
+    /// Number of moves recorded from the start of each game (default: 40)
+    #[clap(long, default_value = "40")]
+    max_ply: usize,
+
+    /// Drop moves played fewer times than this (default: 1)
+    #[clap(long, default_value = "1")]
+    min_count: u32,
+
+    /// Binary format version (see convert_opening_book); versions 2 and 3
+    /// keep positions that differ only in the side to move apart
+    #[clap(long, default_value = "3")]
+    format_version: u32,
+
+    /// Enable gzip compression
+    #[clap(long)]
+    compress: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let scheme = HashScheme::from_version(args.format_version)
+        .ok_or_else(|| anyhow!("Unsupported format version: {}", args.format_version))?;
+
+    println!("Building {} from {}", args.output.display(), args.input.display());
+    println!("  Max ply: {}", args.max_ply);
+    println!("  Min count: {}", args.min_count);
+    println!("  Hash scheme: {:?} (format version {})", scheme, scheme.version());
+
+    let start_time = Instant::now();
+
+    let mut files = Vec::new();
+    collect_files(&args.input, &mut files)?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    if files.is_empty() {
+        return Err(anyhow!("No game record files found in {}", args.input.display()));
+    }
+    println!("\nReading {} game record files...", files.len());
+
+    let mut builder = BookBuilder::new(args.max_ply);
+    let mut failed = 0;
+    for (path, format) in &files {
+        match read_games(path, *format) {
+            Ok(games) => games.iter().for_each(|game| builder.add_game(game)),
+            Err(e) => {
+                eprintln!("Warning: Skipping {}: {e:#}", path.display());
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "Read {} games ({} files skipped), {} positions",
+        builder.game_count(),
+        failed,
+        builder.len()
+    );
+
+    let entries = builder.entries(args.min_count);
+    println!(
+        "Kept {} positions with moves played at least {} times",
+        entries.len(),
+        args.min_count
+    );
+    if entries.is_empty() {
+        return Err(anyhow!("No positions to write"));
+    }
+
+    println!("\nWriting binary output...");
+    let converter = BinaryConverter::with_scheme(scheme);
+    let mut buffer = Vec::new();
+    let stats = converter.write_binary(&entries, &mut buffer)?;
+    if args.compress {
+        buffer = converter.compress_data(&buffer)?;
+    }
+    let mut writer = BufWriter::new(File::create(&args.output)?);
+    writer.write_all(&buffer)?;
+    writer.flush()?;
+
+    println!("\nBuild complete!");
+    println!("Statistics:");
+    println!("  Positions written: {}", stats.positions_written);
+    println!("  Total moves: {}", stats.total_moves);
+    println!("  Output file size: {:.2} MB", buffer.len() as f64 / 1_048_576.0);
+    println!("  Time elapsed: {:.2}s", start_time.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+/// Game record files under `path` with their format
+fn collect_files(path: &Path, files: &mut Vec<(PathBuf, GameFormat)>) -> Result<()> {
+    if path.is_dir() {
+        let entries = std::fs::read_dir(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        for entry in entries {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else if let Some(format) = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(GameFormat::from_extension)
+    {
+        files.push((path.to_path_buf(), format));
+    }
+    Ok(())
+}
+
+/// Parse a file, reading it as UTF-8 or else as Shift_JIS (the usual
+/// encoding of `.kif` files)
+fn read_games(path: &Path, format: GameFormat) -> Result<Vec<GameRecord>> {
+    let bytes = std::fs::read(path)?;
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            let (text, _, had_errors) = encoding_rs::SHIFT_JIS.decode(e.as_bytes());
+            if had_errors {
+                return Err(anyhow!("Neither UTF-8 nor Shift_JIS text"));
+            }
+            text.into_owned()
+        }
+    };
+    format.parse(&text)
+}
@@ -50,6 +50,12 @@ struct Args {
     /// Validate conversion by reading back the output
     #[clap(long)]
     validate: bool,
+
+    /// Binary format version: 1 = board and hands, 2 = also side to move,
+    /// 3 = side to move and per-count hand keys (re-run on the SFEN source to
+    /// migrate an existing book)
+    #[clap(long, default_value = "1")]
+    format_version: u32,
 }
 
 fn main() -> Result<()> {
@@ -113,7 +119,11 @@ fn main() -> Result<()> {
 
     println!("\nApplying filters and converting to binary...");
 
-    let converter = BinaryConverter::new();
+    let scheme = HashScheme::from_version(args.format_version)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported format version: {}", args.format_version))?;
+    println!("Hash scheme: {:?} (format version {})", scheme, scheme.version());
+
+    let converter = BinaryConverter::with_scheme(scheme);
     let mut filtered_entries = Vec::new();
     let mut filtered_count = 0;
 
@@ -7,10 +7,14 @@
 //! Examples:
 //!   cargo run --bin search_opening_book -- converted_openings/opening_book_web.binz "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1"
 //!   cargo run --bin search_opening_book -- converted_openings/opening_book_web.binz --hash 0x6327d878264056a0
+//!
+//! Uncompressed indexed books are memory-mapped and searched in place; other
+//! books are decompressed and loaded into memory first.
 
 use anyhow::{anyhow, Result};
 use shogi_core::opening_book::position_hasher::PositionHasher;
-use shogi_core::opening_book_reader::OpeningBookReader;
+use shogi_core::opening_book::{HashScheme, MappedBook};
+use shogi_core::opening_book_reader::{BookMove, OpeningBookReader};
 use std::env;
 use std::fs;
 use std::path::Path;
@@ -39,34 +43,22 @@ fn main() -> Result<()> {
         std::process::exit(1);
     }
 
+    let book = open_book(binary_file);
+
     // Determine search mode
     let hash = if args.len() >= 4 && args[2] == "--hash" {
         // Hash mode
         let hash_str = &args[3];
         parse_hash(hash_str)?
     } else {
-        // SFEN mode
+        // SFEN mode, hashed the way the loaded book was written
         let sfen = &args[2];
-        PositionHasher::hash_position(sfen)?
+        PositionHasher::hash_position_with(sfen, book.hash_scheme())?
     };
 
-    // Load binary file
-    println!("Loading binary file: {binary_file}");
-    let compressed_data = fs::read(binary_file)?;
-
-    // Create reader and load data
-    let mut reader = OpeningBookReader::new();
-    match reader.load_data(&compressed_data) {
-        Ok(msg) => println!("{msg}"),
-        Err(e) => {
-            eprintln!("Error loading data: {e}");
-            std::process::exit(1);
-        }
-    }
-
     // Search for moves
     println!("\nSearching for hash: {hash:#016x}");
-    let moves = reader.find_moves_by_hash(hash);
+    let moves = book.find_moves_by_hash(hash);
 
     if moves.is_empty() {
         println!("No moves found for this position.");
@@ -89,6 +81,56 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Book opened either by mapping the file or by loading it into memory
+enum Book {
+    Mapped(MappedBook),
+    Loaded(OpeningBookReader),
+}
+
+impl Book {
+    fn hash_scheme(&self) -> HashScheme {
+        match self {
+            Book::Mapped(book) => book.scheme(),
+            Book::Loaded(reader) => reader.hash_scheme(),
+        }
+    }
+
+    fn find_moves_by_hash(&self, hash: u64) -> Vec<BookMove> {
+        match self {
+            Book::Mapped(book) => book.find_moves_by_hash(hash),
+            Book::Loaded(reader) => reader.find_moves_by_hash(hash),
+        }
+    }
+}
+
+fn open_book(binary_file: &str) -> Book {
+    if let Ok(book) = MappedBook::open(binary_file) {
+        println!("Mapped indexed book: {binary_file} ({} positions)", book.len());
+        return Book::Mapped(book);
+    }
+
+    // Load binary file
+    println!("Loading binary file: {binary_file}");
+    let compressed_data = match fs::read(binary_file) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading file: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    // Create reader and load data
+    let mut reader = OpeningBookReader::new();
+    match reader.load_data(&compressed_data) {
+        Ok(msg) => println!("{msg}"),
+        Err(e) => {
+            eprintln!("Error loading data: {e}");
+            std::process::exit(1);
+        }
+    }
+    Book::Loaded(reader)
+}
+
 fn parse_hash(hash_str: &str) -> Result<u64> {
     // Remove 0x prefix if present
     let hash_str = if hash_str.starts_with("0x") || hash_str.starts_with("0X") {
@@ -4,7 +4,7 @@
 //! Example: cargo run --bin sfen_hasher -- "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1"
 
 use anyhow::Result;
-use shogi_core::opening_book::position_hasher::PositionHasher;
+use shogi_core::opening_book::position_hasher::{HashScheme, PositionHasher};
 use std::env;
 
 fn main() -> Result<()> {
@@ -26,6 +26,11 @@ fn main() -> Result<()> {
             println!("SFEN: {sfen}");
             println!("Hash: {hash:#016x}");
             println!("Hash (decimal): {hash}");
+            // Newer book formats hash the position differently
+            let hash_v2 = PositionHasher::hash_position_with(sfen, HashScheme::WithSideToMove)?;
+            println!("Hash with side to move (v2): {hash_v2:#016x}");
+            let hash_v3 = PositionHasher::hash_position_with(sfen, HashScheme::PerCountHands)?;
+            println!("Hash with per-count hands (v3): {hash_v3:#016x}");
         }
         Err(e) => {
             eprintln!("Error: Failed to hash SFEN position: {e}");
@@ -0,0 +1,579 @@
+//! USI protocol engine built on shogi_core
+//!
+//! Reads USI commands from stdin and answers on stdout, so it can be
+//! registered in ShogiGUI, Shogidokoro or a match runner.
+//!
+//! Usage: cargo run --release --bin usi_engine
+//!
+//! On `go` the engine plays a move from the opening book if there is one,
+//! otherwise it spends a share of the time budget looking for a mate with
+//! df-pn, and the rest on the alpha-beta search. The search evaluates with
+//! the NNUE file given as `EvalFile` (YaneuraOu halfKP 256x2-32-32 `nn.bin`),
+//! or with the hand-crafted evaluation when none is set. `EngineStrength`
+//! picks a difficulty preset that caps the search, adds noise and limits
+//! how long the book is followed.
+
+use anyhow::{anyhow, Result};
+use shogi_core::evaluation::NnueNetwork;
+use shogi_core::opening_book_reader::OpeningBookReader;
+use shogi_core::search::{
+    EngineStrength, SearchEngine, SearchLimits, SearchResult, DEFAULT_HASH_MB, MAX_DEPTH,
+    STRENGTH_LEVELS,
+};
+use shogi_core::shogi::{Move, Player, Position};
+use shogi_core::MateSearchSession;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const ENGINE_NAME: &str = "shogi_core";
+const ENGINE_AUTHOR: &str = "shogi_core developers";
+
+/// Nodes searched between checks of the stop flag and the clock
+const MATE_STEP_NODES: u32 = 4096;
+
+/// The mate search gets this fraction of the budget
+const MATE_TIME_SHARE: u32 = 4;
+
+/// Node cap for the mate search when there is no budget to share
+const MATE_MAX_NODES: u32 = 1 << 20;
+
+/// Values set with `setoption`
+#[derive(Debug, Clone)]
+struct Options {
+    /// Gzip compressed binary book (`.binz`); empty for none
+    book_file: String,
+    use_book: bool,
+    /// NNUE network (`nn.bin`); empty for the hand-crafted evaluation
+    eval_file: String,
+    /// Maximum mate length in plies; 0 disables the mate search
+    mate_search_depth: u8,
+    /// Time kept back from every move for communication delays
+    byoyomi_margin_ms: u64,
+    /// Transposition table size in MB
+    hash_mb: usize,
+    /// Difficulty preset, one of `STRENGTH_LEVELS`
+    strength: EngineStrength,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            book_file: String::new(),
+            use_book: true,
+            eval_file: String::new(),
+            mate_search_depth: 15,
+            byoyomi_margin_ms: 100,
+            hash_mb: DEFAULT_HASH_MB,
+            strength: EngineStrength::default(),
+        }
+    }
+}
+
+impl Options {
+    fn print_usi_options() {
+        println!("option name USI_Ponder type check default false");
+        println!("option name USI_Hash type spin default {DEFAULT_HASH_MB} min 1 max 4096");
+        println!("option name BookFile type string default <empty>");
+        println!("option name UseBook type check default true");
+        println!("option name EvalFile type string default <empty>");
+        println!("option name MateSearchDepth type spin default 15 min 0 max 63");
+        println!("option name ByoyomiMargin type spin default 100 min 0 max 10000");
+        let levels: Vec<String> =
+            STRENGTH_LEVELS.iter().map(|level| format!("var {level}")).collect();
+        println!("option name EngineStrength type combo default expert {}", levels.join(" "));
+    }
+
+    /// Apply `setoption name <name> [value <value>]`
+    fn set(&mut self, args: &str) -> Result<()> {
+        let args = args.trim().strip_prefix("name ").ok_or_else(|| anyhow!("missing name"))?;
+        let (name, value) = match args.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (args.trim(), ""),
+        };
+
+        match name {
+            "BookFile" => self.book_file = parse_path(value),
+            "EvalFile" => self.eval_file = parse_path(value),
+            "UseBook" => self.use_book = parse_check(value)?,
+            "MateSearchDepth" => self.mate_search_depth = value.parse()?,
+            "ByoyomiMargin" => self.byoyomi_margin_ms = value.parse()?,
+            "EngineStrength" => self.strength = EngineStrength::from_level(value)?,
+            "USI_Hash" => self.hash_mb = value.parse::<usize>()?.max(1),
+            // Pondering only needs `go ponder`
+            "USI_Ponder" => {}
+            _ => return Err(anyhow!("unknown option: {}", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Path option value, where `<empty>` means none
+fn parse_path(value: &str) -> String {
+    if value == "<empty>" {
+        String::new()
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_check(value: &str) -> Result<bool> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(anyhow!("expected true or false, got {}", value)),
+    }
+}
+
+/// Arguments of `go`, times in milliseconds
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct GoLimits {
+    btime: Option<u64>,
+    wtime: Option<u64>,
+    binc: Option<u64>,
+    winc: Option<u64>,
+    byoyomi: Option<u64>,
+    movetime: Option<u64>,
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    infinite: bool,
+    ponder: bool,
+}
+
+impl GoLimits {
+    fn parse(args: &str) -> Result<GoLimits> {
+        let mut limits = GoLimits::default();
+        let mut tokens = args.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let mut value = || -> Result<u64> {
+                let value = tokens.next().ok_or_else(|| anyhow!("{} needs a value", token))?;
+                value.parse().map_err(|_| anyhow!("invalid {} value: {}", token, value))
+            };
+            match token {
+                "btime" => limits.btime = Some(value()?),
+                "wtime" => limits.wtime = Some(value()?),
+                "binc" => limits.binc = Some(value()?),
+                "winc" => limits.winc = Some(value()?),
+                "byoyomi" => limits.byoyomi = Some(value()?),
+                "movetime" => limits.movetime = Some(value()?),
+                "depth" => limits.depth = Some(value()?.min(MAX_DEPTH as u64) as u8),
+                "nodes" => limits.nodes = Some(value()?),
+                "infinite" => limits.infinite = true,
+                "ponder" => limits.ponder = true,
+                // `go mate` is not supported, search normally
+                _ => {}
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Time to spend on this move for the side to move, `None` when the
+    /// search only ends on `stop`
+    fn budget(&self, position: &Position, margin_ms: u64) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(Duration::from_millis(movetime.saturating_sub(margin_ms).max(1)));
+        }
+
+        let (time, inc) = match position.side_to_move() {
+            Player::Black => (self.btime, self.binc),
+            Player::White => (self.wtime, self.winc),
+        };
+        if time.is_none() && inc.is_none() && self.byoyomi.is_none() {
+            return None;
+        }
+
+        let time = time.unwrap_or(0);
+        let extra = inc.unwrap_or(0) + self.byoyomi.unwrap_or(0);
+        // A fixed share of the remaining time plus everything added per move,
+        // never more than is actually left
+        let budget = (time / 40 + extra).min(time + extra).saturating_sub(margin_ms);
+        Some(Duration::from_millis(budget.max(1)))
+    }
+}
+
+/// A running `go`
+struct Search {
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+    /// Hands the search engine back with its hash table when done
+    thread: JoinHandle<SearchEngine>,
+}
+
+impl Search {
+    fn stop(self) -> Option<SearchEngine> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.pondering.store(false, Ordering::SeqCst);
+        self.thread.join().ok()
+    }
+}
+
+/// Everything a search thread needs, moved into it on `go`
+struct Think {
+    position: Position,
+    book: Option<Arc<OpeningBookReader>>,
+    searcher: SearchEngine,
+    mate_search_depth: u8,
+    budget: Option<Duration>,
+    max_depth: u8,
+    node_limit: Option<u64>,
+    /// `go infinite`: hold the result until `stop`
+    infinite: bool,
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+}
+
+impl Think {
+    fn run(mut self) -> SearchEngine {
+        let start = Instant::now();
+        let best = self
+            .book_move()
+            .or_else(|| self.mate_move())
+            .or_else(|| self.search_move(start));
+
+        // A pondering or infinite search must not answer before it is told to
+        while (self.infinite || self.pondering.load(Ordering::SeqCst))
+            && !self.stop.load(Ordering::SeqCst)
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        match best {
+            Some(mv) => println!("bestmove {}", mv.to_usi()),
+            None => println!("bestmove resign"),
+        }
+        self.searcher
+    }
+
+    /// Highest rated legal book move
+    fn book_move(&self) -> Option<Move> {
+        let book = self.book.as_ref()?;
+        let (mv, evaluation) = book
+            .find_moves_for_position(&self.position)
+            .into_iter()
+            .filter_map(|book_move| {
+                let mv = Move::from_usi(&book_move.notation).ok()?;
+                self.position.is_legal(mv).then_some((mv, book_move.evaluation))
+            })
+            .max_by_key(|&(_, evaluation)| evaluation)?;
+
+        println!("info string book move {} ({})", mv.to_usi(), evaluation);
+        Some(mv)
+    }
+
+    /// First move of the shortest mate found within the mate search's share
+    /// of the budget
+    fn mate_move(&self) -> Option<Move> {
+        if self.mate_search_depth == 0 {
+            return None;
+        }
+
+        let start = Instant::now();
+        let mut deadline = self.deadline(start, MATE_TIME_SHARE);
+        let mut session =
+            MateSearchSession::from_position(&self.position, self.mate_search_depth, None);
+
+        while !session.is_finished() && !self.stop.load(Ordering::SeqCst) {
+            // Pondering runs on the opponent's time; the clock starts at ponderhit
+            if self.pondering.load(Ordering::SeqCst) {
+                deadline = None;
+            } else if deadline.is_none() {
+                deadline = self.deadline(Instant::now(), MATE_TIME_SHARE);
+            }
+            let out_of_time = match deadline {
+                Some(deadline) => Instant::now() >= deadline,
+                None => session.progress().node_count >= MATE_MAX_NODES,
+            };
+            if out_of_time {
+                break;
+            }
+            session.step(MATE_STEP_NODES);
+        }
+
+        let result = session.result();
+        let line = result.moves();
+        let first = Move::from_usi(line.first()?).ok()?;
+        println!(
+            "info depth {} nodes {} time {} score mate {} pv {}",
+            line.len(),
+            result.node_count,
+            start.elapsed().as_millis(),
+            line.len(),
+            line.join(" ")
+        );
+        Some(first)
+    }
+
+    /// Best move of the alpha-beta search in the rest of the budget
+    fn search_move(&mut self, start: Instant) -> Option<Move> {
+        let pondering = self.pondering.load(Ordering::SeqCst);
+        let remaining = self.deadline(start, 1).map(|deadline| {
+            let left = deadline.saturating_duration_since(Instant::now());
+            left.as_millis().max(1) as u32
+        });
+        let limits = SearchLimits {
+            max_depth: self.max_depth,
+            time_ms: if pondering { None } else { remaining },
+            node_limit: self.node_limit,
+        };
+
+        let done = Arc::new(AtomicBool::new(false));
+        let watcher = pondering.then(|| self.watch_ponderhit(Arc::clone(&done)));
+        let result = self.searcher.search_with_info(&self.position, &limits, print_info);
+        done.store(true, Ordering::SeqCst);
+        if let Some(watcher) = watcher {
+            let _ = watcher.join();
+        }
+
+        result.best()
+    }
+
+    /// While pondering the search has no time limit; after ponderhit this
+    /// stops it once the budget has passed
+    fn watch_ponderhit(&self, done: Arc<AtomicBool>) -> JoinHandle<()> {
+        let stop = Arc::clone(&self.stop);
+        let pondering = Arc::clone(&self.pondering);
+        let budget = self.budget.filter(|_| !self.infinite);
+
+        thread::spawn(move || {
+            let finished = || done.load(Ordering::SeqCst) || stop.load(Ordering::SeqCst);
+            while pondering.load(Ordering::SeqCst) && !finished() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let Some(budget) = budget else {
+                return;
+            };
+            let deadline = Instant::now() + budget;
+            while Instant::now() < deadline && !finished() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            if !done.load(Ordering::SeqCst) {
+                stop.store(true, Ordering::SeqCst);
+            }
+        })
+    }
+
+    /// End of `1 / share` of the budget counted from `from`
+    fn deadline(&self, from: Instant, share: u32) -> Option<Instant> {
+        if self.infinite {
+            return None;
+        }
+        self.budget.map(|budget| from + budget / share)
+    }
+}
+
+fn print_info(result: &SearchResult) {
+    let score = match result.mate_in() {
+        Some(plies) => format!("mate {plies}"),
+        None => format!("cp {}", result.score),
+    };
+    let nps = result.node_count as u64 * 1000 / result.elapsed_ms.max(1) as u64;
+    println!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth,
+        score,
+        result.node_count,
+        nps,
+        result.elapsed_ms,
+        result.pv().join(" ")
+    );
+}
+
+/// Protocol state between commands
+struct Engine {
+    options: Options,
+    position: Position,
+    /// Loaded book and the file it came from
+    book: Option<(String, Arc<OpeningBookReader>)>,
+    /// Loaded NNUE network and the file it came from
+    nnue: Option<(String, Arc<NnueNetwork>)>,
+    /// Idle search engine; moved into the search thread during `go`
+    searcher: Option<SearchEngine>,
+    /// Hash size the search engine was created with
+    hash_mb: usize,
+    search: Option<Search>,
+}
+
+impl Engine {
+    fn new() -> Self {
+        Self {
+            options: Options::default(),
+            position: Position::startpos(),
+            book: None,
+            nnue: None,
+            searcher: Some(SearchEngine::new(DEFAULT_HASH_MB)),
+            hash_mb: DEFAULT_HASH_MB,
+            search: None,
+        }
+    }
+
+    /// Handle one command line, returning false on `quit`
+    fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "usi" => {
+                println!("id name {ENGINE_NAME} {}", env!("CARGO_PKG_VERSION"));
+                println!("id author {ENGINE_AUTHOR}");
+                Options::print_usi_options();
+                println!("usiok");
+            }
+            "isready" => {
+                self.stop_search();
+                if self.hash_mb != self.options.hash_mb {
+                    self.hash_mb = self.options.hash_mb;
+                    self.searcher = Some(SearchEngine::new(self.hash_mb));
+                }
+                if let Err(e) = self.load_book() {
+                    println!("info string failed to load book: {e:#}");
+                }
+                if let Err(e) = self.load_nnue() {
+                    println!("info string failed to load eval file: {e:#}");
+                }
+                println!("readyok");
+            }
+            "setoption" => {
+                if let Err(e) = self.options.set(args) {
+                    println!("info string setoption ignored: {e:#}");
+                }
+            }
+            "usinewgame" => {
+                self.stop_search();
+                self.searcher_mut().clear();
+            }
+            "position" => {
+                self.stop_search();
+                match Position::from_usi_position(args) {
+                    Ok(position) => self.position = position,
+                    Err(e) => println!("info string invalid position: {e}"),
+                }
+            }
+            "go" => {
+                self.stop_search();
+                match GoLimits::parse(args) {
+                    Ok(limits) => self.start_search(&limits),
+                    Err(e) => println!("info string invalid go: {e:#}"),
+                }
+            }
+            "stop" => self.stop_search(),
+            "ponderhit" => {
+                if let Some(search) = &self.search {
+                    search.pondering.store(false, Ordering::SeqCst);
+                }
+            }
+            "gameover" => self.stop_search(),
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            "" => {}
+            _ => println!("info string unknown command: {command}"),
+        }
+        true
+    }
+
+    /// Load the book named by the options unless it is already loaded
+    fn load_book(&mut self) -> Result<()> {
+        let path = &self.options.book_file;
+        if !self.options.use_book || path.is_empty() {
+            self.book = None;
+            return Ok(());
+        }
+        if self.book.as_ref().is_some_and(|(loaded, _)| loaded == path) {
+            return Ok(());
+        }
+
+        let data = std::fs::read(path)?;
+        let mut reader = OpeningBookReader::new();
+        let message = reader.load_data(&data).map_err(|e| anyhow!(e))?;
+        println!("info string {message} from {path}");
+        self.book = Some((path.clone(), Arc::new(reader)));
+        Ok(())
+    }
+
+    /// Load the NNUE network named by the options unless it is already loaded
+    fn load_nnue(&mut self) -> Result<()> {
+        let path = &self.options.eval_file;
+        if path.is_empty() {
+            self.nnue = None;
+            return Ok(());
+        }
+        if self.nnue.as_ref().is_some_and(|(loaded, _)| loaded == path) {
+            return Ok(());
+        }
+
+        // Keep playing with the hand-crafted evaluation if the file is unusable
+        self.nnue = None;
+        let file = std::fs::File::open(path)?;
+        let network = NnueNetwork::from_reader(io::BufReader::new(file))?;
+        println!("info string loaded NNUE {} from {path}", network.architecture());
+        self.nnue = Some((path.clone(), Arc::new(network)));
+        Ok(())
+    }
+
+    fn searcher_mut(&mut self) -> &mut SearchEngine {
+        let hash_mb = self.hash_mb;
+        self.searcher.get_or_insert_with(|| SearchEngine::new(hash_mb))
+    }
+
+    fn start_search(&mut self, limits: &GoLimits) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let pondering = Arc::new(AtomicBool::new(limits.ponder));
+
+        let mut searcher = self.searcher.take().unwrap_or_else(|| SearchEngine::new(self.hash_mb));
+        searcher.set_stop_flag(Arc::clone(&stop));
+        searcher.set_nnue(self.nnue.as_ref().map(|(_, network)| Arc::clone(network)));
+        let strength = self.options.strength;
+        searcher.set_strength(strength);
+
+        let think = Think {
+            position: self.position.clone(),
+            book: self
+                .book
+                .as_ref()
+                .filter(|_| self.options.use_book && strength.allows_book(self.position.ply()))
+                .map(|(_, book)| Arc::clone(book)),
+            searcher,
+            // A weakened engine does not see mates deeper than it searches
+            mate_search_depth: self.options.mate_search_depth.min(strength.max_depth),
+            budget: limits.budget(&self.position, self.options.byoyomi_margin_ms),
+            max_depth: limits.depth.unwrap_or(MAX_DEPTH),
+            node_limit: limits.nodes,
+            infinite: limits.infinite,
+            stop: Arc::clone(&stop),
+            pondering: Arc::clone(&pondering),
+        };
+
+        self.search = Some(Search {
+            stop,
+            pondering,
+            thread: thread::spawn(move || think.run()),
+        });
+    }
+
+    /// Stop a running search; it still answers with `bestmove`
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            // A panicked search thread loses its engine; a new one is made on demand
+            self.searcher = search.stop();
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let mut engine = Engine::new();
+
+    for line in io::stdin().lock().lines() {
+        if !engine.handle(&line?) {
+            break;
+        }
+    }
+
+    engine.stop_search();
+    Ok(())
+}
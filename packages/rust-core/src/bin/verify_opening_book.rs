@@ -50,6 +50,16 @@ fn main() -> Result<()> {
     println!("Verifying binary file: {}", args.binary.display());
     println!("{}", "=".repeat(60));
 
+    // A single query on an uncompressed indexed book only needs a mapping
+    if let Some(check_pos) = &args.check_position {
+        if let Ok(book) = MappedBook::open(&args.binary) {
+            println!("Mapped indexed book with {} positions ({:?})", book.len(), book.scheme());
+            println!();
+            let hash = PositionHasher::hash_position_with(check_pos, book.scheme())?;
+            return report_position(check_pos, hash, book.find_entry(hash)?.as_ref());
+        }
+    }
+
     // Load binary file
     let converter = BinaryConverter::new();
     let file = File::open(&args.binary)?;
@@ -60,19 +70,35 @@ fn main() -> Result<()> {
     reader.read_exact(&mut magic)?;
     reader = BufReader::new(File::open(&args.binary)?); // Reset reader
 
-    let entries = if magic == [0x1f, 0x8b] {
+    let (header, entries) = if magic == [0x1f, 0x8b] {
         // Gzip compressed
         println!("Detected gzip compressed file");
         let mut compressed_data = Vec::new();
         reader.read_to_end(&mut compressed_data)?;
         let decompressed = converter.decompress_data(&compressed_data)?;
         let mut cursor = std::io::Cursor::new(decompressed);
-        converter.read_binary(&mut cursor)?
+        converter.read_binary_with_header(&mut cursor)?
     } else {
-        converter.read_binary(&mut reader)?
+        converter.read_binary_with_header(&mut reader)?
     };
+    let scheme = header.scheme().unwrap_or_default();
 
     println!("Successfully loaded {} positions", entries.len());
+    println!(
+        "Format version: {} ({:?}, {}{})",
+        scheme.version(),
+        scheme,
+        if header.is_indexed() {
+            "indexed"
+        } else {
+            "unindexed"
+        },
+        if header.has_move_stats() {
+            ", with move statistics"
+        } else {
+            ""
+        }
+    );
     println!();
 
     // Show statistics
@@ -86,7 +112,7 @@ fn main() -> Result<()> {
 
     // Check specific position if requested
     if let Some(check_pos) = args.check_position {
-        check_specific_position(&check_pos, &entries)?;
+        check_specific_position(&check_pos, &entries, scheme)?;
         return Ok(());
     }
 
@@ -98,7 +124,7 @@ fn main() -> Result<()> {
     // Compare with original if provided
     if let Some(original_path) = args.original {
         println!("\nComparing with original file...");
-        compare_with_original(&original_path, &entries)?;
+        compare_with_original(&original_path, &entries, scheme)?;
     }
 
     // Export to text if requested
@@ -167,17 +193,19 @@ fn show_sample_entries(entries: &[BinaryEntry], count: usize, detailed: bool) ->
         );
 
         println!("  Number of moves: {}", entry.header.move_count);
+        println!("  Popularity: {}", entry.header.popularity);
 
         if detailed {
             println!("  All moves:");
             for (j, move_data) in entry.moves.iter().enumerate() {
                 let move_str = MoveEncoder::decode_move(move_data.move_encoded)?;
                 println!(
-                    "    {}. {} (eval: {}, depth: {})",
+                    "    {}. {} (eval: {}, depth: {}{})",
                     j + 1,
                     move_str,
                     move_data.evaluation,
-                    move_data.depth
+                    move_data.depth,
+                    stats_suffix(entry, j)
                 );
             }
         }
@@ -186,15 +214,18 @@ fn show_sample_entries(entries: &[BinaryEntry], count: usize, detailed: bool) ->
     Ok(())
 }
 
-fn check_specific_position(sfen: &str, entries: &[BinaryEntry]) -> Result<()> {
-    println!("Checking position: {sfen}");
-
+fn check_specific_position(sfen: &str, entries: &[BinaryEntry], scheme: HashScheme) -> Result<()> {
     // Calculate hash
-    let hash = PositionHasher::hash_position(sfen)?;
-    println!("Position hash: 0x{hash:016x}");
+    let hash = PositionHasher::hash_position_with(sfen, scheme)?;
 
     // Find in entries
     let found = entries.iter().find(|e| e.header.position_hash == hash);
+    report_position(sfen, hash, found)
+}
+
+fn report_position(sfen: &str, hash: u64, found: Option<&BinaryEntry>) -> Result<()> {
+    println!("Checking position: {sfen}");
+    println!("Position hash: 0x{hash:016x}");
 
     match found {
         Some(entry) => {
@@ -211,11 +242,12 @@ fn check_specific_position(sfen: &str, entries: &[BinaryEntry]) -> Result<()> {
             for (i, move_data) in entry.moves.iter().enumerate() {
                 let move_str = MoveEncoder::decode_move(move_data.move_encoded)?;
                 println!(
-                    "    {}. {} (eval: {}, depth: {})",
+                    "    {}. {} (eval: {}, depth: {}{})",
                     i + 1,
                     move_str,
                     move_data.evaluation,
-                    move_data.depth
+                    move_data.depth,
+                    stats_suffix(entry, i)
                 );
             }
         }
@@ -228,7 +260,11 @@ fn check_specific_position(sfen: &str, entries: &[BinaryEntry]) -> Result<()> {
     Ok(())
 }
 
-fn compare_with_original(original_path: &PathBuf, binary_entries: &[BinaryEntry]) -> Result<()> {
+fn compare_with_original(
+    original_path: &PathBuf,
+    binary_entries: &[BinaryEntry],
+    scheme: HashScheme,
+) -> Result<()> {
     let file = File::open(original_path)?;
     let reader = BufReader::new(file);
 
@@ -274,7 +310,9 @@ fn compare_with_original(original_path: &PathBuf, binary_entries: &[BinaryEntry]
     let mut not_found = 0;
 
     for orig_entry in &original_entries {
-        let hash = match PositionHasher::hash_position(&orig_entry.position) {
+        let position_str =
+            format!("{} {} {}", orig_entry.position, orig_entry.turn, orig_entry.hand);
+        let hash = match PositionHasher::hash_position_with(&position_str, scheme) {
             Ok(h) => h,
             Err(_) => continue,
         };
@@ -334,11 +372,30 @@ fn export_to_text(entries: &[BinaryEntry], output_path: &PathBuf) -> Result<()>
         )?;
 
         writeln!(file, "# Moves:")?;
-        for move_data in &entry.moves {
+        for (j, move_data) in entry.moves.iter().enumerate() {
             let move_str = MoveEncoder::decode_move(move_data.move_encoded)?;
-            writeln!(file, "{} eval={} depth={}", move_str, move_data.evaluation, move_data.depth)?;
+            write!(file, "{} eval={} depth={}", move_str, move_data.evaluation, move_data.depth)?;
+            match entry.move_stats.get(j) {
+                Some(stats) => writeln!(
+                    file,
+                    " played={} wins={} draws={} losses={}",
+                    stats.play_count, stats.wins, stats.draws, stats.losses
+                )?,
+                None => writeln!(file)?,
+            }
         }
     }
 
     Ok(())
 }
+
+/// Play count and results of a move, for books built from game records
+fn stats_suffix(entry: &BinaryEntry, move_index: usize) -> String {
+    match entry.move_stats.get(move_index) {
+        Some(stats) => format!(
+            ", played: {}, W/D/L: {}/{}/{}",
+            stats.play_count, stats.wins, stats.draws, stats.losses
+        ),
+        None => String::new(),
+    }
+}
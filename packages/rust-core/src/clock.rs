@@ -0,0 +1,106 @@
+//! Millisecond clocks for time-limited searches
+//!
+//! Searches only need elapsed time, so they take a [`Clock`] instead of
+//! talking to `window.performance` directly. [`DefaultClock`] picks the
+//! right implementation for the target: `std::time::Instant` natively, and
+//! the global `performance` object on wasm, which exists on the main thread,
+//! in Web Workers and in Node.
+
+/// Monotonic time source in milliseconds
+pub trait Clock {
+    /// Milliseconds since an arbitrary fixed origin
+    fn now_ms(&self) -> f64;
+}
+
+/// Clock backed by `std::time::Instant`
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub struct InstantClock {
+    epoch: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl InstantClock {
+    pub fn new() -> Self {
+        InstantClock {
+            epoch: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for InstantClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for InstantClock {
+    fn now_ms(&self) -> f64 {
+        self.epoch.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+/// Clock backed by the global `performance` object
+///
+/// Falls back to `Date.now()` when the global scope has no `performance`.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone)]
+pub struct PerformanceClock {
+    performance: Option<web_sys::Performance>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl PerformanceClock {
+    pub fn new() -> Self {
+        use wasm_bindgen::JsCast;
+
+        let performance = js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+            .ok()
+            .filter(|value| value.is_object())
+            .map(|value| value.unchecked_into::<web_sys::Performance>());
+
+        PerformanceClock { performance }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Default for PerformanceClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Clock for PerformanceClock {
+    fn now_ms(&self) -> f64 {
+        match &self.performance {
+            Some(performance) => performance.now(),
+            None => js_sys::Date::now(),
+        }
+    }
+}
+
+/// Clock used when no clock is supplied
+#[cfg(not(target_arch = "wasm32"))]
+pub type DefaultClock = InstantClock;
+
+/// Clock used when no clock is supplied
+#[cfg(target_arch = "wasm32")]
+pub type DefaultClock = PerformanceClock;
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instant_clock_is_monotonic() {
+        let clock = InstantClock::new();
+        let first = clock.now_ms();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = clock.now_ms();
+        assert!(first >= 0.0);
+        assert!(second > first);
+    }
+}
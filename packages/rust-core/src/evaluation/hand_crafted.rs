@@ -0,0 +1,438 @@
+use crate::shogi::{
+    parse_player, piece_attacks, position_from_json, step_attacks, Bitboard, Piece, PieceType,
+    Player, Position, Square,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+// 盤上の駒の価値（歩 = 100、成り駒は成った後の価値）
+const PIECE_VALUES: [i32; 14] = [
+    100,  // 歩
+    430,  // 香
+    450,  // 桂
+    640,  // 銀
+    690,  // 金
+    890,  // 角
+    1040, // 飛
+    0,    // 玉
+    520,  // と
+    690,  // 成香
+    690,  // 成桂
+    690,  // 成銀
+    1040, // 馬
+    1190, // 龍
+];
+
+// 持ち駒の価値（盤上の 9 割、web の評価関数と同じ）
+const HAND_VALUES: [i32; 7] = [90, 387, 405, 576, 621, 801, 936];
+
+// 玉の周囲 8 マスにいる味方の駒（金・成り駒、銀、その他）
+const GOLD_DEFENDER_BONUS: i32 = 30;
+const SILVER_DEFENDER_BONUS: i32 = 20;
+const DEFENDER_BONUS: i32 = 10;
+// 玉とその周囲のマスへの相手の利き 1 つあたり
+const KING_ZONE_ATTACK_PENALTY: i32 = 15;
+
+// 駒の位置の評価（先手から見た値、index = (段 - 1) * 9 + (筋 - 1)）
+//
+// 左右対称なので筋の向きは問わない。後手は盤を 180 度回して引く
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+     15,  15,  15,  20,  25,  20,  15,  15,  15,
+     10,  10,  10,  15,  20,  15,  10,  10,  10,
+      5,   5,   5,  10,  15,  10,   5,   5,   5,
+      0,   0,   0,   5,  10,   5,   0,   0,   0,
+      0,   0,   0,   0,   5,   0,   0,   0,   0,
+     -5,  -5,  -5,  -5,  -5,  -5,  -5,  -5,  -5,
+    -10, -10, -10, -10, -10, -10, -10, -10, -10,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const LANCE_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+     10,   5,   5,   5,   5,   5,   5,   5,  10,
+      5,   0,   0,   0,   0,   0,   0,   0,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+     -5,  -5,  -5,  -5,  -5,  -5,  -5,  -5,  -5,
+    -10, -10, -10, -10, -10, -10, -10, -10, -10,
+    -15, -15, -15, -15, -15, -15, -15, -15, -15,
+    -20, -20, -20, -20, -20, -20, -20, -20, -20,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+     10,  15,  20,  20,  20,  20,  20,  15,  10,
+      5,  10,  15,  15,  15,  15,  15,  10,   5,
+      0,   5,  10,  10,  10,  10,  10,   5,   0,
+     -5,   0,   5,   5,   5,   5,   5,   0,  -5,
+    -10,  -5,   0,   0,   0,   0,   0,  -5, -10,
+    -15, -10,  -5,  -5,  -5,  -5,  -5, -10, -15,
+    -20, -15, -10, -10, -10, -10, -10, -15, -20,
+    -30, -30, -30, -30, -30, -30, -30, -30, -30,
+];
+
+#[rustfmt::skip]
+const SILVER_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+     10,  15,  15,  15,  15,  15,  15,  15,  10,
+     10,  20,  20,  20,  20,  20,  20,  20,  10,
+      5,  15,  25,  25,  25,  25,  25,  15,   5,
+      0,  10,  20,  20,  20,  20,  20,  10,   0,
+     -5,   5,  10,  10,  10,  10,  10,   5,  -5,
+    -10,   0,   5,   5,   5,   5,   5,   0, -10,
+    -15,  -5,   0,   0,   0,   0,   0,  -5, -15,
+    -20, -10,  -5,  -5,  -5,  -5,  -5, -10, -20,
+];
+
+// 金と、金と同じ動きの成り駒
+#[rustfmt::skip]
+const GOLD_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,  10,   5,
+      5,  10,  15,  15,  15,  15,  15,  10,   5,
+      0,   5,  10,  10,  10,  10,  10,   5,   0,
+      0,   5,  10,  10,  10,  10,  10,   5,   0,
+      0,   5,  10,  10,  10,  10,  10,   5,   0,
+      5,  10,  15,  15,  15,  15,  15,  10,   5,
+     10,  15,  20,  20,  20,  20,  20,  15,  10,
+     15,  20,  25,  25,  25,  25,  25,  20,  15,
+];
+
+// 角と馬
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,  10,   5,   5,   5,   5,   5,  10,   0,
+      0,   5,  15,  10,  10,  10,  15,   5,   0,
+      0,   5,  10,  20,  15,  20,  10,   5,   0,
+      0,   5,  10,  15,  25,  15,  10,   5,   0,
+      0,   5,  10,  20,  15,  20,  10,   5,   0,
+      0,   5,  15,  10,  10,  10,  15,   5,   0,
+      0,  10,   5,   5,   5,   5,   5,  10,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+// 飛車と龍
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 81] = [
+      5,   5,   5,  10,  10,  10,   5,   5,   5,
+     10,  10,  10,  15,  15,  15,  10,  10,  10,
+      0,   0,   0,   5,   5,   5,   0,   0,   0,
+      0,   0,   0,   5,   5,   5,   0,   0,   0,
+      0,   0,   0,   5,   5,   5,   0,   0,   0,
+      0,   0,   0,   5,   5,   5,   0,   0,   0,
+      0,   0,   0,   5,   5,   5,   0,   0,   0,
+     20,  20,  20,  25,  25,  25,  20,  20,  20,
+     25,  25,  25,  30,  30,  30,  25,  25,  25,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 81] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,
+      5,   5,   5,   0,   0,   0,   5,   5,   5,
+     10,  10,  10,   5,   5,   5,  10,  10,  10,
+     20,  30,  20,  10,  10,  10,  20,  30,  20,
+];
+
+// 盤上の駒の価値
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    PIECE_VALUES[piece_type.index()]
+}
+
+// 持ち駒の価値（玉は 0）
+pub fn hand_value(piece_type: PieceType) -> i32 {
+    piece_type.hand_index().map_or(0, |index| HAND_VALUES[index])
+}
+
+// 片方の玉の安全度の内訳
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct KingSafety {
+    // 玉の周囲 8 マスにいる味方の駒の数
+    pub defenders: u32,
+    // 玉とその周囲のマスへの相手の利きの数（同じマスへの複数の利きも数える）
+    pub attacks: u32,
+    // この玉の持ち主から見た点数
+    pub score: i32,
+}
+
+// 評価値の内訳（先手から見た値、total は各項目の和）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct EvalBreakdown {
+    // 盤上の駒の価値
+    pub board_material: i32,
+    // 持ち駒の価値
+    pub hand_material: i32,
+    // 駒の位置
+    pub piece_square: i32,
+    // 玉の安全度（先手の玉の点数 - 後手の玉の点数）
+    pub king_safety: i32,
+    // 駒の利きのあるマスの数（飛角と馬龍は 2 倍）
+    pub mobility: i32,
+    pub total: i32,
+    pub black_king: KingSafety,
+    pub white_king: KingSafety,
+}
+
+// 先手から見た評価値の内訳
+pub fn evaluate_breakdown(position: &Position) -> EvalBreakdown {
+    let mut breakdown = EvalBreakdown::default();
+    let occupied = position.occupied();
+    let zones = [Player::Black, Player::White].map(|player| king_zone(position, player));
+    let mut kings = [KingSafety::default(); 2];
+
+    for (square, piece) in position.pieces() {
+        let sign = sign(piece.owner);
+        breakdown.board_material += sign * piece_value(piece.piece_type);
+        breakdown.piece_square += sign * piece_square_value(piece, square);
+
+        let attacks = piece_attacks(piece, square, occupied);
+        breakdown.mobility += sign
+            * mobility_weight(piece.piece_type)
+            * (attacks & !position.occupied_by(piece.owner)).count() as i32;
+        if let Some(zone) = zones[piece.owner.opponent().index()] {
+            kings[piece.owner.opponent().index()].attacks += (attacks & zone).count();
+        }
+    }
+
+    for player in [Player::Black, Player::White] {
+        for (piece_type, count) in position.hand(player).iter() {
+            breakdown.hand_material += sign(player) * hand_value(piece_type) * count as i32;
+        }
+
+        let king = &mut kings[player.index()];
+        if let Some(square) = position.king_square(player) {
+            let (defenders, bonus) = defenders(position, player, square);
+            king.defenders = defenders;
+            king.score = bonus - KING_ZONE_ATTACK_PENALTY * king.attacks as i32;
+        }
+        breakdown.king_safety += sign(player) * king.score;
+    }
+
+    [breakdown.black_king, breakdown.white_king] = kings;
+    breakdown.total = breakdown.board_material
+        + breakdown.hand_material
+        + breakdown.piece_square
+        + breakdown.king_safety
+        + breakdown.mobility;
+    breakdown
+}
+
+// 手番側から見た評価値（探索用）
+pub fn evaluate(position: &Position) -> i32 {
+    sign(position.side_to_move()) * evaluate_breakdown(position).total
+}
+
+// SFEN の局面の評価値の内訳を JSON で返す（UI の表示用）
+#[wasm_bindgen]
+pub fn evaluate_sfen(sfen: &str) -> Result<String, JsValue> {
+    let position = Position::from_sfen(sfen)
+        .map_err(|e| JsValue::from_str(&format!("Invalid SFEN: {e:#}")))?;
+
+    breakdown_json(&position)
+}
+
+// 盤面データを JSON で受け取り、評価値の内訳を JSON で返す
+// スキーマは crate::shogi::json を参照
+#[wasm_bindgen]
+pub fn evaluate_json(board_json: &str, hands_json: &str, player: &str) -> Result<String, JsValue> {
+    let position = parse_player(player)
+        .and_then(|player| position_from_json(board_json, hands_json, player))
+        .map_err(|e| JsValue::from_str(&format!("{e:#}")))?;
+
+    breakdown_json(&position)
+}
+
+fn breakdown_json(position: &Position) -> Result<String, JsValue> {
+    serde_json::to_string(&evaluate_breakdown(position))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn sign(player: Player) -> i32 {
+    match player {
+        Player::Black => 1,
+        Player::White => -1,
+    }
+}
+
+fn piece_square_value(piece: Piece, square: Square) -> i32 {
+    let table = match piece.piece_type {
+        PieceType::Pawn => &PAWN_TABLE,
+        PieceType::Lance => &LANCE_TABLE,
+        PieceType::Knight => &KNIGHT_TABLE,
+        PieceType::Silver => &SILVER_TABLE,
+        PieceType::Gold
+        | PieceType::ProPawn
+        | PieceType::ProLance
+        | PieceType::ProKnight
+        | PieceType::ProSilver => &GOLD_TABLE,
+        PieceType::Bishop | PieceType::Horse => &BISHOP_TABLE,
+        PieceType::Rook | PieceType::Dragon => &ROOK_TABLE,
+        PieceType::King => &KING_TABLE,
+    };
+    let index = (square.rank() as usize - 1) * 9 + (square.file() as usize - 1);
+    match piece.owner {
+        Player::Black => table[index],
+        Player::White => table[80 - index],
+    }
+}
+
+fn mobility_weight(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Bishop | PieceType::Rook | PieceType::Horse | PieceType::Dragon => 2,
+        _ => 1,
+    }
+}
+
+// 玉とその周囲 8 マス（玉がなければ None）
+fn king_zone(position: &Position, player: Player) -> Option<Bitboard> {
+    let square = position.king_square(player)?;
+    Some(step_attacks(Piece::new(PieceType::King, player), square) | Bitboard::from_square(square))
+}
+
+// 玉の周囲 8 マスにいる味方の駒の数とその点数
+fn defenders(position: &Position, player: Player, king: Square) -> (u32, i32) {
+    let around =
+        step_attacks(Piece::new(PieceType::King, player), king) & position.occupied_by(player);
+    let bonus = around
+        .into_iter()
+        .map(|square| match position.piece_at(square).map(|piece| piece.piece_type) {
+            Some(PieceType::Gold)
+            | Some(PieceType::ProPawn)
+            | Some(PieceType::ProLance)
+            | Some(PieceType::ProKnight)
+            | Some(PieceType::ProSilver)
+            | Some(PieceType::Horse)
+            | Some(PieceType::Dragon) => GOLD_DEFENDER_BONUS,
+            Some(PieceType::Silver) => SILVER_DEFENDER_BONUS,
+            _ => DEFENDER_BONUS,
+        })
+        .sum();
+    (around.count(), bonus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakdown(sfen: &str) -> EvalBreakdown {
+        evaluate_breakdown(&Position::from_sfen(sfen).unwrap())
+    }
+
+    // 盤を 180 度回して先後を入れ替えた局面
+    fn flipped(position: &Position) -> Position {
+        let mut flipped = Position::empty();
+        for (square, piece) in position.pieces() {
+            let square = Square::new(10 - square.file(), 10 - square.rank()).unwrap();
+            flipped.set_piece(square, Some(Piece::new(piece.piece_type, piece.owner.opponent())));
+        }
+        for player in [Player::Black, Player::White] {
+            for (piece_type, count) in position.hand(player).iter() {
+                flipped.set_hand_count(player.opponent(), piece_type, count);
+            }
+        }
+        flipped.set_side_to_move(position.side_to_move().opponent());
+        flipped
+    }
+
+    #[test]
+    fn test_startpos_is_balanced() {
+        let breakdown =
+            breakdown("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1");
+        assert_eq!(breakdown.total, 0);
+        assert_eq!(breakdown.black_king, breakdown.white_king);
+        assert_eq!(breakdown.black_king.defenders, 2);
+    }
+
+    #[test]
+    fn test_total_is_sum_of_terms() {
+        let breakdown =
+            breakdown("ln1g3nl/1r1sk1g2/p1pppp1pp/6R2/1p7/2P6/PPSPPPP1P/1BG4+b1/LN2KGSNL b SPp 1");
+        assert_eq!(
+            breakdown.total,
+            breakdown.board_material
+                + breakdown.hand_material
+                + breakdown.piece_square
+                + breakdown.king_safety
+                + breakdown.mobility
+        );
+    }
+
+    #[test]
+    fn test_material_counts_hands_and_promotions() {
+        let bare = breakdown("4k4/9/9/9/9/9/9/9/4K4 b - 1");
+        assert_eq!(bare.board_material, 0);
+
+        let hands = breakdown("4k4/9/9/9/9/9/9/9/4K4 b RP2g 1");
+        assert_eq!(hands.board_material, 0);
+        assert_eq!(hands.hand_material, 936 + 90 - 2 * 621);
+
+        let dragon = breakdown("4k4/9/9/9/9/9/9/9/+R3K4 b - 1");
+        let rook = breakdown("4k4/9/9/9/9/9/9/9/R3K4 b - 1");
+        assert_eq!(dragon.board_material - rook.board_material, 150);
+    }
+
+    #[test]
+    fn test_king_safety_counts_defenders_and_attacks() {
+        let guarded = breakdown("4k4/9/9/9/9/9/9/3GSG3/4K4 b - 1");
+        assert_eq!(guarded.black_king.defenders, 3);
+        assert_eq!(guarded.black_king.score, 2 * GOLD_DEFENDER_BONUS + SILVER_DEFENDER_BONUS);
+        assert_eq!(guarded.white_king.defenders, 0);
+
+        // 後手の龍が先手玉の周囲に利いている
+        let attacked = breakdown("4k4/9/9/9/9/9/9/+r8/4K4 b - 1");
+        assert!(attacked.black_king.attacks >= 3);
+        assert!(attacked.king_safety < 0);
+    }
+
+    #[test]
+    fn test_mobility_prefers_open_rook() {
+        let open = breakdown("4k4/9/9/9/4R4/9/9/9/4K4 b - 1");
+        let boxed = breakdown("4k4/9/9/9/9/9/9/PP7/RL2K4 b - 1");
+        assert!(open.mobility > boxed.mobility);
+    }
+
+    #[test]
+    fn test_evaluation_is_symmetric() {
+        let sfens = [
+            "ln1g3nl/1r1sk1g2/p1pppp1pp/6R2/1p7/2P6/PPSPPPP1P/1BG4+b1/LN2KGSNL b SPp 1",
+            "4k4/9/9/9/9/9/9/+r8/4K4 b G 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 2",
+        ];
+        for sfen in sfens {
+            let position = Position::from_sfen(sfen).unwrap();
+            let flipped = flipped(&position);
+            assert_eq!(
+                evaluate_breakdown(&position).total,
+                -evaluate_breakdown(&flipped).total,
+                "{sfen}"
+            );
+            assert_eq!(evaluate(&position), evaluate(&flipped), "{sfen}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_is_from_side_to_move() {
+        let black = Position::from_sfen("4k4/9/9/9/9/9/9/9/4K4 b R 1").unwrap();
+        let white = Position::from_sfen("4k4/9/9/9/9/9/9/9/4K4 w R 1").unwrap();
+        assert!(evaluate(&black) > 0);
+        assert_eq!(evaluate(&white), -evaluate(&black));
+    }
+
+    #[test]
+    fn test_evaluate_sfen_returns_json() {
+        let json = evaluate_sfen("4k4/9/9/9/9/9/9/9/4K4 b R 1").unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["hand_material"], 936);
+        assert_eq!(value["black_king"]["defenders"], 0);
+        assert!(value["total"].as_i64().unwrap() > 0);
+    }
+}
@@ -0,0 +1,8 @@
+// Evaluation Module
+pub mod hand_crafted;
+pub mod nnue;
+mod nnue_simd;
+
+// Re-export for easier access
+pub use hand_crafted::*;
+pub use nnue::*;
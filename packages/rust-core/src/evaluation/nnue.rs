@@ -0,0 +1,668 @@
+use crate::evaluation::nnue_simd::{add_row, dot, sub_row};
+use crate::shogi::{Move, Piece, PieceType, Player, Position, Square};
+use anyhow::{anyhow, Context, Result};
+use std::io::Read;
+use std::sync::Arc;
+
+// YaneuraOu 互換の NNUE 評価関数（halfKP 256x2-32-32）
+//
+// 入力は「自玉の位置 × 玉以外の駒 1 つ（BonaPiece）」の組を先手・後手それぞれの
+// 視点で数えたもの。視点ごとの 256 次元の和（アキュムレータ）を、指した手で
+// 変わった駒の分だけ足し引きして持ち回り、玉が動いた視点だけ作り直す。
+//
+// ファイル形式（すべてリトルエンディアン）
+//   u32 バージョン、u32 ハッシュ、u32 長さ + 構造を表す文字列
+//   u32 ハッシュ、i16 バイアス [256]、i16 重み [125388][256]   （特徴量変換）
+//   u32 ハッシュ、(i32 バイアス [出力]、i8 重み [出力][入力]) × 3（512→32→32→1）
+
+pub const NNUE_VERSION: u32 = 0x7AF3_2F16;
+// 視点ごとのアキュムレータの次元
+pub const NNUE_HALF_DIMENSIONS: usize = 256;
+// 特徴量の数（玉の位置 × BonaPiece）
+pub const NNUE_INPUT_DIMENSIONS: usize = Square::NUM * FE_END;
+// ヘッダのハッシュ（halfKP 256x2-32-32 なら 0x3E5AA6EE）
+pub const NNUE_HASH: u32 = FEATURE_TRANSFORMER_HASH ^ NETWORK_HASH;
+
+// BonaPiece の数（持ち駒と盤上の駒、玉は含まない）
+const FE_END: usize = 1548;
+const HIDDEN_DIMENSIONS: usize = 32;
+// 中間層の固定小数点のビット数
+const WEIGHT_SCALE_BITS: u32 = 6;
+// 出力層の値を評価値（歩 ≒ 100）に直す割り算
+const FV_SCALE: i32 = 16;
+
+// 各部分のハッシュ（YaneuraOu と同じ計算。構造の違うファイルを読まないため）
+const FEATURE_TRANSFORMER_HASH: u32 = (0x5D69_D5B9 ^ 1) ^ (2 * NNUE_HALF_DIMENSIONS) as u32;
+const NETWORK_HASH: u32 = {
+    let input_slice = 0xEC42_E90D ^ (2 * NNUE_HALF_DIMENSIONS) as u32;
+    let hidden1 = clipped_relu_hash(affine_hash(input_slice, HIDDEN_DIMENSIONS as u32));
+    let hidden2 = clipped_relu_hash(affine_hash(hidden1, HIDDEN_DIMENSIONS as u32));
+    affine_hash(hidden2, 1)
+};
+
+const fn affine_hash(previous: u32, outputs: u32) -> u32 {
+    0xCC03_DAE4u32.wrapping_add(outputs) ^ (previous >> 1) ^ (previous << 31)
+}
+
+const fn clipped_relu_hash(previous: u32) -> u32 {
+    0x538D_24C7u32.wrapping_add(previous)
+}
+
+// 盤上の駒の BonaPiece の先頭（味方の駒。相手の駒はさらに +81）
+// と・成香・成桂・成銀は金と同じ扱い
+const BOARD_BASE: [usize; 14] = [
+    90,   // 歩
+    252,  // 香
+    414,  // 桂
+    576,  // 銀
+    738,  // 金
+    900,  // 角
+    1224, // 飛
+    0,    // 玉（特徴量に含まない）
+    738,  // と
+    738,  // 成香
+    738,  // 成桂
+    738,  // 成銀
+    1062, // 馬
+    1386, // 龍
+];
+
+// 持ち駒の BonaPiece の先頭（味方、相手）。n 枚目（0 始まり）は + n
+const HAND_BASE: [(usize, usize); 7] = [
+    (1, 20),  // 歩
+    (39, 44), // 香
+    (49, 54), // 桂
+    (59, 64), // 銀
+    (69, 74), // 金
+    (79, 82), // 角
+    (85, 88), // 飛
+];
+
+// 玉以外の駒 1 つ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Feature {
+    Board(Piece, Square),
+    // 持ち主、駒の種類、何枚目か（0 始まり）
+    Hand(Player, PieceType, u8),
+}
+
+impl Feature {
+    // perspective の視点での特徴量の番号（king はその視点の玉の位置）
+    fn index(self, perspective: Player, king: Square) -> usize {
+        let bona_piece = match self {
+            Feature::Board(piece, square) => {
+                let enemy = if piece.owner == perspective {
+                    0
+                } else {
+                    Square::NUM
+                };
+                BOARD_BASE[piece.piece_type.index()] + enemy + relative_index(square, perspective)
+            }
+            Feature::Hand(owner, piece_type, nth) => {
+                let (friend, enemy) = HAND_BASE[piece_type.hand_index().expect("king in hand")];
+                let base = if owner == perspective { friend } else { enemy };
+                base + nth as usize
+            }
+        };
+        relative_index(king, perspective) * FE_END + bona_piece
+    }
+}
+
+// 後手の視点では盤を 180 度回す
+fn relative_index(square: Square, perspective: Player) -> usize {
+    match perspective {
+        Player::Black => square.index(),
+        Player::White => Square::NUM - 1 - square.index(),
+    }
+}
+
+// 局面にある全ての特徴量
+fn active_features(position: &Position) -> impl Iterator<Item = Feature> + '_ {
+    let board = position
+        .pieces()
+        .filter(|(_, piece)| piece.piece_type != PieceType::King)
+        .map(|(square, piece)| Feature::Board(piece, square));
+    let hands = Player::ALL.into_iter().flat_map(move |owner| {
+        position.hand(owner).iter().flat_map(move |(piece_type, count)| {
+            (0..count).map(move |nth| Feature::Hand(owner, piece_type, nth))
+        })
+    });
+    board.chain(hands)
+}
+
+// 1 手で消える特徴量と増える特徴量（取る手なら 2 つずつ、それ以外は 1 つずつ）
+struct FeatureDiff {
+    removed: [Option<Feature>; 2],
+    added: [Option<Feature>; 2],
+    // 玉が動いた視点はアキュムレータを作り直す
+    king_moved: Option<Player>,
+}
+
+impl FeatureDiff {
+    // 指す前の局面で求める
+    fn new(position: &Position, mv: Move) -> Self {
+        let us = position.side_to_move();
+        let mut diff = FeatureDiff {
+            removed: [None; 2],
+            added: [None; 2],
+            king_moved: None,
+        };
+
+        match mv {
+            Move::Normal { from, to, promote } => {
+                let piece = position.piece_at(from).expect("no piece on move origin");
+                if piece.piece_type == PieceType::King {
+                    diff.king_moved = Some(us);
+                } else {
+                    let piece_type = if promote {
+                        piece.piece_type.promote().expect("piece cannot promote")
+                    } else {
+                        piece.piece_type
+                    };
+                    diff.removed[0] = Some(Feature::Board(piece, from));
+                    diff.added[0] = Some(Feature::Board(Piece::new(piece_type, us), to));
+                }
+                if let Some(captured) = position.piece_at(to) {
+                    let hand_type = captured.piece_type.unpromote();
+                    let nth = position.hand(us).count(hand_type);
+                    diff.removed[1] = Some(Feature::Board(captured, to));
+                    diff.added[1] = Some(Feature::Hand(us, hand_type, nth));
+                }
+            }
+            Move::Drop { piece_type, to } => {
+                let nth = position.hand(us).count(piece_type) - 1;
+                diff.removed[0] = Some(Feature::Hand(us, piece_type, nth));
+                diff.added[0] = Some(Feature::Board(Piece::new(piece_type, us), to));
+            }
+        }
+        diff
+    }
+}
+
+// 視点（先手、後手）ごとの特徴量変換の出力
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Accumulator {
+    values: [[i16; NNUE_HALF_DIMENSIONS]; 2],
+}
+
+// 全結合層（重みは出力ごとに入力の数だけ並ぶ）
+struct AffineLayer {
+    inputs: usize,
+    biases: Vec<i32>,
+    weights: Vec<i8>,
+}
+
+impl AffineLayer {
+    fn read(reader: &mut impl Read, inputs: usize, outputs: usize) -> Result<Self> {
+        let biases = read_values(reader, outputs, i32::from_le_bytes)?;
+        let weights = read_values(reader, inputs * outputs, i8::from_le_bytes)?;
+        Ok(AffineLayer {
+            inputs,
+            biases,
+            weights,
+        })
+    }
+
+    fn propagate(&self, input: &[u8], output: &mut [i32]) {
+        let rows = self.weights.chunks_exact(self.inputs);
+        for ((output, &bias), row) in output.iter_mut().zip(&self.biases).zip(rows) {
+            *output = bias + dot(input, row);
+        }
+    }
+}
+
+// 読み込んだ NNUE のパラメータ（探索スレッドの間で Arc で共有する）
+pub struct NnueNetwork {
+    architecture: String,
+    biases: Vec<i16>,
+    // [特徴量][256]
+    weights: Vec<i16>,
+    hidden1: AffineLayer,
+    hidden2: AffineLayer,
+    output: AffineLayer,
+}
+
+impl NnueNetwork {
+    // nn.bin の中身から読む
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Self::from_reader(data)
+    }
+
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let version = read_u32(&mut reader).context("Failed to read NNUE header")?;
+        if version != NNUE_VERSION {
+            return Err(anyhow!("Unsupported NNUE version: {:#010x}", version));
+        }
+        let hash = read_u32(&mut reader).context("Failed to read NNUE header")?;
+        if hash != NNUE_HASH {
+            return Err(anyhow!(
+                "Unsupported NNUE architecture: hash {:#010x}, expected {:#010x} (halfKP 256x2-32-32)",
+                hash,
+                NNUE_HASH
+            ));
+        }
+        let length = read_u32(&mut reader).context("Failed to read NNUE header")?;
+        let mut architecture = Vec::new();
+        reader
+            .by_ref()
+            .take(length as u64)
+            .read_to_end(&mut architecture)
+            .context("Failed to read NNUE header")?;
+        if architecture.len() != length as usize {
+            return Err(anyhow!("NNUE header is truncated"));
+        }
+
+        expect_hash(&mut reader, FEATURE_TRANSFORMER_HASH, "feature transformer")?;
+        let biases = read_values(&mut reader, NNUE_HALF_DIMENSIONS, i16::from_le_bytes)
+            .context("Failed to read NNUE feature transformer")?;
+        let weights = read_values(
+            &mut reader,
+            NNUE_INPUT_DIMENSIONS * NNUE_HALF_DIMENSIONS,
+            i16::from_le_bytes,
+        )
+        .context("Failed to read NNUE feature transformer")?;
+
+        expect_hash(&mut reader, NETWORK_HASH, "network")?;
+        let hidden1 = AffineLayer::read(&mut reader, 2 * NNUE_HALF_DIMENSIONS, HIDDEN_DIMENSIONS)
+            .context("Failed to read NNUE hidden layer 1")?;
+        let hidden2 = AffineLayer::read(&mut reader, HIDDEN_DIMENSIONS, HIDDEN_DIMENSIONS)
+            .context("Failed to read NNUE hidden layer 2")?;
+        let output = AffineLayer::read(&mut reader, HIDDEN_DIMENSIONS, 1)
+            .context("Failed to read NNUE output layer")?;
+
+        if reader.read(&mut [0u8; 1])? != 0 {
+            return Err(anyhow!("Unexpected data after the NNUE output layer"));
+        }
+
+        Ok(NnueNetwork {
+            architecture: String::from_utf8_lossy(&architecture).into_owned(),
+            biases,
+            weights,
+            hidden1,
+            hidden2,
+            output,
+        })
+    }
+
+    // ファイルに書かれていた構造の説明
+    pub fn architecture(&self) -> &str {
+        &self.architecture
+    }
+
+    // 差分計算を使わずに評価する（手番側から見た値。玉がどちらか欠けていれば None）
+    pub fn evaluate(&self, position: &Position) -> Option<i32> {
+        let accumulator = self.refresh(position)?;
+        Some(self.propagate(&accumulator, position.side_to_move()))
+    }
+
+    fn refresh(&self, position: &Position) -> Option<Accumulator> {
+        let mut accumulator = Accumulator {
+            values: [[0; NNUE_HALF_DIMENSIONS]; 2],
+        };
+        for perspective in Player::ALL {
+            self.refresh_half(&mut accumulator, position, perspective)?;
+        }
+        Some(accumulator)
+    }
+
+    fn refresh_half(
+        &self,
+        accumulator: &mut Accumulator,
+        position: &Position,
+        perspective: Player,
+    ) -> Option<()> {
+        let king = position.king_square(perspective)?;
+        let values = &mut accumulator.values[perspective.index()];
+        values.copy_from_slice(&self.biases);
+        for feature in active_features(position) {
+            add_row(values, self.weight_row(feature.index(perspective, king)));
+        }
+        Some(())
+    }
+
+    // 指す前のアキュムレータを指した後の局面に合わせる
+    fn update(
+        &self,
+        accumulator: &mut Accumulator,
+        position: &Position,
+        diff: &FeatureDiff,
+    ) -> Option<()> {
+        for perspective in Player::ALL {
+            if diff.king_moved == Some(perspective) {
+                self.refresh_half(accumulator, position, perspective)?;
+                continue;
+            }
+            let king = position.king_square(perspective)?;
+            let values = &mut accumulator.values[perspective.index()];
+            for feature in diff.removed.iter().flatten() {
+                sub_row(values, self.weight_row(feature.index(perspective, king)));
+            }
+            for feature in diff.added.iter().flatten() {
+                add_row(values, self.weight_row(feature.index(perspective, king)));
+            }
+        }
+        Some(())
+    }
+
+    fn weight_row(&self, index: usize) -> &[i16] {
+        &self.weights[index * NNUE_HALF_DIMENSIONS..(index + 1) * NNUE_HALF_DIMENSIONS]
+    }
+
+    // 手番側の視点を前半、相手の視点を後半に並べて 512→32→32→1 と計算する
+    fn propagate(&self, accumulator: &Accumulator, side_to_move: Player) -> i32 {
+        let mut input = [0u8; 2 * NNUE_HALF_DIMENSIONS];
+        let perspectives = [side_to_move, side_to_move.opponent()];
+        for (half, perspective) in input.chunks_exact_mut(NNUE_HALF_DIMENSIONS).zip(perspectives) {
+            for (x, &value) in half.iter_mut().zip(&accumulator.values[perspective.index()]) {
+                *x = value.clamp(0, 127) as u8;
+            }
+        }
+
+        let mut hidden1 = [0; HIDDEN_DIMENSIONS];
+        self.hidden1.propagate(&input, &mut hidden1);
+        let mut hidden2 = [0; HIDDEN_DIMENSIONS];
+        self.hidden2.propagate(&clipped_relu(&hidden1), &mut hidden2);
+        let mut output = [0; 1];
+        self.output.propagate(&clipped_relu(&hidden2), &mut output);
+
+        output[0] / FV_SCALE
+    }
+}
+
+fn clipped_relu(input: &[i32; HIDDEN_DIMENSIONS]) -> [u8; HIDDEN_DIMENSIONS] {
+    input.map(|x| (x >> WEIGHT_SCALE_BITS).clamp(0, 127) as u8)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn expect_hash(reader: &mut impl Read, expected: u32, section: &str) -> Result<()> {
+    let hash = read_u32(reader).with_context(|| format!("Failed to read NNUE {section}"))?;
+    if hash != expected {
+        return Err(anyhow!(
+            "Unexpected NNUE {} hash: {:#010x}, expected {:#010x}",
+            section,
+            hash,
+            expected
+        ));
+    }
+    Ok(())
+}
+
+// リトルエンディアンの値を count 個読む（特徴量変換の重みは 64MB あるので少しずつ）
+fn read_values<T, const N: usize>(
+    reader: &mut impl Read,
+    count: usize,
+    from_le_bytes: fn([u8; N]) -> T,
+) -> Result<Vec<T>> {
+    const CHUNK_BYTES: usize = 1 << 16;
+    let mut values = Vec::with_capacity(count);
+    let mut buffer = vec![0; CHUNK_BYTES];
+    let mut remaining = count * N;
+    while remaining > 0 {
+        let chunk = &mut buffer[..remaining.min(CHUNK_BYTES)];
+        reader.read_exact(chunk)?;
+        values.extend(
+            chunk
+                .chunks_exact(N)
+                .map(|bytes| from_le_bytes(bytes.try_into().expect("chunk of N bytes"))),
+        );
+        remaining -= chunk.len();
+    }
+    Ok(values)
+}
+
+// 探索中に差分計算で評価する
+//
+// reset で root 局面のアキュムレータを作り、do_move / undo_move で局面と
+// 一緒に進めたり戻したりする。手番を渡すだけ（null move）なら駒は変わらない
+// ので何もしなくてよい
+pub struct NnueEvaluator {
+    network: Arc<NnueNetwork>,
+    // root から今の局面までのアキュムレータ（玉が欠けている局面は None）
+    stack: Vec<Option<Accumulator>>,
+}
+
+impl NnueEvaluator {
+    pub fn new(network: Arc<NnueNetwork>) -> Self {
+        NnueEvaluator {
+            network,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn network(&self) -> &Arc<NnueNetwork> {
+        &self.network
+    }
+
+    // position から読み始める
+    pub fn reset(&mut self, position: &Position) {
+        self.stack.clear();
+        self.stack.push(self.network.refresh(position));
+    }
+
+    // position.do_move と同じ（アキュムレータも進める）
+    pub fn do_move(&mut self, position: &mut Position, mv: Move) -> Option<Piece> {
+        let diff = FeatureDiff::new(position, mv);
+        let captured = position.do_move(mv);
+
+        let next = match self.stack.last() {
+            Some(Some(previous)) => {
+                let mut accumulator = previous.clone();
+                self.network.update(&mut accumulator, position, &diff).map(|()| accumulator)
+            }
+            _ => self.network.refresh(position),
+        };
+        self.stack.push(next);
+        captured
+    }
+
+    // position.undo_move と同じ（アキュムレータも戻す）
+    pub fn undo_move(&mut self, position: &mut Position, mv: Move, captured: Option<Piece>) {
+        position.undo_move(mv, captured);
+        self.stack.pop();
+    }
+
+    // 今の局面を評価する（手番側から見た値。玉がどちらか欠けていれば None）
+    pub fn evaluate(&self, position: &Position) -> Option<i32> {
+        let accumulator = self.stack.last()?.as_ref()?;
+        Some(self.network.propagate(accumulator, position.side_to_move()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchEngine, SearchLimits};
+    use std::sync::OnceLock;
+
+    const ARCHITECTURE: &str = "Features=HalfKP(Friend)[125388->256x2],Network=AffineTransform[1<-32](ClippedReLU[32](AffineTransform[32<-32](ClippedReLU[32](AffineTransform[32<-512](InputSlice[512(0:512)])))))";
+
+    // 乱数の重みで作ったネットワーク（ファイル形式に書き出してから読む）
+    fn test_network() -> Arc<NnueNetwork> {
+        static NETWORK: OnceLock<Arc<NnueNetwork>> = OnceLock::new();
+        let network = NETWORK.get_or_init(|| {
+            let network = NnueNetwork::from_bytes(&network_bytes()).unwrap();
+            assert_eq!(network.architecture(), ARCHITECTURE);
+            Arc::new(network)
+        });
+        Arc::clone(network)
+    }
+
+    fn network_bytes() -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        // -range..=range の一様乱数（xorshift）
+        let mut random = move |range: i64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % (2 * range as u64 + 1)) as i64 - range
+        };
+
+        let mut bytes = header_bytes(NNUE_VERSION, NNUE_HASH);
+        bytes.extend(FEATURE_TRANSFORMER_HASH.to_le_bytes());
+        for _ in 0..NNUE_HALF_DIMENSIONS {
+            bytes.extend((random(32) as i16 + 32).to_le_bytes());
+        }
+        for _ in 0..NNUE_INPUT_DIMENSIONS * NNUE_HALF_DIMENSIONS {
+            bytes.extend((random(16) as i16).to_le_bytes());
+        }
+        bytes.extend(NETWORK_HASH.to_le_bytes());
+        for (inputs, outputs) in [(512, 32), (32, 32), (32, 1)] {
+            for _ in 0..outputs {
+                bytes.extend((random(2000) as i32).to_le_bytes());
+            }
+            for _ in 0..inputs * outputs {
+                bytes.extend((random(127) as i8).to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn header_bytes(version: u32, hash: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(version.to_le_bytes());
+        bytes.extend(hash.to_le_bytes());
+        bytes.extend((ARCHITECTURE.len() as u32).to_le_bytes());
+        bytes.extend(ARCHITECTURE.as_bytes());
+        bytes
+    }
+
+    // 盤を 180 度回して先後を入れ替えた局面
+    fn flipped(position: &Position) -> Position {
+        let mut flipped = Position::empty();
+        for (square, piece) in position.pieces() {
+            let square = Square::from_index(Square::NUM - 1 - square.index()).unwrap();
+            flipped.set_piece(square, Some(Piece::new(piece.piece_type, piece.owner.opponent())));
+        }
+        for player in Player::ALL {
+            for (piece_type, count) in position.hand(player).iter() {
+                flipped.set_hand_count(player.opponent(), piece_type, count);
+            }
+        }
+        flipped.set_side_to_move(position.side_to_move().opponent());
+        flipped
+    }
+
+    #[test]
+    fn test_hash_matches_yaneuraou() {
+        assert_eq!(NNUE_HASH, 0x3E5A_A6EE);
+        assert_eq!(NNUE_INPUT_DIMENSIONS, 125_388);
+    }
+
+    #[test]
+    fn test_rejects_other_formats() {
+        let error = NnueNetwork::from_bytes(&header_bytes(0x7AF3_2F17, NNUE_HASH)).err().unwrap();
+        assert!(error.to_string().contains("version"), "{error}");
+
+        // halfKPE9 など別の構造のネットワーク
+        let error =
+            NnueNetwork::from_bytes(&header_bytes(NNUE_VERSION, 0x5F13_4AB8)).err().unwrap();
+        assert!(error.to_string().contains("architecture"), "{error}");
+
+        let mut bytes = header_bytes(NNUE_VERSION, NNUE_HASH);
+        bytes.extend(FEATURE_TRANSFORMER_HASH.to_le_bytes());
+        bytes.extend([0; 100]);
+        let error = NnueNetwork::from_bytes(&bytes).err().unwrap();
+        assert!(format!("{error:#}").contains("feature transformer"), "{error:#}");
+
+        assert!(NnueNetwork::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_incremental_update_matches_refresh() {
+        let network = test_network();
+        let root = Position::from_sfen(
+            "ln1g3nl/1r1s1kg2/p1pppp1pp/1p4p2/7P1/2P6/PP1PPPP1P/1SG4R1/LN2KGSNL b Bb 1",
+        )
+        .unwrap();
+        let mut position = root.clone();
+        let mut evaluator = NnueEvaluator::new(Arc::clone(&network));
+        evaluator.reset(&position);
+
+        // 取る手・打つ手・成る手・玉の移動を含むよう、決まった順で手を選ぶ
+        let mut played = Vec::new();
+        let mut values = std::collections::HashSet::new();
+        let mut state = 7u64;
+        for _ in 0..120 {
+            let moves = position.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let captures: Vec<Move> = moves
+                .iter()
+                .copied()
+                .filter(|mv| position.piece_at(mv.to()).is_some())
+                .collect();
+            let mv = if !captures.is_empty() && state >> 63 == 1 {
+                captures[(state >> 33) as usize % captures.len()]
+            } else {
+                moves[(state >> 33) as usize % moves.len()]
+            };
+
+            let captured = evaluator.do_move(&mut position, mv);
+            played.push((mv, captured));
+            assert_eq!(evaluator.stack.last().unwrap(), &network.refresh(&position), "{mv}");
+            assert_eq!(evaluator.evaluate(&position), network.evaluate(&position));
+            values.insert(evaluator.evaluate(&position));
+        }
+        // 乱数の重みでも局面ごとに違う値になる
+        assert!(values.len() > 10, "{values:?}");
+        assert!(played.iter().any(|(_, captured)| captured.is_some()));
+        assert!(played.iter().any(|(mv, _)| matches!(mv, Move::Drop { .. })));
+
+        while let Some((mv, captured)) = played.pop() {
+            evaluator.undo_move(&mut position, mv, captured);
+        }
+        assert_eq!(position, root);
+        assert_eq!(evaluator.stack.len(), 1);
+        assert_eq!(evaluator.evaluate(&position), network.evaluate(&root));
+    }
+
+    #[test]
+    fn test_flipped_position_has_the_same_value() {
+        let network = test_network();
+        for sfen in [
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+            "ln1g3nl/1r1s1kg2/p1pppp1pp/1p4p2/7P1/2P6/PP1PPPP1P/1SG4R1/LN2KGSNL w Bb 1",
+            "4k4/9/4P4/9/9/9/9/9/4K4 b G2r2b3g4s4n4l17p 1",
+        ] {
+            let position = Position::from_sfen(sfen).unwrap();
+            let value = network.evaluate(&position).unwrap();
+            assert_eq!(network.evaluate(&flipped(&position)), Some(value), "{sfen}");
+        }
+    }
+
+    #[test]
+    fn test_missing_king_is_not_evaluated() {
+        let network = test_network();
+        let position = Position::from_sfen("4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1").unwrap();
+        assert_eq!(network.evaluate(&position), None);
+
+        let mut evaluator = NnueEvaluator::new(network);
+        evaluator.reset(&position);
+        assert_eq!(evaluator.evaluate(&position), None);
+    }
+
+    #[test]
+    fn test_search_with_network() {
+        let mut engine = SearchEngine::new(4);
+        engine.set_nnue(Some(test_network()));
+        let limits = SearchLimits {
+            max_depth: 4,
+            ..SearchLimits::default()
+        };
+
+        let position = Position::from_sfen("4k4/9/4P4/9/9/9/9/9/4K4 b G2r2b3g4s4n4l17p 1").unwrap();
+        let result = engine.search(&position, &limits);
+        assert_eq!(result.best_move(), Some("G*5b".to_string()));
+
+        let result = engine.search(&Position::startpos(), &limits);
+        assert_eq!(result.depth, 4);
+        assert!(result.best().is_some_and(|mv| Position::startpos().is_legal(mv)));
+    }
+}
@@ -0,0 +1,218 @@
+// NNUE の内側のループ（アキュムレータの加減算と全結合層の内積）
+//
+// ネイティブの x86_64 では AVX2 が使えるか実行時に調べて切り替える。
+// wasm は simd128 を有効にしてビルドしたときだけ SIMD 版になる。
+// どの実装も結果はスカラー版と完全に一致する
+
+// アキュムレータの行に重みの行を足す（桁あふれは YaneuraOu と同じく折り返す）
+pub(crate) fn add_row(values: &mut [i16], weights: &[i16]) {
+    debug_assert_eq!(values.len(), weights.len());
+
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") && values.len().is_multiple_of(16) {
+        // SAFETY: AVX2 が使えることを確認済み
+        unsafe { avx2::add_row(values, weights) };
+        return;
+    }
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    if values.len().is_multiple_of(8) {
+        wasm_simd::add_row(values, weights);
+        return;
+    }
+
+    add_row_scalar(values, weights);
+}
+
+// アキュムレータの行から重みの行を引く
+pub(crate) fn sub_row(values: &mut [i16], weights: &[i16]) {
+    debug_assert_eq!(values.len(), weights.len());
+
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") && values.len().is_multiple_of(16) {
+        // SAFETY: AVX2 が使えることを確認済み
+        unsafe { avx2::sub_row(values, weights) };
+        return;
+    }
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    if values.len().is_multiple_of(8) {
+        wasm_simd::sub_row(values, weights);
+        return;
+    }
+
+    sub_row_scalar(values, weights);
+}
+
+// 入力（0-127）と 1 行分の重みの内積
+//
+// 入力が 127 以下なので、隣り合う 2 項の和は i16 に収まる（AVX2 の
+// maddubs が飽和しない）
+pub(crate) fn dot(input: &[u8], weights: &[i8]) -> i32 {
+    debug_assert_eq!(input.len(), weights.len());
+    debug_assert!(input.iter().all(|&x| x <= 127));
+
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") && input.len().is_multiple_of(32) {
+        // SAFETY: AVX2 が使えることを確認済み
+        return unsafe { avx2::dot(input, weights) };
+    }
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    if input.len().is_multiple_of(16) {
+        return wasm_simd::dot(input, weights);
+    }
+
+    dot_scalar(input, weights)
+}
+
+fn add_row_scalar(values: &mut [i16], weights: &[i16]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(weight);
+    }
+}
+
+fn sub_row_scalar(values: &mut [i16], weights: &[i16]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(weight);
+    }
+}
+
+fn dot_scalar(input: &[u8], weights: &[i8]) -> i32 {
+    input.iter().zip(weights).map(|(&x, &w)| x as i32 * w as i32).sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn add_row(values: &mut [i16], weights: &[i16]) {
+        for (value, weight) in values.chunks_exact_mut(16).zip(weights.chunks_exact(16)) {
+            let v = _mm256_loadu_si256(value.as_ptr() as *const __m256i);
+            let w = _mm256_loadu_si256(weight.as_ptr() as *const __m256i);
+            _mm256_storeu_si256(value.as_mut_ptr() as *mut __m256i, _mm256_add_epi16(v, w));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn sub_row(values: &mut [i16], weights: &[i16]) {
+        for (value, weight) in values.chunks_exact_mut(16).zip(weights.chunks_exact(16)) {
+            let v = _mm256_loadu_si256(value.as_ptr() as *const __m256i);
+            let w = _mm256_loadu_si256(weight.as_ptr() as *const __m256i);
+            _mm256_storeu_si256(value.as_mut_ptr() as *mut __m256i, _mm256_sub_epi16(v, w));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn dot(input: &[u8], weights: &[i8]) -> i32 {
+        let ones = _mm256_set1_epi16(1);
+        let mut sum = _mm256_setzero_si256();
+        for (x, w) in input.chunks_exact(32).zip(weights.chunks_exact(32)) {
+            let x = _mm256_loadu_si256(x.as_ptr() as *const __m256i);
+            let w = _mm256_loadu_si256(w.as_ptr() as *const __m256i);
+            // u8 * i8 を 2 つずつ i16 に、さらに 2 つずつ i32 に足し込む
+            let product = _mm256_madd_epi16(_mm256_maddubs_epi16(x, w), ones);
+            sum = _mm256_add_epi32(sum, product);
+        }
+
+        let sum = _mm_add_epi32(_mm256_castsi256_si128(sum), _mm256_extracti128_si256(sum, 1));
+        let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+        let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+        _mm_cvtsi128_si32(sum)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm_simd {
+    use std::arch::wasm32::*;
+
+    pub(super) fn add_row(values: &mut [i16], weights: &[i16]) {
+        for (value, weight) in values.chunks_exact_mut(8).zip(weights.chunks_exact(8)) {
+            // SAFETY: どちらも 8 要素（16 バイト）のスライス
+            unsafe {
+                let v = v128_load(value.as_ptr() as *const v128);
+                let w = v128_load(weight.as_ptr() as *const v128);
+                v128_store(value.as_mut_ptr() as *mut v128, i16x8_add(v, w));
+            }
+        }
+    }
+
+    pub(super) fn sub_row(values: &mut [i16], weights: &[i16]) {
+        for (value, weight) in values.chunks_exact_mut(8).zip(weights.chunks_exact(8)) {
+            // SAFETY: どちらも 8 要素（16 バイト）のスライス
+            unsafe {
+                let v = v128_load(value.as_ptr() as *const v128);
+                let w = v128_load(weight.as_ptr() as *const v128);
+                v128_store(value.as_mut_ptr() as *mut v128, i16x8_sub(v, w));
+            }
+        }
+    }
+
+    pub(super) fn dot(input: &[u8], weights: &[i8]) -> i32 {
+        let mut sum = i32x4_splat(0);
+        for (x, w) in input.chunks_exact(16).zip(weights.chunks_exact(16)) {
+            // SAFETY: どちらも 16 バイトのスライス
+            let (x, w) = unsafe {
+                (v128_load(x.as_ptr() as *const v128), v128_load(w.as_ptr() as *const v128))
+            };
+            // i16 に広げてから 2 つずつ i32 に足し込む
+            let low = i32x4_dot_i16x8(u16x8_extend_low_u8x16(x), i16x8_extend_low_i8x16(w));
+            let high = i32x4_dot_i16x8(u16x8_extend_high_u8x16(x), i16x8_extend_high_i8x16(w));
+            sum = i32x4_add(sum, i32x4_add(low, high));
+        }
+
+        i32x4_extract_lane::<0>(sum)
+            + i32x4_extract_lane::<1>(sum)
+            + i32x4_extract_lane::<2>(sum)
+            + i32x4_extract_lane::<3>(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // テスト用の再現できる乱数（xorshift）
+    fn random_values(seed: u64, count: usize) -> Vec<u64> {
+        let mut state = seed;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rows_match_scalar() {
+        let values: Vec<i16> = random_values(1, 256).iter().map(|&r| r as i16).collect();
+        let weights: Vec<i16> = random_values(2, 256).iter().map(|&r| r as i16).collect();
+
+        let mut simd = values.clone();
+        let mut scalar = values.clone();
+        add_row(&mut simd, &weights);
+        add_row_scalar(&mut scalar, &weights);
+        assert_eq!(simd, scalar);
+
+        sub_row(&mut simd, &weights);
+        sub_row_scalar(&mut scalar, &weights);
+        assert_eq!(simd, scalar);
+        assert_eq!(simd, values);
+    }
+
+    #[test]
+    fn test_dot_matches_scalar() {
+        for len in [32, 512] {
+            let input: Vec<u8> = random_values(3, len).iter().map(|&r| (r % 128) as u8).collect();
+            let weights: Vec<i8> = random_values(4, len).iter().map(|&r| r as i8).collect();
+            assert_eq!(dot(&input, &weights), dot_scalar(&input, &weights));
+        }
+
+        // 最も飽和しやすい組み合わせでも一致する
+        let input = vec![127u8; 64];
+        for weight in [i8::MIN, i8::MAX] {
+            let weights = vec![weight; 64];
+            assert_eq!(dot(&input, &weights), 64 * 127 * weight as i32);
+        }
+    }
+}
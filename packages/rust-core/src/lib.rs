@@ -4,10 +4,32 @@ use wasm_bindgen::prelude::*;
 mod simple_webrtc;
 pub use simple_webrtc::*;
 
+// Add shogi position model module
+pub mod shogi;
+pub use shogi::*;
+
+// Add search clock module
+pub mod clock;
+
+// Add shared helpers module
+mod util;
+
 // Add mate search module
 mod mate_search;
 pub use mate_search::*;
 
+// Add evaluation function module
+pub mod evaluation;
+pub use evaluation::*;
+
+// Add alpha-beta search module
+pub mod search;
+pub use search::*;
+
+// Add tsume problem validator module
+mod tsume_validator;
+pub use tsume_validator::*;
+
 // Add opening book module
 pub mod opening_book;
 pub use opening_book::*;
@@ -26,40 +48,6 @@ macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
 }
 
-// Dummy structs for now - will be replaced with actual game logic
-#[wasm_bindgen]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PieceType {
-    Pawn,
-}
-
-#[wasm_bindgen]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Player {
-    Black,
-    White,
-}
-
-#[wasm_bindgen]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Piece {
-    pub piece_type: PieceType,
-    pub owner: Player,
-    pub promoted: bool,
-}
-
-#[wasm_bindgen]
-impl Piece {
-    #[wasm_bindgen(constructor)]
-    pub fn new(piece_type: PieceType, owner: Player) -> Piece {
-        Piece {
-            piece_type,
-            owner,
-            promoted: false,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,7 +61,7 @@ mod tests {
         let piece = Piece::new(PieceType::Pawn, Player::Black);
         assert_eq!(piece.piece_type, PieceType::Pawn);
         assert_eq!(piece.owner, Player::Black);
-        assert!(!piece.promoted);
+        assert!(!piece.promoted());
     }
 
     #[test]
@@ -87,5 +75,7 @@ mod tests {
     fn test_piece_type_enum() {
         let pawn = PieceType::Pawn;
         assert_eq!(pawn, PieceType::Pawn);
+        assert_eq!(PieceType::ALL.len(), 14);
+        assert_eq!(pawn.promote(), Some(PieceType::ProPawn));
     }
 }
@@ -1,96 +1,124 @@
-use std::collections::HashMap;
+use crate::clock::{Clock, DefaultClock};
+use crate::shogi::{
+    moves_to_kif, parse_player, position_from_json, Move, Player, Position, ZobristKeys,
+};
+use std::collections::{HashMap, HashSet};
 use wasm_bindgen::prelude::*;
-use web_sys::Performance;
-
-// 駒の種類
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum PieceType {
-    King,   // 玉/王
-    Rook,   // 飛車
-    Bishop, // 角
-    Gold,   // 金
-    Silver, // 銀
-    Knight, // 桂
-    Lance,  // 香
-    Pawn,   // 歩
-}
 
-// プレイヤー
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Player {
-    Black,
-    White,
+// 詰み探索結果
+#[wasm_bindgen]
+pub struct MateSearchResult {
+    pub is_mate: bool,
+    pub move_count: usize,
+    pub node_count: u32,
+    pub elapsed_ms: u32,
+    moves: Vec<String>,
+    kif_moves: Vec<String>,
 }
 
-impl Player {
-    fn opponent(&self) -> Player {
-        match self {
-            Player::Black => Player::White,
-            Player::White => Player::Black,
+impl MateSearchResult {
+    fn new(position: &Position, moves: &[Move], node_count: u32, elapsed_ms: u32) -> Self {
+        MateSearchResult {
+            is_mate: !moves.is_empty(),
+            move_count: moves.len(),
+            node_count,
+            elapsed_ms,
+            moves: moves.iter().map(|mv| mv.to_usi()).collect(),
+            kif_moves: moves_to_kif(position, moves),
         }
     }
 }
 
-// 駒
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Piece {
-    pub piece_type: PieceType,
-    pub owner: Player,
-    pub promoted: bool,
+#[wasm_bindgen]
+impl MateSearchResult {
+    // 詰み手順（USI形式、例: "G*5b"）
+    #[wasm_bindgen(getter)]
+    pub fn moves(&self) -> Vec<String> {
+        self.moves.clone()
+    }
+
+    // 詰み手順（KIF形式、例: "５二金打"）
+    #[wasm_bindgen(getter)]
+    pub fn kif_moves(&self) -> Vec<String> {
+        self.kif_moves.clone()
+    }
 }
 
-// マス目
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Square {
-    pub row: u8,    // 1-9
-    pub column: u8, // 1-9
+// 探索アルゴリズム
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MateSearchAlgorithm {
+    // 反復深化の AND/OR 探索（最短手順を保証、短手数向け）
+    Naive,
+    // 置換表付き df-pn（長手数向け）。詰みが見つかるたびに手数の上限を縮めて
+    // 再探索するので、探索を終えれば最短手順を返す（時間切れならそれまでの最短）
+    DfPn,
 }
 
-impl Square {
-    pub fn new(row: u8, column: u8) -> Option<Square> {
-        if (1..=9).contains(&row) && (1..=9).contains(&column) {
-            Some(Square { row, column })
-        } else {
-            None
-        }
-    }
+// df-pn の証明数・反証数の無限大
+const DFPN_INF: u32 = u32::MAX / 2;
 
-    pub fn to_key(self) -> String {
-        format!("{}{}", self.row, self.column)
-    }
-}
+// df-pn 置換表の最大エントリ数（超えたらクリアして探索を続ける）
+const DFPN_TABLE_LIMIT: usize = 1 << 20;
 
-// 手
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Move {
-    Normal {
-        from: Square,
-        to: Square,
-        promote: bool,
-    },
-    Drop {
-        piece_type: PieceType,
-        to: Square,
-    },
+// df-pn 置換表のエントリ
+#[derive(Debug, Clone, Copy)]
+struct DfPnEntry {
+    pn: u32,
+    dn: u32,
+    // 証明済み局面の詰み手数（攻め方は最短、受け方は最長の応手で数える）
+    mate_len: u16,
 }
 
-// 盤面
-pub type Board = HashMap<Square, Piece>;
+// df-pn 置換表の局面ごとの記録（キーは局面のみ）
+//
+// 証明と反証は残り手数をまたいで使える。mate_len 手の詰みはそれ以上の残り手数でも、
+// depth 手での不詰はそれ以下の残り手数でも成り立つ。途中の証明数・反証数は
+// 同じ残り手数の探索でだけ使う
+#[derive(Debug, Clone, Copy, Default)]
+struct DfPnSlot {
+    // 見つかった最短の詰み手数
+    proof: Option<u16>,
+    // 不詰が示された最大の残り手数
+    disproof: Option<u8>,
+    // 探索途中の値と、その残り手数
+    working: Option<(u8, DfPnEntry)>,
+}
 
-// 持ち駒
-pub type Hand = HashMap<PieceType, u8>;
-pub struct Hands {
-    pub black: Hand,
-    pub white: Hand,
+impl DfPnSlot {
+    // 残り depth 手の探索で使える値
+    fn probe(&self, depth: u8) -> Option<DfPnEntry> {
+        if let Some(mate_len) = self.proof.filter(|&len| len <= depth as u16) {
+            return Some(DfPnEntry {
+                mate_len,
+                ..DfPnEntry::PROVEN
+            });
+        }
+        if self.disproof.is_some_and(|disproved| disproved >= depth) {
+            return Some(DfPnEntry::DISPROVEN);
+        }
+        self.working
+            .filter(|(working_depth, _)| *working_depth == depth)
+            .map(|(_, entry)| entry)
+    }
 }
 
-// 詰み探索結果
-#[wasm_bindgen]
-pub struct MateSearchResult {
-    pub is_mate: bool,
-    pub move_count: usize,
-    pub node_count: u32,
-    pub elapsed_ms: u32,
+impl DfPnEntry {
+    const UNKNOWN: DfPnEntry = DfPnEntry {
+        pn: 1,
+        dn: 1,
+        mate_len: 0,
+    };
+    const PROVEN: DfPnEntry = DfPnEntry {
+        pn: 0,
+        dn: DFPN_INF,
+        mate_len: 0,
+    };
+    const DISPROVEN: DfPnEntry = DfPnEntry {
+        pn: DFPN_INF,
+        dn: 0,
+        mate_len: 0,
+    };
 }
 
 // 詰み探索エンジン
@@ -98,37 +126,66 @@ pub struct MateSearchEngine {
     node_count: u32,
     start_time: f64,
     timeout_ms: u32,
-    performance: Performance,
+    timed_out: bool,
+    node_limit: u32,
+    dfpn_table: HashMap<u64, DfPnSlot>,
+    dfpn_path: HashSet<u64>,
+    clock: Box<dyn Clock>,
 }
 
 impl MateSearchEngine {
+    // プラットフォーム既定の時計を使う（ネイティブは Instant、wasm は performance）
     pub fn new(timeout_ms: u32) -> Self {
-        let window = web_sys::window().expect("window should exist");
-        let performance = window.performance().expect("performance should exist");
+        Self::with_clock(timeout_ms, Box::new(DefaultClock::default()))
+    }
+
+    // 時計を指定して作成（CLI やテストで計時方法を差し替える）
+    pub fn with_clock(timeout_ms: u32, clock: Box<dyn Clock>) -> Self {
+        let start_time = clock.now_ms();
 
         MateSearchEngine {
             node_count: 0,
-            start_time: performance.now(),
+            start_time,
             timeout_ms,
-            performance,
+            timed_out: false,
+            node_limit: u32::MAX,
+            dfpn_table: HashMap::new(),
+            dfpn_path: HashSet::new(),
+            clock,
         }
     }
 
-    // 詰み探索メイン関数
-    pub fn search(
-        &mut self,
-        board: &Board,
-        hands: &Hands,
-        attacker: Player,
-        max_depth: u8,
-    ) -> (bool, Vec<Move>) {
+    // 探索ノード数
+    pub fn node_count(&self) -> u32 {
+        self.node_count
+    }
+
+    // 直前の探索が時間切れで打ち切られたか
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    // 探索開始時にノード数と計時をリセット
+    fn start(&mut self) {
         self.node_count = 0;
-        self.start_time = self.performance.now();
+        self.timed_out = false;
+        self.node_limit = u32::MAX;
+        self.start_time = self.now();
+    }
+
+    // 詰み探索メイン関数
+    //
+    // 手番側を攻め方として探索し、見つかった場合は受け方の最長抵抗を含む詰み手順を返す
+    pub fn search(&mut self, position: &Position, max_depth: u8) -> (bool, Vec<Move>) {
+        self.start();
+
+        // 局面は探索中に指して戻すため、作業用に複製する
+        let mut position = position.clone();
 
         // 奇数深さで探索（1手詰め、3手詰め、5手詰め...）
         for depth in (1..=max_depth).step_by(2) {
             let mut moves = Vec::new();
-            if self.search_mate(board, hands, attacker, depth, &mut moves) {
+            if self.search_mate(&mut position, depth, &mut moves) {
                 return (true, moves);
             }
 
@@ -141,46 +198,36 @@ impl MateSearchEngine {
     }
 
     // 攻め方の手番での探索
-    fn search_mate(
-        &mut self,
-        board: &Board,
-        hands: &Hands,
-        attacker: Player,
-        depth: u8,
-        moves: &mut Vec<Move>,
-    ) -> bool {
+    fn search_mate(&mut self, position: &mut Position, depth: u8, moves: &mut Vec<Move>) -> bool {
         self.node_count += 1;
 
         if self.is_timeout() {
             return false;
         }
 
-        if depth == 1 {
-            // 1手詰めの判定
-            return self.search_one_move_mate(board, hands, attacker, moves);
+        // 短い詰みを優先するため、まず1手詰めを判定
+        if self.search_one_move_mate(position, moves) {
+            return true;
         }
 
-        // 攻め方の全ての合法手を生成
-        let legal_moves = self.generate_all_moves(board, hands, attacker);
-
-        for mv in legal_moves {
-            // 手を実行
-            let (new_board, new_hands) = self.apply_move(board, hands, &mv, attacker);
-
-            moves.push(mv.clone());
+        if depth < 3 {
+            return false;
+        }
 
-            // 相手が詰んでいるかチェック
-            if self.is_checkmate(&new_board, &new_hands, attacker.opponent()) {
-                return true;
-            }
+        // 攻め方の王手を生成
+        let checks = self.generate_checks(position);
 
+        for mv in checks {
             // 受け方の応手を探索
-            if self.search_defense(&new_board, &new_hands, attacker.opponent(), depth - 1, moves) {
+            let mut line = vec![mv];
+            let captured = position.do_move(mv);
+            let is_mate = self.search_defense(position, depth - 1, &mut line);
+            position.undo_move(mv, captured);
+
+            if is_mate {
+                moves.extend(line);
                 return true;
             }
-
-            // 手を戻す
-            moves.pop();
         }
 
         false
@@ -189,9 +236,7 @@ impl MateSearchEngine {
     // 受け方の手番での探索
     fn search_defense(
         &mut self,
-        board: &Board,
-        hands: &Hands,
-        defender: Player,
+        position: &mut Position,
         depth: u8,
         moves: &mut Vec<Move>,
     ) -> bool {
@@ -201,51 +246,47 @@ impl MateSearchEngine {
             return false;
         }
 
-        // 受け方の全ての合法手を生成
-        let legal_moves = self.generate_all_moves(board, hands, defender);
+        // 受け方の王手回避手を生成
+        let legal_moves = self.generate_evasions(position);
 
         // 合法手がない場合は詰み
         if legal_moves.is_empty() {
             return true;
         }
 
-        // 全ての応手に対して詰みがあるかチェック
+        // 全ての応手に対して詰みがあるかチェックし、最も長く逃れる応手を手順に採用
+        let mut longest: Vec<Move> = Vec::new();
         for mv in legal_moves {
-            let (new_board, new_hands) = self.apply_move(board, hands, &mv, defender);
-
-            moves.push(mv.clone());
-
             // 攻め方の次の手を探索
-            let is_mate =
-                self.search_mate(&new_board, &new_hands, defender.opponent(), depth - 1, moves);
-
-            moves.pop();
+            let mut line = vec![mv];
+            let captured = position.do_move(mv);
+            let is_mate = self.search_mate(position, depth - 1, &mut line);
+            position.undo_move(mv, captured);
 
             // 詰まない応手が見つかった
             if !is_mate {
                 return false;
             }
+
+            if line.len() > longest.len() {
+                longest = line;
+            }
         }
 
         // 全ての応手で詰む
+        moves.extend(longest);
         true
     }
 
     // 1手詰めを探索
-    fn search_one_move_mate(
-        &mut self,
-        board: &Board,
-        hands: &Hands,
-        attacker: Player,
-        moves: &mut Vec<Move>,
-    ) -> bool {
-        let legal_moves = self.generate_all_moves(board, hands, attacker);
-
-        for mv in legal_moves {
-            let (new_board, new_hands) = self.apply_move(board, hands, &mv, attacker);
-
+    fn search_one_move_mate(&mut self, position: &mut Position, moves: &mut Vec<Move>) -> bool {
+        for mv in self.generate_checks(position) {
             // 相手が詰んでいるかチェック
-            if self.is_checkmate(&new_board, &new_hands, attacker.opponent()) {
+            let captured = position.do_move(mv);
+            let is_mate = self.is_checkmate(position);
+            position.undo_move(mv, captured);
+
+            if is_mate {
                 moves.push(mv);
                 return true;
             }
@@ -254,42 +295,368 @@ impl MateSearchEngine {
         false
     }
 
+    // 王手になる合法手を生成（詰将棋では攻め方は王手のみ指せる）
+    fn generate_checks(&self, position: &Position) -> Vec<Move> {
+        position.check_moves()
+    }
+
+    // 現在時刻（ミリ秒）
+    fn now(&self) -> f64 {
+        self.clock.now_ms()
+    }
+
     // タイムアウトチェック
-    fn is_timeout(&self) -> bool {
-        (self.performance.now() - self.start_time) > self.timeout_ms as f64
+    fn is_timeout(&mut self) -> bool {
+        if (self.now() - self.start_time) > self.timeout_ms as f64 {
+            self.timed_out = true;
+        }
+        self.timed_out
     }
 
-    // 合法手生成（簡略版）
-    fn generate_all_moves(&self, _board: &Board, _hands: &Hands, _player: Player) -> Vec<Move> {
-        // TODO: 実際の合法手生成を実装
-        // 現在は仮実装
-        Vec::new()
+    // タイムアウトまたはノード数の上限で探索を中断するか
+    fn should_stop(&mut self) -> bool {
+        self.node_count >= self.node_limit || self.is_timeout()
     }
 
-    // 手を適用（簡略版）
-    fn apply_move(
+    // 受け方の応手生成（王手を受ける合法手のみ。玉の移動・王手駒の取り・合駒）
+    fn generate_evasions(&self, position: &Position) -> Vec<Move> {
+        position.evasion_moves()
+    }
+
+    // 詰みチェック（手番側が王手されていて合法手がない）
+    fn is_checkmate(&self, position: &Position) -> bool {
+        position.is_checkmate()
+    }
+
+    // df-pn による詰み探索
+    //
+    // 手番側を攻め方とし、置換表を使って証明数・反証数で max_depth 手以内の詰みを探す。
+    // 詰みが見つかったら手数の上限を縮めて再探索し、時間内でより短い手順を返す。
+    // 置換表は再探索の間も残し、証明済み・反証済みの局面を使い回す
+    pub fn search_dfpn(&mut self, position: &Position, max_depth: u8) -> (bool, Vec<Move>) {
+        self.start();
+        self.dfpn_path.clear();
+        self.dfpn_table.clear();
+
+        let mut root = position.clone();
+        let attacker = root.side_to_move();
+        let hash = position_hash(&root);
+
+        let mut best = Vec::new();
+        let mut limit = max_depth;
+        while limit > 0 && !self.is_timeout() {
+            if !self.dfpn_prove(&mut root, hash, limit, attacker) {
+                break;
+            }
+
+            // 置換表がクリアされて手順を復元できなかった場合はそこで打ち切る
+            let line = self.dfpn_principal_variation(&root, limit, attacker);
+            if line.is_empty() {
+                break;
+            }
+            limit = line.len().saturating_sub(2) as u8;
+            best = line;
+        }
+
+        (!best.is_empty(), best)
+    }
+
+    // 攻め方の手のうち max_depth 手以内に詰む手を全て列挙（詰将棋の余詰検出用）
+    pub fn mating_moves(&mut self, position: &Position, max_depth: u8) -> Vec<Move> {
+        self.start();
+        self.dfpn_path.clear();
+        self.dfpn_table.clear();
+
+        let mut root = position.clone();
+        let attacker = root.side_to_move();
+        if max_depth == 0 {
+            return Vec::new();
+        }
+
+        // 各手の探索は同じ置換表を使い、初期局面へ戻る手順は千日手として扱う
+        self.dfpn_path.insert(position_hash(&root));
+        let mut mates = Vec::new();
+        for (mv, hash) in self.dfpn_children(&mut root, true) {
+            let captured = root.do_move(mv);
+            let is_mate = self.dfpn_prove(&mut root, hash, max_depth - 1, attacker);
+            root.undo_move(mv, captured);
+
+            if is_mate {
+                mates.push(mv);
+            }
+        }
+        self.dfpn_path.clear();
+        mates
+    }
+
+    // depth 手以内の詰みを証明できるか調べる（置換表はそれまでの結果を使う）
+    fn dfpn_prove(&mut self, root: &mut Position, hash: u64, depth: u8, attacker: Player) -> bool {
+        self.dfpn_mid(root, hash, depth, attacker, DFPN_INF - 1, DFPN_INF - 1);
+        self.dfpn_lookup(hash, depth).pn == 0
+    }
+
+    // 置換表を残したまま、最大 node_budget ノードだけ探索を進める
+    fn dfpn_resume(
+        &mut self,
+        root: &mut Position,
+        hash: u64,
+        depth: u8,
+        attacker: Player,
+        node_budget: u32,
+    ) -> DfPnEntry {
+        self.node_limit = self.node_count.saturating_add(node_budget);
+        self.dfpn_mid(root, hash, depth, attacker, DFPN_INF - 1, DFPN_INF - 1);
+        self.node_limit = u32::MAX;
+        self.dfpn_lookup(hash, depth)
+    }
+
+    // 閾値を超えるまで最良の子局面を展開する
+    //
+    // depth は残り手数。置換表は局面で引き、残り手数に合う値だけを使う
+    fn dfpn_mid(
+        &mut self,
+        position: &mut Position,
+        hash: u64,
+        depth: u8,
+        attacker: Player,
+        thpn: u32,
+        thdn: u32,
+    ) {
+        self.node_count += 1;
+
+        let or_node = position.side_to_move() == attacker;
+        if or_node && depth == 0 {
+            self.dfpn_store(hash, depth, DfPnEntry::DISPROVEN);
+            return;
+        }
+
+        // 攻め方は王手がなければ不詰、受け方は応手がなければ詰み
+        let children = self.dfpn_children(position, or_node);
+        if children.is_empty() {
+            let entry = if or_node {
+                DfPnEntry::DISPROVEN
+            } else {
+                DfPnEntry::PROVEN
+            };
+            self.dfpn_store(hash, depth, entry);
+            return;
+        }
+
+        // 手数を使い切った受け方の局面は、応手があれば不詰
+        if depth == 0 {
+            self.dfpn_store(hash, depth, DfPnEntry::DISPROVEN);
+            return;
+        }
+
+        // 王手の初期証明数は受け方の応手数とし、応手の少ない王手から調べる
+        if or_node {
+            for &(mv, child_hash) in &children {
+                if self.dfpn_probe(child_hash, depth - 1).is_none() {
+                    let captured = position.do_move(mv);
+                    let evasions = self.generate_evasions(position).len() as u32;
+                    position.undo_move(mv, captured);
+                    let entry = if evasions == 0 {
+                        DfPnEntry::PROVEN
+                    } else if depth == 1 {
+                        DfPnEntry::DISPROVEN
+                    } else {
+                        DfPnEntry {
+                            pn: evasions,
+                            ..DfPnEntry::UNKNOWN
+                        }
+                    };
+                    self.dfpn_store(child_hash, depth - 1, entry);
+                }
+            }
+        }
+
+        self.dfpn_path.insert(hash);
+        loop {
+            let entry = self.dfpn_collect(&children, depth - 1, or_node);
+            if entry.pn == 0
+                || entry.dn == 0
+                || entry.pn >= thpn
+                || entry.dn >= thdn
+                || self.should_stop()
+            {
+                self.dfpn_store(hash, depth, entry);
+                break;
+            }
+
+            let (best, child_thpn, child_thdn) =
+                self.dfpn_select(&children, depth - 1, or_node, entry, thpn, thdn);
+            let (mv, child_hash) = children[best];
+            let captured = position.do_move(mv);
+            self.dfpn_mid(position, child_hash, depth - 1, attacker, child_thpn, child_thdn);
+            position.undo_move(mv, captured);
+        }
+        self.dfpn_path.remove(&hash);
+    }
+
+    // 子局面の値から証明数・反証数を計算
+    //
+    // 攻め方: pn = min(pn), dn = sum(dn) / 受け方: pn = sum(pn), dn = min(dn)
+    fn dfpn_collect(&self, children: &[(Move, u64)], depth: u8, or_node: bool) -> DfPnEntry {
+        let mut min = DFPN_INF;
+        let mut sum = 0u32;
+        let mut mate_len: Option<u16> = None;
+
+        for (_, hash) in children {
+            let child = self.dfpn_child_entry(*hash, depth);
+            let (minimized, summed) = if or_node {
+                (child.pn, child.dn)
+            } else {
+                (child.dn, child.pn)
+            };
+            min = min.min(minimized);
+            sum = sum.saturating_add(summed).min(DFPN_INF);
+
+            if child.pn == 0 {
+                let len = child.mate_len.saturating_add(1);
+                mate_len = Some(match mate_len {
+                    Some(current) if or_node => current.min(len),
+                    Some(current) => current.max(len),
+                    None => len,
+                });
+            }
+        }
+
+        let (pn, dn) = if or_node { (min, sum) } else { (sum, min) };
+        DfPnEntry {
+            pn,
+            dn,
+            mate_len: if pn == 0 { mate_len.unwrap_or(0) } else { 0 },
+        }
+    }
+
+    // 展開する子局面とその閾値を選ぶ
+    fn dfpn_select(
         &self,
-        board: &Board,
-        hands: &Hands,
-        _move: &Move,
-        _player: Player,
-    ) -> (Board, Hands) {
-        // TODO: 実際の手の適用を実装
-        // 現在は仮実装
-        (
-            board.clone(),
-            Hands {
-                black: hands.black.clone(),
-                white: hands.white.clone(),
-            },
-        )
+        children: &[(Move, u64)],
+        depth: u8,
+        or_node: bool,
+        entry: DfPnEntry,
+        thpn: u32,
+        thdn: u32,
+    ) -> (usize, u32, u32) {
+        // 攻め方は pn 最小、受け方は dn 最小の子を選ぶ
+        let mut best = 0;
+        let mut best_value = DFPN_INF;
+        let mut second_value = DFPN_INF;
+        for (index, (_, hash)) in children.iter().enumerate() {
+            let child = self.dfpn_child_entry(*hash, depth);
+            let value = if or_node { child.pn } else { child.dn };
+            if value < best_value {
+                second_value = best_value;
+                best_value = value;
+                best = index;
+            } else if value < second_value {
+                second_value = value;
+            }
+        }
+
+        let child = self.dfpn_child_entry(children[best].1, depth);
+        let second = second_value.saturating_add(1);
+        if or_node {
+            (best, thpn.min(second), thdn - entry.dn + child.dn)
+        } else {
+            (best, thpn - entry.pn + child.pn, thdn.min(second))
+        }
     }
 
-    // 詰みチェック（簡略版）
-    fn is_checkmate(&self, _board: &Board, _hands: &Hands, _player: Player) -> bool {
-        // TODO: 実際の詰みチェックを実装
-        // 現在は仮実装
-        false
+    // 攻め方は王手、受け方は王手回避手を子局面とし、指し手と子局面のハッシュを返す
+    fn dfpn_children(&self, position: &mut Position, or_node: bool) -> Vec<(Move, u64)> {
+        let moves = if or_node {
+            self.generate_checks(position)
+        } else {
+            self.generate_evasions(position)
+        };
+
+        moves
+            .into_iter()
+            .map(|mv| {
+                let captured = position.do_move(mv);
+                let hash = position_hash(position);
+                position.undo_move(mv, captured);
+                (mv, hash)
+            })
+            .collect()
+    }
+
+    // 探索中の経路上に戻る手は千日手（連続王手）として攻め方の負けとみなす
+    fn dfpn_child_entry(&self, hash: u64, depth: u8) -> DfPnEntry {
+        if self.dfpn_path.contains(&hash) {
+            DfPnEntry::DISPROVEN
+        } else {
+            self.dfpn_lookup(hash, depth)
+        }
+    }
+
+    fn dfpn_probe(&self, hash: u64, depth: u8) -> Option<DfPnEntry> {
+        self.dfpn_table.get(&hash).and_then(|slot| slot.probe(depth))
+    }
+
+    fn dfpn_lookup(&self, hash: u64, depth: u8) -> DfPnEntry {
+        self.dfpn_probe(hash, depth).unwrap_or(DfPnEntry::UNKNOWN)
+    }
+
+    // 残り depth 手での結果を記録（証明は最短手数、反証は最大の残り手数を残す）
+    fn dfpn_store(&mut self, hash: u64, depth: u8, entry: DfPnEntry) {
+        if self.dfpn_table.len() >= DFPN_TABLE_LIMIT && !self.dfpn_table.contains_key(&hash) {
+            self.dfpn_table.clear();
+        }
+        let slot = self.dfpn_table.entry(hash).or_default();
+        if entry.pn == 0 {
+            slot.proof = Some(slot.proof.map_or(entry.mate_len, |len| len.min(entry.mate_len)));
+        } else if entry.dn == 0 {
+            slot.disproof = Some(slot.disproof.map_or(depth, |disproved| disproved.max(depth)));
+        } else {
+            slot.working = Some((depth, entry));
+        }
+    }
+
+    // 置換表から詰み手順を復元（攻め方は最短、受け方は最長の証明済み手を辿る）
+    fn dfpn_principal_variation(&self, root: &Position, depth: u8, attacker: Player) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut position = root.clone();
+        let mut depth = depth;
+
+        while depth > 0 {
+            let or_node = position.side_to_move() == attacker;
+            let proven = self
+                .dfpn_children(&mut position, or_node)
+                .into_iter()
+                .map(|(mv, hash)| (mv, self.dfpn_lookup(hash, depth - 1)))
+                .filter(|(_, entry)| entry.pn == 0);
+            let next = if or_node {
+                proven.min_by_key(|(_, entry)| entry.mate_len)
+            } else {
+                proven.max_by_key(|(_, entry)| entry.mate_len)
+            };
+
+            match next {
+                Some((mv, _)) => {
+                    line.push(mv);
+                    position.do_move(mv);
+                    depth -= 1;
+                }
+                None => break,
+            }
+        }
+
+        if position.is_checkmate() {
+            line
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+// 手数を除いた盤面・持ち駒・手番のハッシュ（定跡キーに手番を加えたもの）
+fn position_hash(position: &Position) -> u64 {
+    match position.side_to_move() {
+        Player::Black => position.book_key(),
+        Player::White => position.book_key() ^ ZobristKeys::get().turn,
     }
 }
 
@@ -297,6 +664,7 @@ impl MateSearchEngine {
 #[wasm_bindgen]
 pub struct MateSearcher {
     engine: MateSearchEngine,
+    algorithm: MateSearchAlgorithm,
 }
 
 #[wasm_bindgen]
@@ -305,38 +673,556 @@ impl MateSearcher {
     pub fn new(timeout_ms: Option<u32>) -> MateSearcher {
         MateSearcher {
             engine: MateSearchEngine::new(timeout_ms.unwrap_or(30000)),
+            algorithm: MateSearchAlgorithm::Naive,
         }
     }
 
+    // 探索アルゴリズムを切り替える（既定は Naive）
+    pub fn set_algorithm(&mut self, algorithm: MateSearchAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
     // 盤面データをJSONで受け取り、探索を実行
+    // スキーマは crate::shogi::json を参照
     pub fn search_from_json(
         &mut self,
-        _board_json: &str,
-        _hands_json: &str,
+        board_json: &str,
+        hands_json: &str,
         attacker: &str,
         max_depth: u8,
-    ) -> MateSearchResult {
-        let start_time = self.engine.performance.now();
-
-        // TODO: JSONパース処理を実装
-        let board = Board::new();
-        let hands = Hands {
-            black: Hand::new(),
-            white: Hand::new(),
-        };
-        let attacker = if attacker == "black" {
-            Player::Black
-        } else {
-            Player::White
+    ) -> Result<MateSearchResult, JsValue> {
+        let position = parse_player(attacker)
+            .and_then(|attacker| position_from_json(board_json, hands_json, attacker))
+            .map_err(|e| JsValue::from_str(&format!("{e:#}")))?;
+
+        Ok(self.search_position(&position, max_depth))
+    }
+
+    // SFEN文字列で局面を受け取り、手番側を攻め方として探索を実行
+    pub fn search_from_sfen(
+        &mut self,
+        sfen: &str,
+        max_depth: u8,
+    ) -> Result<MateSearchResult, JsValue> {
+        let position = Position::from_sfen(sfen)
+            .map_err(|e| JsValue::from_str(&format!("Invalid SFEN: {e:#}")))?;
+
+        Ok(self.search_position(&position, max_depth))
+    }
+}
+
+impl MateSearcher {
+    fn search_position(&mut self, position: &Position, max_depth: u8) -> MateSearchResult {
+        let start_time = self.engine.now();
+        let (_, moves) = match self.algorithm {
+            MateSearchAlgorithm::Naive => self.engine.search(position, max_depth),
+            MateSearchAlgorithm::DfPn => self.engine.search_dfpn(position, max_depth),
         };
 
-        let (is_mate, moves) = self.engine.search(&board, &hands, attacker, max_depth);
+        MateSearchResult::new(
+            position,
+            &moves,
+            self.engine.node_count,
+            (self.engine.now() - start_time) as u32,
+        )
+    }
+}
 
-        MateSearchResult {
-            is_mate,
-            move_count: moves.len(),
+// 段階的な探索の途中経過
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MateSearchProgress {
+    pub node_count: u32,
+    // 探索中の手数の上限
+    pub depth: u8,
+    // ルート局面の証明数・反証数（無限大は 2^31 - 1）
+    pub proof_number: u32,
+    pub disproof_number: u32,
+    // これまでに見つかった最短の詰み手数（未発見なら 0）
+    pub mate_length: usize,
+    pub is_finished: bool,
+}
+
+// 段階的に進める df-pn 詰み探索（Web Worker 向け）
+//
+// step で指定ノード数ずつ探索を進めて途中経過を返す。置換表は step 間でも、
+// 詰みが見つかって手数の上限を縮めた後も保持される。cancel で打ち切っても
+// それまでに見つかった手順は result で取得できる
+#[wasm_bindgen]
+pub struct MateSearchSession {
+    engine: MateSearchEngine,
+    root: Position,
+    hash: u64,
+    attacker: Player,
+    limit: u8,
+    best: Vec<Move>,
+    finished: bool,
+    start_time: f64,
+}
+
+#[wasm_bindgen]
+impl MateSearchSession {
+    // SFEN で局面を受け取る（手番側が攻め方、timeout_ms 省略時は無制限）
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        sfen: &str,
+        max_depth: u8,
+        timeout_ms: Option<u32>,
+    ) -> Result<MateSearchSession, JsValue> {
+        let position = Position::from_sfen(sfen)
+            .map_err(|e| JsValue::from_str(&format!("Invalid SFEN: {e:#}")))?;
+
+        Ok(Self::from_position(&position, max_depth, timeout_ms))
+    }
+
+    // 盤面データを JSON で受け取る（スキーマは crate::shogi::json を参照）
+    pub fn from_json(
+        board_json: &str,
+        hands_json: &str,
+        attacker: &str,
+        max_depth: u8,
+        timeout_ms: Option<u32>,
+    ) -> Result<MateSearchSession, JsValue> {
+        let position = parse_player(attacker)
+            .and_then(|attacker| position_from_json(board_json, hands_json, attacker))
+            .map_err(|e| JsValue::from_str(&format!("{e:#}")))?;
+
+        Ok(Self::from_position(&position, max_depth, timeout_ms))
+    }
+
+    // 最大 node_budget ノードだけ探索を進める
+    pub fn step(&mut self, node_budget: u32) -> MateSearchProgress {
+        let end = self.engine.node_count.saturating_add(node_budget);
+
+        while !self.finished && self.engine.node_count < end {
+            let budget = end - self.engine.node_count;
+            let entry = self.engine.dfpn_resume(
+                &mut self.root,
+                self.hash,
+                self.limit,
+                self.attacker,
+                budget,
+            );
+
+            if entry.pn == 0 {
+                let line =
+                    self.engine.dfpn_principal_variation(&self.root, self.limit, self.attacker);
+                if line.is_empty() {
+                    self.finished = true;
+                    break;
+                }
+
+                // より短い詰みを探すため上限を縮める（置換表はそのまま使う）
+                self.limit = line.len().saturating_sub(2) as u8;
+                self.best = line;
+                self.finished = self.limit == 0;
+            } else if entry.dn == 0 {
+                self.finished = true;
+            }
+
+            if self.engine.timed_out() {
+                self.finished = true;
+            }
+        }
+
+        self.progress()
+    }
+
+    // 現在の途中経過
+    pub fn progress(&self) -> MateSearchProgress {
+        let (proof_number, disproof_number) = match (self.finished, self.best.is_empty()) {
+            (true, false) => (0, DFPN_INF),
+            (true, true) => (DFPN_INF, 0),
+            (false, _) => {
+                let entry = self.engine.dfpn_lookup(self.hash, self.limit);
+                (entry.pn, entry.dn)
+            }
+        };
+
+        MateSearchProgress {
             node_count: self.engine.node_count,
-            elapsed_ms: (self.engine.performance.now() - start_time) as u32,
+            depth: self.limit,
+            proof_number,
+            disproof_number,
+            mate_length: self.best.len(),
+            is_finished: self.finished,
+        }
+    }
+
+    // 探索を打ち切る（以降の step は何もしない）
+    pub fn cancel(&mut self) {
+        self.finished = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // これまでに見つかった最短の詰み手順
+    pub fn result(&self) -> MateSearchResult {
+        MateSearchResult::new(
+            &self.root,
+            &self.best,
+            self.engine.node_count,
+            (self.engine.now() - self.start_time) as u32,
+        )
+    }
+}
+
+impl MateSearchSession {
+    pub fn from_position(position: &Position, max_depth: u8, timeout_ms: Option<u32>) -> Self {
+        let mut engine = MateSearchEngine::new(timeout_ms.unwrap_or(u32::MAX));
+        engine.start();
+        engine.dfpn_path.clear();
+
+        let root = position.clone();
+        let start_time = engine.now();
+
+        MateSearchSession {
+            hash: position_hash(&root),
+            attacker: root.side_to_move(),
+            limit: max_depth,
+            best: Vec::new(),
+            finished: max_depth == 0,
+            start_time,
+            engine,
+            root,
         }
     }
 }
+
+// 詰将棋の局面を作成（先手が攻め方で、玉方の持駒は残り全部）。テスト用
+#[cfg(test)]
+pub(crate) fn tsume(board: &str, attacker_hand: &str) -> Position {
+    let mut position = Position::from_sfen(&format!("{board} b {attacker_hand} 1")).unwrap();
+    let totals = [18u8, 4, 4, 4, 4, 2, 2];
+    for (piece_type, total) in crate::shogi::PieceType::HAND.into_iter().zip(totals) {
+        let on_board = position
+            .pieces()
+            .filter(|(_, p)| p.piece_type.unpromote() == piece_type)
+            .count() as u8;
+        let used = on_board + position.hand(Player::Black).count(piece_type);
+        position.set_hand_count(Player::White, piece_type, total - used);
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi::PieceType;
+
+    /// 手順を再生して最終局面が詰みであることを確認
+    fn assert_mating_line(position: &Position, line: &[Move]) {
+        let mut current = position.clone();
+        for (ply, mv) in line.iter().enumerate() {
+            assert!(current.is_legal(*mv), "illegal move {mv} at ply {ply}");
+            current.do_move(*mv);
+            if ply % 2 == 0 {
+                assert!(current.in_check(), "attacker move {mv} is not a check");
+            }
+        }
+        assert!(current.is_checkmate());
+    }
+
+    fn usi(line: &[Move]) -> Vec<String> {
+        line.iter().map(|mv| mv.to_usi()).collect()
+    }
+
+    fn assert_mate_in(position: &Position, plies: usize) -> Vec<Move> {
+        let mut engine = MateSearchEngine::new(60_000);
+        let (is_mate, line) = engine.search(position, plies as u8);
+        assert!(is_mate, "no mate found in {plies} plies");
+        assert_eq!(line.len(), plies, "unexpected line {:?}", usi(&line));
+        assert_mating_line(position, &line);
+
+        if plies > 1 {
+            let (shorter, _) = engine.search(position, plies as u8 - 2);
+            assert!(!shorter, "mate is shorter than {plies} plies");
+        }
+        line
+    }
+
+    #[test]
+    fn test_move_in_place_capture_goes_to_hand_demoted() {
+        let position = Position::from_sfen("4k4/9/4+p4/9/4R4/9/9/9/4K4 b - 1").unwrap();
+        let mut after = position.clone();
+
+        let mv = Move::from_usi("5e5c+").unwrap();
+        let captured = after.do_move(mv);
+
+        assert_eq!(after.hand(Player::Black).count(PieceType::Pawn), 1);
+        assert_eq!(after.piece_at(mv.to()).map(|p| p.piece_type), Some(PieceType::Dragon));
+        assert_eq!(after.side_to_move(), Player::White);
+        assert!(after.in_check());
+
+        after.undo_move(mv, captured);
+        assert_eq!(after, position);
+        assert_eq!(position_hash(&after), position_hash(&position));
+    }
+
+    #[test]
+    fn test_checkmate_detection() {
+        let engine = MateSearchEngine::new(1000);
+
+        // 頭金
+        let mated = Position::from_sfen("4k4/4G4/4P4/9/9/9/9/9/4K4 w - 1").unwrap();
+        assert!(engine.is_checkmate(&mated));
+
+        // 王手だが玉が逃げられる
+        let escapable = Position::from_sfen("4k4/4G4/9/9/9/9/9/9/4K4 w - 1").unwrap();
+        assert!(escapable.in_check());
+        assert!(!engine.is_checkmate(&escapable));
+
+        // 王手でなければ詰みではない
+        assert!(!engine.is_checkmate(&Position::startpos()));
+    }
+
+    #[test]
+    fn test_mate_in_1() {
+        let position = tsume("4k4/9/4P4/9/9/9/9/9/9", "G");
+        let line = assert_mate_in(&position, 1);
+        assert_eq!(usi(&line), ["G*5b"]);
+    }
+
+    #[test]
+    fn test_mate_in_3() {
+        let position = tsume("6+B1k/6s2/9/9/9/9/9/9/9", "RB");
+        let line = assert_mate_in(&position, 3);
+        assert_eq!(usi(&line)[0], "B*2b");
+
+        let position = tsume("4+B4/6k2/7N1/5l2g/9/9/9/9/9", "2G");
+        assert_mate_in(&position, 3);
+    }
+
+    #[test]
+    fn test_mate_in_5() {
+        let position = tsume("6+B1k/9/9/9/9/9/9/9/9", "2S");
+        let line = assert_mate_in(&position, 5);
+        assert_eq!(usi(&line)[0], "S*2b");
+    }
+
+    #[test]
+    fn test_mate_in_7() {
+        let position = tsume("5k2B/9/4S4/9/7+P1/9/9/9/9", "S");
+        let line = assert_mate_in(&position, 7);
+        assert_eq!(usi(&line)[0], "S*4b");
+    }
+
+    #[test]
+    fn test_no_mate() {
+        // 玉の逃げ道が広く、金1枚では詰まない
+        let position = tsume("4k4/9/9/9/9/9/9/9/9", "G");
+        let mut engine = MateSearchEngine::new(60_000);
+        let (is_mate, line) = engine.search(&position, 3);
+        assert!(!is_mate);
+        assert!(line.is_empty());
+    }
+
+    #[test]
+    fn test_uchifuzume_is_not_mate() {
+        // 打ち歩詰めは反則のため1手詰めにならない
+        let position = tsume("8k/6S2/7G1/9/9/9/9/9/9", "P");
+        let mut engine = MateSearchEngine::new(60_000);
+        let (is_mate, line) = engine.search(&position, 1);
+        assert!(!is_mate, "unexpected mate {:?}", usi(&line));
+    }
+
+    #[test]
+    fn test_dfpn_finds_shortest_mate() {
+        let problems = [
+            ("4k4/9/4P4/9/9/9/9/9/9", "G", 1),
+            ("6+B1k/6s2/9/9/9/9/9/9/9", "RB", 3),
+            ("6+B1k/9/9/9/9/9/9/9/9", "2S", 5),
+            ("5k2B/9/4S4/9/7+P1/9/9/9/9", "S", 7),
+        ];
+
+        for (board, hand, plies) in problems {
+            let position = tsume(board, hand);
+            let mut engine = MateSearchEngine::new(60_000);
+            let (is_mate, line) = engine.search_dfpn(&position, 31);
+            assert!(is_mate, "{board}: no mate found");
+            assert_eq!(line.len(), plies, "{board}: unexpected line {:?}", usi(&line));
+            assert_mating_line(&position, &line);
+        }
+    }
+
+    #[test]
+    fn test_dfpn_no_mate() {
+        let mut engine = MateSearchEngine::new(60_000);
+
+        let position = tsume("4k4/9/9/9/9/9/9/9/9", "G");
+        assert_eq!(engine.search_dfpn(&position, 31), (false, Vec::new()));
+
+        // 打ち歩詰め
+        let position = tsume("8k/6S2/7G1/9/9/9/9/9/9", "P");
+        assert!(!engine.search_dfpn(&position, 31).0);
+
+        // 手数制限内に詰みがない
+        let position = tsume("5k2B/9/4S4/9/7+P1/9/9/9/9", "S");
+        assert!(!engine.search_dfpn(&position, 5).0);
+    }
+
+    #[test]
+    fn test_dfpn_solves_mate_beyond_naive_search() {
+        // 反復深化の AND/OR 探索では時間のかかる9手詰め
+        let position =
+            Position::from_sfen("8+P/2k6/4+L2R1/9/8+R/9/7pS/9/9 b GSP2b3g2s4n3l15p 1").unwrap();
+        let mut engine = MateSearchEngine::new(10_000);
+        let (is_mate, line) = engine.search_dfpn(&position, 31);
+        assert!(is_mate);
+        assert!(line.len() >= 9);
+        assert_mating_line(&position, &line);
+    }
+
+    #[test]
+    fn test_dfpn_solves_long_mate_within_node_budget() {
+        // 1筋の合駒を取り切ってから1段の合駒を取っていく23手詰め。縮めた上限でも
+        // 置換表を使い回すので、最短であることの確認まで含めて上限内に収まる
+        let position = tsume("RG6k/1pllllpp1/9/9/9/nnn6/3s4N/ggss4R/gbbs5", "-");
+        let mut session = MateSearchSession::from_position(&position, 31, None);
+        let progress = session.step(200_000);
+        assert!(progress.is_finished);
+        assert_eq!(progress.mate_length, 23);
+
+        let result = session.result();
+        assert!(result.is_mate);
+        assert_eq!(result.moves()[0], "1g2e");
+        assert_mating_line(&position, &session.best);
+    }
+
+    #[test]
+    fn test_searcher_algorithm_selection() {
+        let position = tsume("6+B1k/9/9/9/9/9/9/9/9", "2S");
+        let mut searcher = MateSearcher::new(Some(60_000));
+        searcher.set_algorithm(MateSearchAlgorithm::DfPn);
+
+        let result = searcher.search_from_sfen(&position.to_sfen(), 15).unwrap();
+        assert!(result.is_mate);
+        assert_eq!(result.move_count, 5);
+        assert_eq!(result.moves()[0], "S*2b");
+    }
+
+    /// 呼ばれるたびに 1ms 進む時計
+    #[derive(Default)]
+    struct StepClock(std::cell::Cell<f64>);
+
+    impl Clock for StepClock {
+        fn now_ms(&self) -> f64 {
+            let now = self.0.get();
+            self.0.set(now + 1.0);
+            now
+        }
+    }
+
+    #[test]
+    fn test_engine_uses_injected_clock() {
+        let position = tsume("5k2B/9/4S4/9/7+P1/9/9/9/9", "S");
+
+        // 時計を差し替えると実時間に関係なくタイムアウトする
+        let mut engine = MateSearchEngine::with_clock(5, Box::new(StepClock::default()));
+        assert!(!engine.search(&position, 7).0);
+        assert!(!engine.search_dfpn(&position, 7).0);
+
+        let mut engine = MateSearchEngine::with_clock(1_000_000, Box::new(StepClock::default()));
+        assert!(engine.search(&position, 7).0);
+    }
+
+    #[test]
+    fn test_session_steps_to_shortest_mate() {
+        let position = tsume("5k2B/9/4S4/9/7+P1/9/9/9/9", "S");
+        let (_, expected) = MateSearchEngine::new(60_000).search_dfpn(&position, 9);
+
+        let mut session = MateSearchSession::from_position(&position, 9, None);
+        let mut last_nodes = 0;
+        while !session.is_finished() {
+            let progress = session.step(16);
+            assert!(progress.node_count > last_nodes);
+            assert!(progress.node_count <= last_nodes + 16 + 9);
+            last_nodes = progress.node_count;
+        }
+
+        let progress = session.progress();
+        assert_eq!(progress.proof_number, 0);
+        assert_eq!(progress.mate_length, expected.len());
+        let result = session.result();
+        assert!(result.is_mate);
+        assert_eq!(result.move_count, expected.len());
+        assert_eq!(result.kif_moves().len(), expected.len());
+    }
+
+    #[test]
+    fn test_session_disproves_and_cancels() {
+        let position = tsume("3gkg3/9/4P4/9/9/9/9/9/9", "S");
+        let mut session = MateSearchSession::from_position(&position, 5, None);
+        let progress = session.step(u32::MAX);
+        assert!(progress.is_finished);
+        assert_eq!(progress.disproof_number, 0);
+        assert!(!session.result().is_mate);
+
+        let position = tsume("8+P/2k6/4+L2R1/9/8+R/9/7pS/9/9", "GSP");
+        let mut session = MateSearchSession::from_position(&position, 15, None);
+        let progress = session.step(8);
+        assert!(!progress.is_finished);
+
+        session.cancel();
+        assert!(session.is_finished());
+        assert_eq!(session.step(1000).node_count, progress.node_count);
+    }
+
+    #[test]
+    fn test_session_from_sfen() {
+        let mut session =
+            MateSearchSession::new("4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1", 3, None).unwrap();
+        session.step(u32::MAX);
+        assert!(session.is_finished());
+        assert_eq!(session.result().moves(), vec!["G*5b"]);
+    }
+
+    #[test]
+    fn test_searcher_from_json() {
+        // Web の MateSearchPanel が送る形式（キーは "段筋"）
+        let board = r#"{
+            "15": { "type": "gyoku", "promoted": false, "owner": "white" },
+            "35": { "type": "pawn", "promoted": false, "owner": "black" }
+        }"#;
+        let hands = r#"{ "black": { "金": 1 }, "white": {} }"#;
+
+        let mut searcher = MateSearcher::new(Some(60_000));
+        let result = searcher.search_from_json(board, hands, "black", 3).unwrap();
+        assert!(result.is_mate);
+        assert_eq!(result.move_count, 1);
+        assert_eq!(result.moves(), vec!["G*5b"]);
+        assert_eq!(result.kif_moves(), vec!["５二金打"]);
+    }
+
+    #[test]
+    fn test_searcher_from_sfen() {
+        let mut searcher = MateSearcher::new(None);
+        let result = searcher
+            .search_from_sfen("sfen 4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1", 3)
+            .unwrap();
+        assert!(result.is_mate);
+        assert_eq!(result.move_count, 1);
+
+        let result = searcher.search_from_sfen("4k4/9/9/9/9/9/9/9/9 w G 1", 1).unwrap();
+        assert!(!result.is_mate);
+        assert!(result.moves().is_empty());
+    }
+
+    #[test]
+    fn test_searcher_returns_mate_line() {
+        let position = tsume("6+B1k/6s2/9/9/9/9/9/9/9", "RB");
+        let mut searcher = MateSearcher::new(Some(60_000));
+        let result = searcher.search_from_sfen(&position.to_sfen(), 3).unwrap();
+
+        assert!(result.is_mate);
+        assert_eq!(result.moves().len(), result.move_count);
+        assert_eq!(result.kif_moves().len(), result.move_count);
+        assert_eq!(result.moves()[0], "B*2b");
+        assert_eq!(result.kif_moves()[0], "２二角打");
+
+        let line: Vec<Move> = result.moves().iter().map(|m| Move::from_usi(m).unwrap()).collect();
+        assert_mating_line(&position, &line);
+    }
+}
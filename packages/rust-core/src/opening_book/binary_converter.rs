@@ -2,10 +2,16 @@
 //!
 //! This module converts SFEN opening book data to a compact binary format,
 //! reducing file size by 70-90% while maintaining fast lookup performance.
+//! Books are written sorted by position hash behind a [`PositionIndex`]
+//! table (see [`IndexedBook`](super::IndexedBook)); unindexed books from
+//! older converters can still be read. When the moves carry game statistics
+//! ([`MoveStats`]) the book is written with [`MOVE_STATS_FLAG`] and every move
+//! record is followed by its counts.
 
 use crate::opening_book::{
-    CompactMove, CompactPosition, MoveEncoder, PositionFilter, PositionHasher, RawMove,
-    RawSfenEntry,
+    CompactMove, CompactPosition, HashScheme, MoveEncoder, MoveStats, PositionFilter,
+    PositionHasher, PositionIndex, RawMove, RawSfenEntry, FILE_HEADER_SIZE, INDEXED_BOOK_FLAG,
+    MAX_INDEX_OFFSET, MOVE_SIZE, MOVE_STATS_FLAG, MOVE_STATS_SIZE, POSITION_INDEX_SIZE,
 };
 use anyhow::{anyhow, Result};
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
@@ -21,6 +27,32 @@ pub struct BinaryFileHeader {
     pub checksum: u32,
 }
 
+impl BinaryFileHeader {
+    /// Whether the positions are preceded by a sorted [`PositionIndex`] table
+    pub fn is_indexed(&self) -> bool {
+        self.version & INDEXED_BOOK_FLAG != 0
+    }
+
+    /// Whether every move record is followed by its [`MoveStats`]
+    pub fn has_move_stats(&self) -> bool {
+        self.version & MOVE_STATS_FLAG != 0
+    }
+
+    /// Size of one move record in bytes
+    pub fn move_size(&self) -> usize {
+        if self.has_move_stats() {
+            MOVE_SIZE + MOVE_STATS_SIZE
+        } else {
+            MOVE_SIZE
+        }
+    }
+
+    /// Hashing scheme named by the version, ignoring the format flags
+    pub fn scheme(&self) -> Option<HashScheme> {
+        HashScheme::from_version(self.version & !(INDEXED_BOOK_FLAG | MOVE_STATS_FLAG))
+    }
+}
+
 /// Statistics about the conversion process
 #[derive(Debug, Clone)]
 pub struct ConversionStats {
@@ -35,28 +67,40 @@ pub struct ConversionStats {
 pub struct BinaryEntry {
     pub header: CompactPosition,
     pub moves: Vec<CompactMove>,
+    /// Statistics of each move, in the same order; empty when the entry has
+    /// none
+    pub move_stats: Vec<MoveStats>,
 }
 
 /// Binary converter for opening book data
 pub struct BinaryConverter {
-    #[allow(dead_code)]
-    hasher: PositionHasher,
+    /// Hashing scheme of the written book; also decides the header version
+    scheme: HashScheme,
 }
 
 impl BinaryConverter {
-    /// Create a new binary converter
+    /// Create a new binary converter writing the legacy (version 1) format
     pub fn new() -> Self {
-        Self {
-            hasher: PositionHasher::new(),
-        }
+        Self::with_scheme(HashScheme::BoardAndHands)
+    }
+
+    /// Create a new binary converter hashing positions with the given scheme
+    pub fn with_scheme(scheme: HashScheme) -> Self {
+        Self { scheme }
+    }
+
+    /// Hashing scheme used for position keys
+    pub fn scheme(&self) -> HashScheme {
+        self.scheme
     }
 
     /// Convert a single SFEN entry to binary format
     pub fn convert_entry(&self, entry: &RawSfenEntry) -> Result<BinaryEntry> {
-        // Hash the position - includes board, turn and hands (but NOT move count)
+        // Hash the position - board and hands, plus turn if the scheme says so
+        // (but NOT move count)
         // 手数は定跡検索では使用しないため、ハッシュ生成時に含めない
         let position_str = format!("{} {} {}", entry.position, entry.turn, entry.hand);
-        let position_hash = PositionHasher::hash_position(&position_str)?;
+        let position_hash = PositionHasher::hash_position_with(&position_str, self.scheme)?;
 
         // Find best move
         let best_move = entry
@@ -68,21 +112,46 @@ impl BinaryConverter {
         // Encode best move
         let best_move_encoded = MoveEncoder::encode_move(&best_move.move_notation)?;
 
+        // The header counts moves in one byte
+        let move_count = u8::try_from(entry.moves.len())
+            .map_err(|_| anyhow!("Too many moves in entry: {}", entry.moves.len()))?;
+
         // Create position header
         let header = CompactPosition {
             position_hash,
             best_move: best_move_encoded,
             evaluation: best_move.evaluation as i16,
             depth: best_move.depth as u8,
-            move_count: entry.moves.len() as u8,
-            popularity: 1, // Default popularity
+            move_count,
+            popularity: Self::popularity(&entry.moves),
             reserved: 0,
         };
 
         // Convert moves
         let moves = entry.moves.iter().map(|m| self.convert_move(m)).collect::<Result<Vec<_>>>()?;
 
-        Ok(BinaryEntry { header, moves })
+        // Keep the statistics only if the entry was built from game records
+        let move_stats = if entry.moves.iter().any(|m| m.stats.is_some()) {
+            entry.moves.iter().map(|m| m.stats.unwrap_or_default()).collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(BinaryEntry {
+            header,
+            moves,
+            move_stats,
+        })
+    }
+
+    /// Games that reached the position, saturating at 255; books without
+    /// game statistics rate every position 1
+    fn popularity(moves: &[RawMove]) -> u8 {
+        if moves.iter().all(|m| m.stats.is_none()) {
+            return 1;
+        }
+        let games: u64 = moves.iter().filter_map(|m| m.stats).map(|s| s.play_count as u64).sum();
+        games.min(u8::MAX as u64) as u8
     }
 
     /// Convert a raw move to compact format
@@ -177,6 +246,82 @@ impl BinaryConverter {
         })
     }
 
+    /// Encode move statistics to bytes
+    pub fn encode_move_stats(stats: &MoveStats) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MOVE_STATS_SIZE);
+
+        bytes.extend_from_slice(&stats.play_count.to_le_bytes());
+        bytes.extend_from_slice(&stats.wins.to_le_bytes());
+        bytes.extend_from_slice(&stats.draws.to_le_bytes());
+        bytes.extend_from_slice(&stats.losses.to_le_bytes());
+
+        bytes
+    }
+
+    /// Decode move statistics from bytes
+    pub fn decode_move_stats(bytes: &[u8]) -> Result<MoveStats> {
+        if bytes.len() < MOVE_STATS_SIZE {
+            return Err(anyhow!("Invalid move statistics size"));
+        }
+
+        Ok(MoveStats {
+            play_count: u32::from_le_bytes(bytes[0..4].try_into()?),
+            wins: u32::from_le_bytes(bytes[4..8].try_into()?),
+            draws: u32::from_le_bytes(bytes[8..12].try_into()?),
+            losses: u32::from_le_bytes(bytes[12..16].try_into()?),
+        })
+    }
+
+    /// Decode `count` move records of `move_size` bytes, with their
+    /// statistics when the records have room for them
+    pub fn decode_move_records(
+        bytes: &[u8],
+        count: usize,
+        move_size: usize,
+    ) -> Result<(Vec<CompactMove>, Vec<MoveStats>)> {
+        if bytes.len() < count * move_size {
+            return Err(anyhow!("Unexpected end of data"));
+        }
+
+        let mut moves = Vec::with_capacity(count);
+        let mut move_stats = Vec::new();
+        for record in bytes.chunks_exact(move_size).take(count) {
+            moves.push(Self::decode_move(record)?);
+            if move_size >= MOVE_SIZE + MOVE_STATS_SIZE {
+                move_stats.push(Self::decode_move_stats(&record[MOVE_SIZE..])?);
+            }
+        }
+        Ok((moves, move_stats))
+    }
+
+    /// Encode index entry to bytes
+    pub fn encode_position_index(index: &PositionIndex) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(POSITION_INDEX_SIZE);
+
+        debug_assert!(index.offset < MAX_INDEX_OFFSET);
+        bytes.extend_from_slice(&index.hash.to_le_bytes());
+        bytes.extend_from_slice(&(index.offset as u32).to_le_bytes());
+        bytes.extend_from_slice(&index.length.to_le_bytes());
+        bytes.extend_from_slice(&((index.offset >> 32) as u16).to_le_bytes());
+
+        bytes
+    }
+
+    /// Decode index entry from bytes
+    pub fn decode_position_index(bytes: &[u8]) -> Result<PositionIndex> {
+        if bytes.len() < POSITION_INDEX_SIZE {
+            return Err(anyhow!("Invalid index entry size"));
+        }
+
+        let offset_low = u32::from_le_bytes(bytes[8..12].try_into()?);
+        let offset_high = u16::from_le_bytes(bytes[14..16].try_into()?);
+        Ok(PositionIndex {
+            hash: u64::from_le_bytes(bytes[0..8].try_into()?),
+            offset: (offset_high as u64) << 32 | offset_low as u64,
+            length: u16::from_le_bytes(bytes[12..14].try_into()?),
+        })
+    }
+
     /// Encode file header
     pub fn encode_file_header(&self, header: &BinaryFileHeader) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(16);
@@ -204,34 +349,68 @@ impl BinaryConverter {
     }
 
     /// Write binary data to writer
+    ///
+    /// Positions are sorted by hash (keeping the input order among equal
+    /// hashes) and preceded by an index, so readers can binary-search the
+    /// book without decoding it.
     pub fn write_binary<W: Write>(
         &self,
         entries: &[RawSfenEntry],
         writer: &mut W,
     ) -> Result<ConversionStats> {
-        let binary_entries: Vec<BinaryEntry> =
+        let mut binary_entries: Vec<BinaryEntry> =
             entries.iter().map(|e| self.convert_entry(e)).collect::<Result<Vec<_>>>()?;
+        binary_entries.sort_by_key(|entry| entry.header.position_hash);
+        let with_stats = binary_entries.iter().any(|entry| !entry.move_stats.is_empty());
+        let move_size = if with_stats {
+            MOVE_SIZE + MOVE_STATS_SIZE
+        } else {
+            MOVE_SIZE
+        };
 
-        let mut data = Vec::new();
+        let mut index = Vec::with_capacity(binary_entries.len() * POSITION_INDEX_SIZE);
+        let mut positions = Vec::new();
         let mut total_moves = 0;
+        let positions_start = FILE_HEADER_SIZE + binary_entries.len() * POSITION_INDEX_SIZE;
 
-        // Write positions and moves
+        // Write positions and moves, indexing each one by its absolute offset
         for entry in &binary_entries {
-            data.extend(Self::encode_position_header(&entry.header));
+            let offset = (positions_start + positions.len()) as u64;
+            if offset >= MAX_INDEX_OFFSET {
+                return Err(anyhow!("Book is too large for 48-bit offsets"));
+            }
+            positions.extend(Self::encode_position_header(&entry.header));
             total_moves += entry.moves.len();
 
-            for mov in &entry.moves {
-                data.extend(Self::encode_move(mov));
+            for (i, mov) in entry.moves.iter().enumerate() {
+                positions.extend(Self::encode_move(mov));
+                if with_stats {
+                    let stats = entry.move_stats.get(i).copied().unwrap_or_default();
+                    positions.extend(Self::encode_move_stats(&stats));
+                }
             }
+
+            index.extend(Self::encode_position_index(&PositionIndex {
+                hash: entry.header.position_hash,
+                offset,
+                length: (16 + entry.moves.len() * move_size) as u16,
+            }));
         }
 
+        let mut data = index;
+        data.extend(positions);
+
         // Calculate checksum
         let checksum = self.calculate_checksum(&data);
 
         // Create and write header
+        let mut version = self.scheme.version() | INDEXED_BOOK_FLAG;
+        if with_stats {
+            version |= MOVE_STATS_FLAG;
+        }
         let header = BinaryFileHeader {
             magic: *b"SFEN",
-            version: 1,
+            version,
             position_count: binary_entries.len() as u32,
             checksum,
         };
@@ -251,6 +430,18 @@ impl BinaryConverter {
 
     /// Read binary data from reader
     pub fn read_binary<R: Read>(&self, reader: &mut R) -> Result<Vec<BinaryEntry>> {
+        let (_, entries) = self.read_binary_with_header(reader)?;
+        Ok(entries)
+    }
+
+    /// Read binary data from reader, also returning the file header
+    ///
+    /// The header's version tells which [`HashScheme`] the position hashes use
+    /// and whether the positions are indexed; entries come back in file order.
+    pub fn read_binary_with_header<R: Read>(
+        &self,
+        reader: &mut R,
+    ) -> Result<(BinaryFileHeader, Vec<BinaryEntry>)> {
         // Read header
         let mut header_bytes = [0u8; 16];
         reader.read_exact(&mut header_bytes)?;
@@ -259,6 +450,9 @@ impl BinaryConverter {
         if &header.magic != b"SFEN" {
             return Err(anyhow!("Invalid file magic"));
         }
+        if header.scheme().is_none() {
+            return Err(anyhow!("Unsupported format version: {}", header.version));
+        }
 
         // Read data
         let mut data = Vec::new();
@@ -274,6 +468,14 @@ impl BinaryConverter {
         let mut entries = Vec::new();
         let mut offset = 0;
 
+        // Skip the index; its entries point at the positions parsed below
+        if header.is_indexed() {
+            offset = header.position_count as usize * POSITION_INDEX_SIZE;
+            if offset > data.len() {
+                return Err(anyhow!("Index does not fit in the data"));
+            }
+        }
+
         while offset < data.len() {
             // Read position header
             if offset + 16 > data.len() {
@@ -283,23 +485,19 @@ impl BinaryConverter {
             offset += 16;
 
             // Read moves
-            let mut moves = Vec::new();
-            for _ in 0..pos_header.move_count {
-                if offset + 6 > data.len() {
-                    return Err(anyhow!("Unexpected end of data"));
-                }
-                let mov = Self::decode_move(&data[offset..offset + 6])?;
-                moves.push(mov);
-                offset += 6;
-            }
+            let move_count = pos_header.move_count as usize;
+            let (moves, move_stats) =
+                Self::decode_move_records(&data[offset..], move_count, header.move_size())?;
+            offset += move_count * header.move_size();
 
             entries.push(BinaryEntry {
                 header: pos_header,
                 moves,
+                move_stats,
             });
         }
 
-        Ok(entries)
+        Ok((header, entries))
     }
 
     /// Compress data using gzip
@@ -0,0 +1,172 @@
+//! Building opening books from game records
+//!
+//! [`BookBuilder`] replays [`GameRecord`]s and counts, for every position in
+//! the first moves of each game, which moves were played and how those games
+//! ended. The result is a list of [`RawSfenEntry`] that
+//! [`BinaryConverter::write_binary`](crate::opening_book::BinaryConverter::write_binary)
+//! turns into a book with move statistics, like one converted from a
+//! YaneuraOu database.
+
+use crate::opening_book::{GameRecord, GameResult, MoveStats, RawMove, RawSfenEntry};
+use std::collections::HashMap;
+
+/// Centipawns per unit of log-odds when turning a score into an evaluation,
+/// the usual scale of shogi engines' win-rate curves
+const EVAL_SCALE: f64 = 600.0;
+
+/// A book entry stores at most this many moves
+const MAX_MOVES_PER_POSITION: usize = u8::MAX as usize;
+
+/// Moves and statistics of one position seen in the games
+#[derive(Debug, Clone)]
+struct PositionMoves {
+    /// Lowest ply at which the position was reached
+    ply: u32,
+    /// USI notation and statistics, in the order first played
+    moves: Vec<(String, MoveStats)>,
+}
+
+/// Accumulates move statistics from game records
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    max_ply: usize,
+    /// Keyed by SFEN without the move number
+    positions: HashMap<String, PositionMoves>,
+    games: usize,
+}
+
+impl BookBuilder {
+    /// Builder recording the first `max_ply` moves of each game
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            positions: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    /// Count the opening moves of a game
+    pub fn add_game(&mut self, game: &GameRecord) {
+        let mut position = game.start.clone();
+        for &mv in game.moves.iter().take(self.max_ply) {
+            let sfen = position.to_sfen();
+            let (key, _) = sfen.rsplit_once(' ').expect("SFEN ends with the move number");
+            let entry = self.positions.entry(key.to_string()).or_insert_with(|| PositionMoves {
+                ply: position.ply(),
+                moves: Vec::new(),
+            });
+            entry.ply = entry.ply.min(position.ply());
+
+            let notation = mv.to_usi();
+            let index = match entry.moves.iter().position(|(usi, _)| *usi == notation) {
+                Some(index) => index,
+                None => {
+                    entry.moves.push((notation, MoveStats::default()));
+                    entry.moves.len() - 1
+                }
+            };
+            let stats = &mut entry.moves[index].1;
+            stats.play_count += 1;
+            match game.result {
+                Some(GameResult::Win(winner)) if winner == position.side_to_move() => {
+                    stats.wins += 1
+                }
+                Some(GameResult::Win(_)) => stats.losses += 1,
+                Some(GameResult::Draw) => stats.draws += 1,
+                None => {}
+            }
+
+            position.do_move(mv);
+        }
+        self.games += 1;
+    }
+
+    /// Number of games added
+    pub fn game_count(&self) -> usize {
+        self.games
+    }
+
+    /// Number of distinct positions with at least one move
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Book entries keeping moves played at least `min_count` times
+    ///
+    /// Moves are ordered by play count. Each evaluation is the smoothed score
+    /// of the move's games turned into centipawns, so moves that won more
+    /// often rank higher with the `best` strategy; with no results it is 0.
+    pub fn entries(&self, min_count: u32) -> Vec<RawSfenEntry> {
+        let mut entries: Vec<RawSfenEntry> = self
+            .positions
+            .iter()
+            .filter_map(|(sfen, position)| {
+                let mut moves: Vec<RawMove> = position
+                    .moves
+                    .iter()
+                    .filter(|(_, stats)| stats.play_count >= min_count)
+                    .map(|(notation, stats)| RawMove {
+                        move_notation: notation.clone(),
+                        move_type: "none".to_string(),
+                        evaluation: evaluation(stats),
+                        depth: 0,
+                        nodes: stats.play_count as u64,
+                        stats: Some(*stats),
+                    })
+                    .collect();
+                if moves.is_empty() {
+                    return None;
+                }
+                // Stable, so equally popular moves keep the order first played
+                moves.sort_by_key(|m| std::cmp::Reverse(m.nodes));
+                moves.truncate(MAX_MOVES_PER_POSITION);
+
+                let mut fields = sfen.split(' ');
+                Some(RawSfenEntry {
+                    position: fields.next()?.to_string(),
+                    turn: fields.next()?.chars().next()?,
+                    hand: fields.next()?.to_string(),
+                    move_count: position.ply,
+                    moves,
+                })
+            })
+            .collect();
+
+        // Same input, same book
+        entries.sort_by(|a, b| (a.move_count, &a.position).cmp(&(b.move_count, &b.position)));
+        entries
+    }
+}
+
+/// Log-odds of the move's score (draws count half, one win and one loss
+/// added so few games stay near 0) in centipawns
+fn evaluation(stats: &MoveStats) -> i32 {
+    let decided = (stats.wins + stats.draws + stats.losses) as f64;
+    let score = (stats.wins as f64 + stats.draws as f64 / 2.0 + 1.0) / (decided + 2.0);
+    (EVAL_SCALE * (score / (1.0 - score)).ln()).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluation() {
+        let stats = |wins, draws, losses| MoveStats {
+            play_count: wins + draws + losses,
+            wins,
+            draws,
+            losses,
+        };
+        assert_eq!(evaluation(&MoveStats::default()), 0);
+        assert_eq!(evaluation(&stats(3, 2, 3)), 0);
+        assert_eq!(evaluation(&stats(1, 0, 0)), 416);
+        assert_eq!(evaluation(&stats(0, 0, 1)), -416);
+        // Results of many games fit the i16 stored in books
+        assert!(evaluation(&stats(100_000, 0, 0)) < i16::MAX as i32);
+    }
+}
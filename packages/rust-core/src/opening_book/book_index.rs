@@ -0,0 +1,157 @@
+//! Sorted hash index of a binary opening book
+//!
+//! Indexed books store a [`PositionIndex`] table, sorted by position hash,
+//! right after the file header, followed by the position entries in the same
+//! order. [`IndexedBook`] binary-searches that table directly on the byte
+//! buffer, so opening a book costs nothing beyond validating the header and a
+//! lookup decodes only the entry that was found.
+
+use crate::opening_book::{BinaryConverter, BinaryEntry, HashScheme, PositionIndex};
+use anyhow::{anyhow, Result};
+
+/// Set in the header version of books written with a sorted index; the low
+/// bits stay the [`HashScheme`] version, so older readers reject such files
+pub const INDEXED_BOOK_FLAG: u32 = 0x8000_0000;
+
+/// Set in the header version of books whose move records carry
+/// [`MoveStats`](crate::opening_book::MoveStats)
+pub const MOVE_STATS_FLAG: u32 = 0x4000_0000;
+
+/// Size of the file header in bytes
+pub const FILE_HEADER_SIZE: usize = 16;
+
+/// Size of one [`PositionIndex`] entry in bytes
+pub const POSITION_INDEX_SIZE: usize = 16;
+
+/// Index offsets are stored in 48 bits and must be below this
+pub const MAX_INDEX_OFFSET: u64 = 1 << 48;
+
+/// Size of a move record without statistics in bytes
+pub const MOVE_SIZE: usize = 6;
+
+/// Size of the statistics following a move in books with [`MOVE_STATS_FLAG`]
+pub const MOVE_STATS_SIZE: usize = 16;
+
+/// Read-only view of an uncompressed, indexed book
+#[derive(Debug, Clone, Copy)]
+pub struct IndexedBook<'a> {
+    data: &'a [u8],
+    count: usize,
+    scheme: HashScheme,
+    move_size: usize,
+}
+
+impl<'a> IndexedBook<'a> {
+    /// Whether the bytes start with the header of an indexed book
+    pub fn is_indexed(data: &[u8]) -> bool {
+        data.len() >= FILE_HEADER_SIZE
+            && &data[0..4] == b"SFEN"
+            && u32::from_le_bytes(data[4..8].try_into().unwrap()) & INDEXED_BOOK_FLAG != 0
+    }
+
+    /// Validate the header and the index bounds without touching the entries
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if !Self::is_indexed(data) {
+            return Err(anyhow!("Not an indexed book"));
+        }
+        let header = BinaryConverter::new().decode_file_header(data)?;
+        let scheme = header
+            .scheme()
+            .ok_or_else(|| anyhow!("Unsupported format version: {}", header.version))?;
+
+        let count = header.position_count as usize;
+        let index_end = count
+            .checked_mul(POSITION_INDEX_SIZE)
+            .and_then(|size| size.checked_add(FILE_HEADER_SIZE));
+        if index_end.is_none_or(|end| end > data.len()) {
+            return Err(anyhow!("Index of {count} positions does not fit in the book"));
+        }
+
+        Ok(Self {
+            data,
+            count,
+            scheme,
+            move_size: header.move_size(),
+        })
+    }
+
+    /// Number of indexed positions
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Hashing scheme of the position keys
+    pub fn scheme(&self) -> HashScheme {
+        self.scheme
+    }
+
+    /// Whether the moves carry game statistics
+    pub fn has_move_stats(&self) -> bool {
+        self.move_size > MOVE_SIZE
+    }
+
+    /// Index entry at `i` (`i < len()`)
+    pub fn index_entry(&self, i: usize) -> PositionIndex {
+        let start = FILE_HEADER_SIZE + i * POSITION_INDEX_SIZE;
+        BinaryConverter::decode_position_index(&self.data[start..start + POSITION_INDEX_SIZE])
+            .expect("index bounds are checked in parse")
+    }
+
+    fn hash_at(&self, i: usize) -> u64 {
+        let start = FILE_HEADER_SIZE + i * POSITION_INDEX_SIZE;
+        u64::from_le_bytes(self.data[start..start + 8].try_into().unwrap())
+    }
+
+    /// Index of the position with the given hash
+    ///
+    /// When a hash occurs more than once the last one written wins, as it did
+    /// when whole books were loaded into a map.
+    pub fn find(&self, hash: u64) -> Option<usize> {
+        // Binary search for the first entry whose hash is greater than `hash`
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.hash_at(mid) <= hash {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        (low > 0 && self.hash_at(low - 1) == hash).then(|| low - 1)
+    }
+
+    /// Decode the position entry at index `i`
+    pub fn entry(&self, i: usize) -> Result<BinaryEntry> {
+        let index = self.index_entry(i);
+        let bytes = usize::try_from(index.offset)
+            .ok()
+            .and_then(|start| self.data.get(start..start.checked_add(index.length as usize)?))
+            .ok_or_else(|| anyhow!("Position entry {i} is out of bounds"))?;
+
+        let header = BinaryConverter::decode_position_header(bytes)?;
+        if header.position_hash != index.hash {
+            return Err(anyhow!("Position entry {i} does not match its index"));
+        }
+        let (moves, move_stats) = BinaryConverter::decode_move_records(
+            &bytes[16..],
+            header.move_count as usize,
+            self.move_size,
+        )
+        .map_err(|_| anyhow!("Position entry {i} is truncated"))?;
+
+        Ok(BinaryEntry {
+            header,
+            moves,
+            move_stats,
+        })
+    }
+
+    /// Look up and decode the position with the given hash
+    pub fn find_entry(&self, hash: u64) -> Result<Option<BinaryEntry>> {
+        self.find(hash).map(|i| self.entry(i)).transpose()
+    }
+}
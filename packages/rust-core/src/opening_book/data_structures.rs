@@ -19,7 +19,7 @@ pub struct RawSfenEntry {
 }
 
 /// Raw move data as parsed from the input file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct RawMove {
     /// Move notation (e.g., "7g7f", "P*5f", "3d3c+")
     pub move_notation: String,
@@ -31,6 +31,24 @@ pub struct RawMove {
     pub depth: u32,
     /// Number of nodes searched
     pub nodes: u64,
+    /// Game statistics when the book is built from game records
+    pub stats: Option<MoveStats>,
+}
+
+/// How often a move was played and how those games ended
+///
+/// Results are counted from the point of view of the player making the move;
+/// `play_count` may exceed the sum of the results when some games had none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    /// Number of games in which the move was played
+    pub play_count: u32,
+    /// Games won by the player making the move
+    pub wins: u32,
+    /// Drawn games (sennichite, impasse)
+    pub draws: u32,
+    /// Games lost by the player making the move
+    pub losses: u32,
 }
 
 /// Compact binary representation of a position
@@ -47,7 +65,8 @@ pub struct CompactPosition {
     pub depth: u8, // 1 byte
     /// Number of alternative moves stored
     pub move_count: u8, // 1 byte
-    /// Usage frequency indicator
+    /// Usage frequency indicator: games that reached the position, saturating
+    /// at 255, or 1 when the book has no game statistics
     pub popularity: u8, // 1 byte
     /// Reserved for alignment
     pub reserved: u8, // 1 byte
@@ -70,16 +89,16 @@ pub struct CompactMove {
 }
 
 /// Index entry for fast position lookup
+///
+/// Stored in 16 bytes: the hash, the low 32 bits of the offset, the length
+/// and the next 16 bits of the offset, so offsets reach 2^48 and books
+/// written with 32-bit offsets read the same.
 #[derive(Debug, Clone)]
-#[repr(C)]
 pub struct PositionIndex {
     /// Position hash (matches CompactPosition.position_hash)
-    pub hash: u64, // 8 bytes
-    /// Byte offset in the binary file
-    pub offset: u32, // 4 bytes
+    pub hash: u64,
+    /// Byte offset in the binary file (below 2^48)
+    pub offset: u64,
     /// Length of the position data in bytes
-    pub length: u16, // 2 bytes
-    /// Reserved for alignment
-    pub reserved: u16, // 2 bytes
-                       // Total: 16 bytes per index entry
+    pub length: u16,
 }
@@ -0,0 +1,630 @@
+//! Game records in KIF, CSA and USI notation
+//!
+//! The parsers replay every move on a [`Position`] and reject records with
+//! moves that are not legal, so a [`GameRecord`] is always playable from its
+//! start position. Only the main line is read: KIF variations (`変化`) are
+//! ignored, and CSA files may hold several games separated by `/` lines.
+//! KIF board diagrams are not supported; even games and the standard
+//! handicaps (`手合割`) are.
+
+use crate::shogi::{kif_piece_name, Move, Piece, PieceType, Player, Position, Square};
+use anyhow::{anyhow, Context, Result};
+
+/// How a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    /// The player won (resignation, mate, time, foul or entering king)
+    Win(Player),
+    /// Repetition, impasse or another drawn ending
+    Draw,
+}
+
+/// Start position, main line and result of one game
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub start: Position,
+    pub moves: Vec<Move>,
+    /// `None` when the record stops without a result (e.g. `中断`)
+    pub result: Option<GameResult>,
+}
+
+impl GameRecord {
+    /// Record ending at `end`; a missing result is taken from the board when
+    /// the last move mated
+    fn finish(
+        start: Position,
+        moves: Vec<Move>,
+        end: &Position,
+        result: Option<GameResult>,
+    ) -> Self {
+        let result = result
+            .or_else(|| end.is_checkmate().then(|| GameResult::Win(end.side_to_move().opponent())));
+        Self {
+            start,
+            moves,
+            result,
+        }
+    }
+}
+
+/// Notation of a game record file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameFormat {
+    /// Japanese KIF (`.kif`, `.kifu`)
+    Kif,
+    /// CSA standard (`.csa`)
+    Csa,
+    /// One USI `position` command per game (`.usi`)
+    Usi,
+}
+
+impl GameFormat {
+    /// Format by file extension (case-insensitive)
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "kif" | "kifu" => Some(GameFormat::Kif),
+            "csa" => Some(GameFormat::Csa),
+            "usi" => Some(GameFormat::Usi),
+            _ => None,
+        }
+    }
+
+    /// Parse all games of a file's text
+    pub fn parse(self, text: &str) -> Result<Vec<GameRecord>> {
+        match self {
+            GameFormat::Kif => parse_kif(text).map(|game| vec![game]),
+            GameFormat::Csa => parse_csa(text),
+            GameFormat::Usi => parse_usi(text),
+        }
+    }
+}
+
+/// Start positions of the `手合割` values KIF files use
+const KIF_HANDICAPS: [(&str, &str); 11] = [
+    ("平手", "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1"),
+    ("香落ち", "lnsgkgsn1/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("右香落ち", "1nsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("角落ち", "lnsgkgsnl/1r7/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("飛車落ち", "lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("飛香落ち", "lnsgkgsn1/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("二枚落ち", "lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("四枚落ち", "1nsgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("六枚落ち", "2sgkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("八枚落ち", "3gkg3/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+    ("十枚落ち", "4k4/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+];
+
+/// KIF piece names, longer names first so `成銀` is not read as `成`
+const KIF_PIECES: [(&str, PieceType); 19] = [
+    ("成銀", PieceType::ProSilver),
+    ("成香", PieceType::ProLance),
+    ("成桂", PieceType::ProKnight),
+    ("全", PieceType::ProSilver),
+    ("杏", PieceType::ProLance),
+    ("圭", PieceType::ProKnight),
+    ("歩", PieceType::Pawn),
+    ("香", PieceType::Lance),
+    ("桂", PieceType::Knight),
+    ("銀", PieceType::Silver),
+    ("金", PieceType::Gold),
+    ("角", PieceType::Bishop),
+    ("飛", PieceType::Rook),
+    ("玉", PieceType::King),
+    ("王", PieceType::King),
+    ("と", PieceType::ProPawn),
+    ("馬", PieceType::Horse),
+    ("龍", PieceType::Dragon),
+    ("竜", PieceType::Dragon),
+];
+
+/// Parse a KIF game
+pub fn parse_kif(text: &str) -> Result<GameRecord> {
+    let mut start: Option<Position> = None;
+    let mut position = Position::startpos();
+    let mut moves = Vec::new();
+    let mut previous_to = None;
+    let mut result = None;
+
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with(['#', '*', '&']) {
+            continue;
+        }
+        if line.starts_with("変化") {
+            break;
+        }
+        if line.starts_with('|') || line.contains("の持駒") {
+            return Err(anyhow!("KIF board diagrams are not supported"));
+        }
+
+        let Some(number_end) = line.find(|c: char| !c.is_ascii_digit()).filter(|&i| i > 0) else {
+            // Header such as `手合割：平手`
+            if let Some((key, value)) = line.split_once(['：', ':']) {
+                if key.trim() == "手合割" && moves.is_empty() {
+                    let value = value.trim();
+                    let (_, sfen) = KIF_HANDICAPS
+                        .iter()
+                        .find(|(name, _)| *name == value)
+                        .ok_or_else(|| anyhow!("Unsupported handicap: {value}"))?;
+                    position = Position::from_sfen(sfen)?;
+                }
+            }
+            continue;
+        };
+
+        start.get_or_insert_with(|| position.clone());
+        let number = &line[..number_end];
+        let notation = line[number_end..].trim_start();
+        if let Some(ending) = kif_result(notation, position.side_to_move()) {
+            result = ending;
+            break;
+        }
+
+        let mv = parse_kif_move(&position, notation, previous_to)
+            .with_context(|| format!("Move {number}: {notation}"))?;
+        if !position.is_legal(mv) {
+            return Err(anyhow!("Move {number} is illegal: {notation}"));
+        }
+        position.do_move(mv);
+        moves.push(mv);
+        previous_to = Some(mv.to());
+    }
+
+    let start = start.ok_or_else(|| anyhow!("No moves found"))?;
+    Ok(GameRecord::finish(start, moves, &position, result))
+}
+
+/// Result recorded by a KIF special move, `Some(None)` for endings without
+/// a result and `None` for regular moves
+fn kif_result(notation: &str, side_to_move: Player) -> Option<Option<GameResult>> {
+    let loss = Some(GameResult::Win(side_to_move.opponent()));
+    let ending = [
+        ("投了", loss),
+        ("詰み", loss),
+        ("切れ負け", loss),
+        ("反則負け", loss),
+        ("反則勝ち", Some(GameResult::Win(side_to_move))),
+        ("入玉勝ち", Some(GameResult::Win(side_to_move))),
+        ("千日手", Some(GameResult::Draw)),
+        ("持将棋", Some(GameResult::Draw)),
+        ("中断", None),
+        ("封じ手", None),
+    ];
+    ending
+        .iter()
+        .find(|(word, _)| notation.starts_with(word))
+        .map(|(_, result)| *result)
+}
+
+/// Parse a KIF move such as `７六歩(77)`, `同　銀成(43)` or `５五角打`
+fn parse_kif_move(
+    position: &Position,
+    notation: &str,
+    previous_to: Option<Square>,
+) -> Result<Move> {
+    let (to, rest) = if let Some(rest) = notation.strip_prefix('同') {
+        let to = previous_to.ok_or_else(|| anyhow!("同 without a previous move"))?;
+        (to, rest.trim_start_matches(['　', ' ']))
+    } else {
+        let mut chars = notation.chars();
+        let file = chars.next().and_then(kif_digit);
+        let rank = chars.next().and_then(kif_digit);
+        let to = file
+            .zip(rank)
+            .and_then(|(file, rank)| Square::new(file, rank))
+            .ok_or_else(|| anyhow!("Invalid destination"))?;
+        (to, chars.as_str())
+    };
+
+    let (piece_type, rest) = KIF_PIECES
+        .iter()
+        .find_map(|(name, piece_type)| rest.strip_prefix(name).map(|rest| (*piece_type, rest)))
+        .ok_or_else(|| anyhow!("Invalid piece"))?;
+
+    if rest.starts_with('打') {
+        return Ok(Move::Drop { piece_type, to });
+    }
+    let (promote, rest) = if let Some(rest) = rest.strip_prefix("不成") {
+        (false, rest)
+    } else if let Some(rest) = rest.strip_prefix('成') {
+        (true, rest)
+    } else {
+        (false, rest)
+    };
+
+    let from = rest
+        .strip_prefix('(')
+        .and_then(|origin| origin.get(..2))
+        .and_then(square_from_digits)
+        .ok_or_else(|| anyhow!("Missing origin square"))?;
+    if position.piece_at(from).map(|piece| piece.piece_type) != Some(piece_type) {
+        return Err(anyhow!("No {} on {}{}", kif_piece_name(piece_type), from.file(), from.rank()));
+    }
+
+    Ok(Move::Normal { from, to, promote })
+}
+
+/// File or rank written as a full-width, ASCII or kanji numeral
+fn kif_digit(c: char) -> Option<u8> {
+    let value = match c {
+        '１'..='９' => c as u32 - '０' as u32,
+        '1'..='9' => c as u32 - '0' as u32,
+        _ => "一二三四五六七八九".chars().position(|k| k == c)? as u32 + 1,
+    };
+    Some(value as u8)
+}
+
+/// Parse the games of a CSA file
+pub fn parse_csa(text: &str) -> Result<Vec<GameRecord>> {
+    let mut games = Vec::new();
+    let mut lines = Vec::new();
+    for line in text.lines().chain(["/"]) {
+        if line.trim() == "/" {
+            if lines.iter().any(|line: &&str| line.starts_with(['+', '-', 'P'])) {
+                let number = games.len() + 1;
+                games.push(parse_csa_game(&lines).with_context(|| format!("Game {number}"))?);
+            }
+            lines.clear();
+        } else {
+            lines.push(line.trim());
+        }
+    }
+    Ok(games)
+}
+
+fn parse_csa_game(lines: &[&str]) -> Result<GameRecord> {
+    let mut position = Position::empty();
+    let mut start: Option<Position> = None;
+    let mut moves = Vec::new();
+    let mut result = None;
+
+    // Several statements may share a line, separated by commas
+    let statements = lines
+        .iter()
+        .filter(|line| !line.starts_with(['\'', '$', 'N', 'V']))
+        .flat_map(|line| line.split(','));
+
+    for statement in statements {
+        match start {
+            None => {
+                if statement == "+" || statement == "-" {
+                    position.set_side_to_move(csa_player(statement)?);
+                    position.validate()?;
+                    start = Some(position.clone());
+                } else if let Some(removed) = statement.strip_prefix("PI") {
+                    position = csa_initial_position(removed)?;
+                } else if let Some(rest) = statement.strip_prefix('P') {
+                    csa_setup_line(&mut position, rest)
+                        .with_context(|| format!("Invalid setup: {statement}"))?;
+                } else if !statement.is_empty() && !statement.starts_with('T') {
+                    return Err(anyhow!("Move before the side to move: {statement}"));
+                }
+            }
+            Some(_) => {
+                if let Some(ending) = statement.strip_prefix('%') {
+                    result = csa_result(ending, position.side_to_move());
+                    break;
+                }
+                if !statement.starts_with(['+', '-']) {
+                    continue;
+                }
+                let number = moves.len() + 1;
+                let mv = parse_csa_move(&position, statement)
+                    .with_context(|| format!("Move {number}: {statement}"))?;
+                if !position.is_legal(mv) {
+                    return Err(anyhow!("Move {number} is illegal: {statement}"));
+                }
+                position.do_move(mv);
+                moves.push(mv);
+            }
+        }
+    }
+
+    let start = start.ok_or_else(|| anyhow!("Missing side to move"))?;
+    Ok(GameRecord::finish(start, moves, &position, result))
+}
+
+/// Result of a CSA `%` ending, from the side to move when it was recorded
+fn csa_result(ending: &str, side_to_move: Player) -> Option<GameResult> {
+    match ending {
+        "TORYO" | "TSUMI" | "TIME_UP" | "ILLEGAL_MOVE" => {
+            Some(GameResult::Win(side_to_move.opponent()))
+        }
+        "KACHI" => Some(GameResult::Win(side_to_move)),
+        "+ILLEGAL_ACTION" => Some(GameResult::Win(Player::White)),
+        "-ILLEGAL_ACTION" => Some(GameResult::Win(Player::Black)),
+        "SENNICHITE" | "JISHOGI" | "HIKIWAKE" | "MAX_MOVES" => Some(GameResult::Draw),
+        _ => None,
+    }
+}
+
+fn csa_player(sign: &str) -> Result<Player> {
+    match sign {
+        "+" => Ok(Player::Black),
+        "-" => Ok(Player::White),
+        _ => Err(anyhow!("Invalid side: {sign}")),
+    }
+}
+
+fn csa_piece(code: &str) -> Option<PieceType> {
+    let piece_type = match code {
+        "FU" => PieceType::Pawn,
+        "KY" => PieceType::Lance,
+        "KE" => PieceType::Knight,
+        "GI" => PieceType::Silver,
+        "KI" => PieceType::Gold,
+        "KA" => PieceType::Bishop,
+        "HI" => PieceType::Rook,
+        "OU" => PieceType::King,
+        "TO" => PieceType::ProPawn,
+        "NY" => PieceType::ProLance,
+        "NK" => PieceType::ProKnight,
+        "NG" => PieceType::ProSilver,
+        "UM" => PieceType::Horse,
+        "RY" => PieceType::Dragon,
+        _ => return None,
+    };
+    Some(piece_type)
+}
+
+/// Square from two ASCII digits (file then rank), as in `(77)` or `+7776FU`;
+/// `None` for `00` and invalid digits
+fn square_from_digits(digits: &str) -> Option<Square> {
+    let mut digits = digits.chars().map(|c| c.to_digit(10));
+    Square::new(digits.next()?? as u8, digits.next()?? as u8)
+}
+
+/// `PI` initial position with pieces removed for handicaps (`PI82HI22KA`)
+fn csa_initial_position(removed: &str) -> Result<Position> {
+    let mut position = Position::startpos();
+    for chunk in removed.as_bytes().chunks(4) {
+        let chunk = std::str::from_utf8(chunk)?;
+        let square = chunk.get(..2).and_then(square_from_digits);
+        let piece_type = chunk.get(2..).and_then(csa_piece);
+        match square.zip(piece_type) {
+            Some((square, piece_type))
+                if position.piece_at(square).map(|p| p.piece_type) == Some(piece_type) =>
+            {
+                position.set_piece(square, None);
+            }
+            _ => return Err(anyhow!("Invalid handicap: PI{removed}")),
+        }
+    }
+    Ok(position)
+}
+
+/// Board rank (`P1`-`P9`) or piece placement (`P+`, `P-`) after the `P`
+fn csa_setup_line(position: &mut Position, line: &str) -> Result<()> {
+    let (kind, rest) = line.split_at_checked(1).ok_or_else(|| anyhow!("Empty line"))?;
+
+    if let Some(rank) = kind.parse::<u8>().ok().filter(|rank| (1..=9).contains(rank)) {
+        let cells = rest.as_bytes().chunks(3).map(std::str::from_utf8);
+        for (file, cell) in (1..=9).rev().zip(cells) {
+            let cell = cell?;
+            let square = Square::new(file, rank).expect("file and rank in range");
+            if cell.trim() == "*" {
+                position.set_piece(square, None);
+                continue;
+            }
+            let (sign, code) =
+                cell.split_at_checked(1).ok_or_else(|| anyhow!("Invalid piece: {cell}"))?;
+            let owner = csa_player(sign)?;
+            let piece_type = csa_piece(code).ok_or_else(|| anyhow!("Invalid piece: {cell}"))?;
+            position.set_piece(square, Some(Piece::new(piece_type, owner)));
+        }
+        return Ok(());
+    }
+
+    let owner = csa_player(kind)?;
+    for chunk in rest.as_bytes().chunks(4) {
+        let chunk = std::str::from_utf8(chunk)?;
+        let (square, code) = chunk.split_at_checked(2).ok_or_else(|| anyhow!("Invalid piece"))?;
+        if code == "AL" {
+            give_remaining_pieces(position, owner);
+            continue;
+        }
+        let piece_type = csa_piece(code).ok_or_else(|| anyhow!("Invalid piece"))?;
+        if square == "00" {
+            let count = position.hand(owner).count(piece_type);
+            position.set_hand_count(owner, piece_type, count + 1);
+        } else {
+            let square = square_from_digits(square).ok_or_else(|| anyhow!("Invalid square"))?;
+            position.set_piece(square, Some(Piece::new(piece_type, owner)));
+        }
+    }
+    Ok(())
+}
+
+/// `00AL`: every piece not yet on the board or in a hand goes to `owner`
+fn give_remaining_pieces(position: &mut Position, owner: Player) {
+    for (piece_type, total) in PieceType::HAND.into_iter().zip([18, 4, 4, 4, 4, 2, 2]) {
+        let on_board = position
+            .pieces()
+            .filter(|(_, piece)| piece.piece_type.unpromote() == piece_type);
+        let in_hands: u32 = Player::ALL
+            .iter()
+            .map(|&player| position.hand(player).count(piece_type) as u32)
+            .sum();
+        let remaining = (total as u32).saturating_sub(on_board.count() as u32 + in_hands);
+        let count = position.hand(owner).count(piece_type) as u32 + remaining;
+        position.set_hand_count(owner, piece_type, count as u8);
+    }
+}
+
+/// Parse a CSA move such as `+7776FU`; the piece is the one after the move
+fn parse_csa_move(position: &Position, statement: &str) -> Result<Move> {
+    let (sign, rest) = statement.split_at_checked(1).ok_or_else(|| anyhow!("Invalid move"))?;
+    if csa_player(sign)? != position.side_to_move() {
+        return Err(anyhow!("Move out of turn"));
+    }
+    let (from, rest) = rest.split_at_checked(2).ok_or_else(|| anyhow!("Invalid move"))?;
+    let (to, code) = rest.split_at_checked(2).ok_or_else(|| anyhow!("Invalid move"))?;
+    let to = square_from_digits(to).ok_or_else(|| anyhow!("Invalid destination"))?;
+    let piece_type = csa_piece(code).ok_or_else(|| anyhow!("Invalid piece"))?;
+
+    if from == "00" {
+        return Ok(Move::Drop { piece_type, to });
+    }
+    let from = square_from_digits(from).ok_or_else(|| anyhow!("Invalid origin"))?;
+    let moved = position
+        .piece_at(from)
+        .ok_or_else(|| anyhow!("No piece on the origin square"))?
+        .piece_type;
+    let promote = moved != piece_type && moved.promote() == Some(piece_type);
+    if moved != piece_type && !promote {
+        return Err(anyhow!("Piece on the origin square is not {code}"));
+    }
+
+    Ok(Move::Normal { from, to, promote })
+}
+
+/// Parse one game per line from USI `position` commands (`position` itself
+/// is optional); blank lines and `#` comments are skipped
+pub fn parse_usi(text: &str) -> Result<Vec<GameRecord>> {
+    let mut games = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let game = parse_usi_game(line).with_context(|| format!("Line {}", line_number + 1))?;
+        games.push(game);
+    }
+    Ok(games)
+}
+
+fn parse_usi_game(command: &str) -> Result<GameRecord> {
+    let args = command.strip_prefix("position").unwrap_or(command).trim_start();
+    let (setup, notations) = args.split_once(" moves").unwrap_or((args, ""));
+    let start = Position::from_usi_position(setup)?;
+
+    let mut position = start.clone();
+    let mut moves = Vec::new();
+    for notation in notations.split_whitespace() {
+        let mv = Move::from_usi(notation)?;
+        if !position.is_legal(mv) {
+            return Err(anyhow!("Illegal move: {notation}"));
+        }
+        position.do_move(mv);
+        moves.push(mv);
+    }
+
+    Ok(GameRecord::finish(start, moves, &position, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usi_moves(game: &GameRecord) -> Vec<String> {
+        game.moves.iter().map(|mv| mv.to_usi()).collect()
+    }
+
+    #[test]
+    fn test_parse_kif() {
+        let text = "\
+# ---- Kifu for Windows ----
+開始日時：2024/01/01
+手合割：平手
+先手：A
+後手：B
+手数----指手---------消費時間--
+   1 ７六歩(77)   ( 0:01/00:00:01)
+   2 ３四歩(33)   ( 0:01/00:00:01)
+*コメント
+   3 ２二角成(88) ( 0:01/00:00:02)
+   4 同　銀(31)   ( 0:01/00:00:02)
+   5 ４五角打     ( 0:01/00:00:03)
+   6 投了
+まで5手で先手の勝ち
+
+変化：4手
+   4 同　飛(82)
+";
+        let game = parse_kif(text).unwrap();
+        assert_eq!(usi_moves(&game), ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+        assert_eq!(game.start, Position::startpos());
+        assert_eq!(game.result, Some(GameResult::Win(Player::Black)));
+
+        // Handicap games start with White, and 中断 has no result
+        let game = parse_kif("手合割：角落ち\n1 ３四歩(33)\n2 中断\n").unwrap();
+        assert_eq!(game.start.side_to_move(), Player::White);
+        assert_eq!(game.result, None);
+
+        assert!(parse_kif("1 ７六歩(78)\n").is_err());
+        assert!(parse_kif("手合割：駒落ち\n").is_err());
+        assert!(parse_kif("not a game record\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_csa() {
+        let text = "\
+V2.2
+N+A
+N-B
+PI
++
++7776FU,T1
+-3334FU
+T2
++8822UM
+-3122GI
++0045KA
+%TORYO
+/
+P1-KY-KE-GI-KI-OU-KI-GI-KE-KY
+P2 * -HI *  *  *  *  * -KA *
+P3-FU-FU-FU-FU-FU-FU-FU-FU-FU
+P4 *  *  *  *  *  *  *  *  *
+P5 *  *  *  *  *  *  *  *  *
+P6 *  *  *  *  *  *  *  *  *
+P7+FU+FU+FU+FU+FU+FU+FU+FU+FU
+P8 * +KA *  *  *  *  * +HI *
+P9+KY+KE+GI+KI+OU+KI+GI+KE+KY
+-
+-3334FU
+%SENNICHITE
+";
+        let games = parse_csa(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(usi_moves(&games[0]), ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+        assert_eq!(games[0].result, Some(GameResult::Win(Player::Black)));
+        assert_eq!(games[1].start.side_to_move(), Player::White);
+        assert_eq!(games[1].result, Some(GameResult::Draw));
+
+        // Handicap removal and a move by the wrong side
+        let game = &parse_csa("PI82HI\n-\n-3334FU\n").unwrap()[0];
+        assert!(game.start.piece_at(Square::new(8, 2).unwrap()).is_none());
+        assert!(parse_csa("PI\n+\n-3334FU\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_csa_rejects_malformed_setup() {
+        // Cells are three ASCII characters; anything else is an error, not a panic
+        assert!(parse_csa("V2.2\nP1歩歩歩\n+\n+7776FU\n").is_err());
+        assert!(parse_csa("P1-KY-KE-\n+\n").is_err());
+        assert!(parse_csa("P1+XX\n+\n").is_err());
+        assert!(parse_csa("P1*FU\n+\n").is_err());
+        assert!(parse_csa("P+55\n+\n").is_err());
+        assert!(parse_csa("P+歩\n+\n").is_err());
+        assert!(parse_csa("PI\n+\n+歩\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_usi_detects_mate() {
+        let text = "\
+# gold drop mate
+position sfen 4k4/9/4P4/9/9/9/9/9/4K4 b G 1 moves G*5b
+startpos moves 7g7f 3c3d
+";
+        let games = parse_usi(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(usi_moves(&games[0]), ["G*5b"]);
+        assert_eq!(games[0].result, Some(GameResult::Win(Player::Black)));
+        assert_eq!(games[1].result, None);
+        assert!(parse_usi("startpos moves 7g7f 7g7f\n").is_err());
+        assert!(parse_usi("startpos moves 7g7`\n").is_err());
+        assert_eq!(GameFormat::from_extension("KIFU"), Some(GameFormat::Kif));
+        assert_eq!(GameFormat::from_extension("db"), None);
+    }
+}
@@ -0,0 +1,87 @@
+//! Memory-mapped access to uncompressed, indexed opening books
+//!
+//! Opening a [`MappedBook`] only maps the file and validates its header, so
+//! even multi-gigabyte books are ready immediately; the operating system pages
+//! in the index and the entries that lookups actually touch. Native only.
+
+use crate::opening_book::{BinaryEntry, HashScheme, IndexedBook, PositionHasher};
+use crate::opening_book_reader::{find_indexed_moves, BookMove};
+use crate::shogi::Position;
+use anyhow::{anyhow, Context, Result};
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::Path;
+
+/// Indexed book mapped read-only from a file
+pub struct MappedBook {
+    mmap: Mmap,
+    count: usize,
+    scheme: HashScheme,
+}
+
+impl MappedBook {
+    /// Map an uncompressed book written with a sorted index
+    ///
+    /// Gzip-compressed or unindexed books are rejected; load those with
+    /// [`OpeningBookReader`](crate::opening_book_reader::OpeningBookReader).
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        // SAFETY: the map is read-only; like any reader of the file we assume
+        // it is not truncated or rewritten while the book is open
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to map {}", path.display()))?;
+
+        if mmap.starts_with(&[0x1f, 0x8b]) {
+            return Err(anyhow!("{} is gzip-compressed; decompress it first", path.display()));
+        }
+        let book = IndexedBook::parse(&mmap).with_context(|| format!("{}", path.display()))?;
+        let (count, scheme) = (book.len(), book.scheme());
+
+        Ok(Self {
+            mmap,
+            count,
+            scheme,
+        })
+    }
+
+    /// View of the mapped bytes for direct index access
+    pub fn indexed(&self) -> IndexedBook<'_> {
+        IndexedBook::parse(&self.mmap).expect("validated in open")
+    }
+
+    /// Number of indexed positions
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Hashing scheme of the position keys
+    pub fn scheme(&self) -> HashScheme {
+        self.scheme
+    }
+
+    /// Look up and decode the whole entry for a hash
+    pub fn find_entry(&self, hash: u64) -> Result<Option<BinaryEntry>> {
+        self.indexed().find_entry(hash)
+    }
+
+    pub fn find_moves_by_hash(&self, hash: u64) -> Vec<BookMove> {
+        find_indexed_moves(&self.indexed(), hash)
+    }
+
+    pub fn find_moves(&self, sfen: &str) -> Vec<BookMove> {
+        match PositionHasher::hash_position_with(sfen, self.scheme) {
+            Ok(hash) => self.find_moves_by_hash(hash),
+            Err(_) => vec![],
+        }
+    }
+
+    pub fn find_moves_for_position(&self, position: &Position) -> Vec<BookMove> {
+        self.find_moves_by_hash(self.scheme.position_key(position))
+    }
+}
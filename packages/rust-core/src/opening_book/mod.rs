@@ -1,15 +1,27 @@
 // Opening Book Module
 pub mod binary_converter;
+pub mod book_builder;
+pub mod book_index;
 pub mod data_structures;
+pub mod game_record;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mapped_book;
 pub mod move_encoder;
+pub mod move_selector;
 pub mod position_filter;
 pub mod position_hasher;
 pub mod sfen_parser;
 
 // Re-export for easier access
 pub use binary_converter::*;
+pub use book_builder::*;
+pub use book_index::*;
 pub use data_structures::*;
+pub use game_record::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use mapped_book::*;
 pub use move_encoder::*;
+pub use move_selector::*;
 pub use position_filter::*;
 pub use position_hasher::*;
 pub use sfen_parser::*;
@@ -8,10 +8,13 @@
 //! - Bits 13-7:  From square (7 bits) OR piece type for drops (7 bits)
 //! - Bits 6-0:   To square (7 bits)
 //!
-//! Square encoding: file * 9 + rank - 10 (0-80 for squares 1a-9i)
+//! Square encoding: file * 9 + rank - 10 (0-80 for squares 1a-9i), the same
+//! as the [`Square`] index
 
 use anyhow::{anyhow, Result};
 
+use crate::shogi::Square;
+
 /// Move encoder for converting between move notation and 16-bit integers
 pub struct MoveEncoder;
 
@@ -63,33 +66,12 @@ impl MoveEncoder {
         }
 
         // Parse squares: "7g7f" -> from=7g, to=7f
-        let from_file = move_part
-            .chars()
-            .nth(0)
-            .unwrap()
-            .to_digit(10)
-            .ok_or_else(|| anyhow!("Invalid from file: {}", move_part))?
-            as u8;
-        let from_rank = move_part.chars().nth(1).unwrap().to_ascii_lowercase() as u8;
-        let to_file = move_part
-            .chars()
-            .nth(2)
-            .unwrap()
-            .to_digit(10)
-            .ok_or_else(|| anyhow!("Invalid to file: {}", move_part))? as u8;
-        let to_rank = move_part.chars().nth(3).unwrap().to_ascii_lowercase() as u8;
-
-        // Validate ranges
-        if !(1..=9).contains(&from_file) || !(1..=9).contains(&to_file) {
-            return Err(anyhow!("Invalid file range: {}", move_notation));
-        }
-        if !(b'a'..=b'i').contains(&from_rank) || !(b'a'..=b'i').contains(&to_rank) {
-            return Err(anyhow!("Invalid rank range: {}", move_notation));
-        }
-
-        // Convert to square indices (0-80)
-        let from_square = ((from_file - 1) * 9 + (from_rank - b'a')) as u16;
-        let to_square = ((to_file - 1) * 9 + (to_rank - b'a')) as u16;
+        let (from_part, to_part) = match (move_part.get(0..2), move_part.get(2..4)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Err(anyhow!("Invalid normal move format: {}", move_notation)),
+        };
+        let from_square = Self::encode_square(from_part)? as u16;
+        let to_square = Self::encode_square(to_part)? as u16;
 
         // Build encoding: [type:2][from:7][to:7]
         let move_type = if promotion { 1u16 } else { 0u16 };
@@ -125,13 +107,8 @@ impl MoveEncoder {
         }
 
         // Convert back to file/rank notation
-        let from_file = (from_square / 9) + 1;
-        let from_rank = (from_square % 9) + b'a';
-        let to_file = (to_square / 9) + 1;
-        let to_rank = (to_square % 9) + b'a';
-
         let mut result =
-            format!("{}{}{}{}", from_file, from_rank as char, to_file, to_rank as char);
+            format!("{}{}", Self::decode_square(from_square)?, Self::decode_square(to_square)?);
 
         if promotion {
             result.push('+');
@@ -186,37 +163,16 @@ impl MoveEncoder {
 
     /// Encode square notation to number
     fn encode_square(square: &str) -> Result<u8> {
-        if square.len() != 2 {
-            return Err(anyhow!("Invalid square notation: {}", square));
-        }
-
-        let file = square
-            .chars()
-            .nth(0)
-            .unwrap()
-            .to_digit(10)
-            .ok_or_else(|| anyhow!("Invalid file: {}", square))? as u8;
-        let rank = square.chars().nth(1).unwrap().to_ascii_lowercase() as u8;
-
-        if !(1..=9).contains(&file) {
-            return Err(anyhow!("Invalid file range: {}", square));
-        }
-        if !(b'a'..=b'i').contains(&rank) {
-            return Err(anyhow!("Invalid rank range: {}", square));
-        }
-
-        Ok((file - 1) * 9 + (rank - b'a'))
+        // Ranks are accepted in either case, like "7G"
+        Square::from_usi(&square.to_ascii_lowercase())
+            .map(|square| square.index() as u8)
+            .ok_or_else(|| anyhow!("Invalid square notation: {}", square))
     }
 
     /// Decode square number to notation
     fn decode_square(square: u8) -> Result<String> {
-        if square > 80 {
-            return Err(anyhow!("Invalid square number: {}", square));
-        }
-
-        let file = (square / 9) + 1;
-        let rank = (square % 9) + b'a';
-
-        Ok(format!("{}{}", file, rank as char))
+        Square::from_index(square as usize)
+            .map(|square| square.to_string())
+            .ok_or_else(|| anyhow!("Invalid square number: {}", square))
     }
 }
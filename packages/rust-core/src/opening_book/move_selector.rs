@@ -0,0 +1,272 @@
+//! Choosing one move among the book moves of a position
+//!
+//! [`BookMoveSelector`] turns the flat list returned by the readers into a
+//! single choice according to a [`BookStrategy`]. Its random generator can be
+//! seeded so that games and tests are reproducible.
+
+use crate::opening_book_reader::BookMove;
+use crate::util::rng::Rng;
+use anyhow::{anyhow, Result};
+use wasm_bindgen::prelude::*;
+
+/// Names accepted by [`BookStrategy::from_name`]
+pub const BOOK_STRATEGIES: [&str; 4] = ["best", "softmax", "popularity", "margin"];
+
+/// Softmax temperature in centipawns used by `Popularity` when the book has
+/// no play counts
+pub const POPULARITY_FALLBACK_TEMPERATURE: f64 = 100.0;
+
+/// How a move is picked among the book moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookStrategy {
+    /// Always the highest evaluation (the first one listed among ties)
+    Best,
+    /// Weighted by `exp((eval - best) / temperature)`, temperature in
+    /// centipawns; a temperature of 0 or less behaves like `Best`
+    Softmax { temperature: f64 },
+    /// Weighted by how often each move was played; books without play
+    /// counts (every move counted once and no results) fall back to a
+    /// softmax over the evaluations at [`POPULARITY_FALLBACK_TEMPERATURE`]
+    Popularity,
+    /// Uniformly among the moves at most `margin` centipawns below the best
+    WithinMargin { margin: u32 },
+}
+
+impl BookStrategy {
+    /// Strategy by name; `param` is the softmax temperature or the margin
+    /// and is ignored by the other strategies
+    pub fn from_name(name: &str, param: f64) -> Result<Self> {
+        match name {
+            "best" => Ok(BookStrategy::Best),
+            "softmax" => Ok(BookStrategy::Softmax { temperature: param }),
+            "popularity" => Ok(BookStrategy::Popularity),
+            "margin" if param >= 0.0 => Ok(BookStrategy::WithinMargin {
+                margin: param as u32,
+            }),
+            "margin" => Err(anyhow!("Margin must not be negative: {param}")),
+            _ => Err(anyhow!(
+                "Invalid book strategy: {:?} (expected one of {})",
+                name,
+                BOOK_STRATEGIES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Picks book moves by strategy with a seedable random generator
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct BookMoveSelector {
+    strategy: BookStrategy,
+    rng: Rng,
+}
+
+impl BookMoveSelector {
+    /// Selector seeded differently on every run
+    pub fn new(strategy: BookStrategy) -> Self {
+        Self {
+            strategy,
+            rng: Rng::from_entropy(),
+        }
+    }
+
+    /// Selector that makes the same choices for the same seed
+    pub fn with_seed(strategy: BookStrategy, seed: u64) -> Self {
+        Self {
+            strategy,
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn strategy(&self) -> BookStrategy {
+        self.strategy
+    }
+
+    pub fn set_strategy(&mut self, strategy: BookStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Probability of each move being chosen, in the order given
+    pub fn probabilities(&self, moves: &[BookMove]) -> Vec<f64> {
+        let Some(best) = moves.iter().map(|m| m.evaluation as i32).max() else {
+            return vec![];
+        };
+
+        let softmax = |temperature: f64| -> Vec<f64> {
+            moves
+                .iter()
+                .map(|m| ((m.evaluation as i32 - best) as f64 / temperature).exp())
+                .collect()
+        };
+        // Books without statistics report every move as played once
+        let has_play_counts = moves.iter().any(|m| m.play_count > 1 || m.win_rate().is_some());
+
+        let weights: Vec<f64> = match self.strategy {
+            BookStrategy::Softmax { temperature } if temperature > 0.0 => softmax(temperature),
+            BookStrategy::Best | BookStrategy::Softmax { .. } => {
+                let first = moves.iter().position(|m| m.evaluation as i32 == best);
+                (0..moves.len()).map(|i| if Some(i) == first { 1.0 } else { 0.0 }).collect()
+            }
+            BookStrategy::Popularity if has_play_counts => {
+                moves.iter().map(|m| m.play_count as f64).collect()
+            }
+            BookStrategy::Popularity => softmax(POPULARITY_FALLBACK_TEMPERATURE),
+            BookStrategy::WithinMargin { margin } => moves
+                .iter()
+                .map(|m| {
+                    if (best - m.evaluation as i32) as u32 <= margin {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                })
+                .collect(),
+        };
+
+        // Moves nobody played are still better than no book move at all
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return vec![1.0 / moves.len() as f64; moves.len()];
+        }
+        weights.iter().map(|w| w / total).collect()
+    }
+
+    /// Choose one of the moves, or `None` when there are none
+    pub fn select<'a>(&mut self, moves: &'a [BookMove]) -> Option<&'a BookMove> {
+        let probabilities = self.probabilities(moves);
+        let mut target = self.rng.next_f64();
+        for (book_move, probability) in moves.iter().zip(&probabilities) {
+            if target < *probability {
+                return Some(book_move);
+            }
+            target -= probability;
+        }
+        // Rounding can leave a sliver past the last move
+        moves.iter().zip(&probabilities).rev().find(|(_, &p)| p > 0.0).map(|(m, _)| m)
+    }
+}
+
+#[wasm_bindgen]
+impl BookMoveSelector {
+    /// "best" / "softmax" / "popularity" / "margin"; `param` is the softmax
+    /// temperature or the margin in centipawns
+    #[wasm_bindgen(constructor)]
+    pub fn from_name(strategy: &str, param: f64) -> Result<BookMoveSelector, JsValue> {
+        BookStrategy::from_name(strategy, param)
+            .map(BookMoveSelector::new)
+            .map_err(|e| JsValue::from_str(&format!("{e:#}")))
+    }
+
+    /// Fix the random sequence (for tests and replays)
+    pub fn set_seed(&mut self, seed: u32) {
+        self.rng = Rng::new(seed as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book_move(notation: &str, evaluation: i16, play_count: u32) -> BookMove {
+        BookMove {
+            notation: notation.to_string(),
+            evaluation,
+            depth: 10,
+            play_count,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+
+    fn test_moves() -> Vec<BookMove> {
+        vec![
+            book_move("7g7f", 40, 10),
+            book_move("2g2f", 50, 30),
+            book_move("5g5f", -100, 0),
+        ]
+    }
+
+    // How often each move is chosen in 1000 seeded picks
+    fn counts(strategy: BookStrategy, moves: &[BookMove]) -> Vec<usize> {
+        let mut selector = BookMoveSelector::with_seed(strategy, 7);
+        let mut counts = vec![0; moves.len()];
+        for _ in 0..1000 {
+            let chosen = selector.select(moves).unwrap();
+            counts[moves.iter().position(|m| m.notation == chosen.notation).unwrap()] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_best_and_margin() {
+        let moves = test_moves();
+        assert_eq!(counts(BookStrategy::Best, &moves), vec![0, 1000, 0]);
+        // A softmax at temperature 0 is the best move
+        assert_eq!(counts(BookStrategy::Softmax { temperature: 0.0 }, &moves), vec![0, 1000, 0]);
+
+        let counts = counts(BookStrategy::WithinMargin { margin: 10 }, &moves);
+        assert_eq!(counts[2], 0);
+        assert!(counts[0] > 400 && counts[1] > 400, "{counts:?}");
+    }
+
+    #[test]
+    fn test_weighted_strategies() {
+        let moves = test_moves();
+        let selector = BookMoveSelector::with_seed(BookStrategy::Popularity, 1);
+        assert_eq!(selector.probabilities(&moves), vec![0.25, 0.75, 0.0]);
+
+        // Hotter softmax picks the worse moves more often
+        let cold = counts(BookStrategy::Softmax { temperature: 10.0 }, &moves);
+        let hot = counts(BookStrategy::Softmax { temperature: 200.0 }, &moves);
+        assert!(cold[1] > cold[0] && cold[0] > cold[2], "{cold:?}");
+        assert!(hot[2] > cold[2] && hot[1] < cold[1], "{hot:?} {cold:?}");
+
+        assert!(BookMoveSelector::with_seed(BookStrategy::Best, 1).select(&[]).is_none());
+    }
+
+    #[test]
+    fn test_popularity_without_play_counts() {
+        // Books without statistics count every move once: weigh by evaluation
+        let moves = vec![
+            book_move("7g7f", 40, 1),
+            book_move("2g2f", 50, 1),
+            book_move("5g5f", -100, 1),
+        ];
+        let popularity = BookMoveSelector::with_seed(BookStrategy::Popularity, 1);
+        let softmax = BookMoveSelector::with_seed(
+            BookStrategy::Softmax {
+                temperature: POPULARITY_FALLBACK_TEMPERATURE,
+            },
+            1,
+        );
+        let probabilities = popularity.probabilities(&moves);
+        assert_eq!(probabilities, softmax.probabilities(&moves));
+        assert!(probabilities[1] > probabilities[0] && probabilities[0] > probabilities[2]);
+
+        let counts = counts(BookStrategy::Popularity, &moves);
+        assert!(counts[1] > counts[0] && counts[0] > counts[2], "{counts:?}");
+
+        // A move played once with a recorded result is a real count
+        let mut played_once = moves.clone();
+        played_once[2].wins = 1;
+        assert_eq!(popularity.probabilities(&played_once), vec![1.0 / 3.0; 3]);
+    }
+
+    #[test]
+    fn test_seed_reproduces_choices() {
+        let moves = test_moves();
+        let strategy = BookStrategy::from_name("softmax", 100.0).unwrap();
+        let choices = |seed| {
+            let mut selector = BookMoveSelector::with_seed(strategy, seed);
+            (0..20)
+                .map(|_| selector.select(&moves).unwrap().notation.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(choices(3), choices(3));
+        assert_ne!(choices(3), choices(4));
+
+        assert!(BookStrategy::from_name("random", 0.0).is_err());
+        assert!(BookStrategy::from_name("margin", -1.0).is_err());
+    }
+}
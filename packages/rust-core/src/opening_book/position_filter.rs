@@ -73,7 +73,7 @@ impl PositionFilter {
     /// Filter moves within a position (keep top moves, sorted by evaluation)
     pub fn filter_moves(&self, entry: &mut RawSfenEntry) {
         // Sort moves by evaluation (best first)
-        entry.moves.sort_by(|a, b| b.evaluation.cmp(&a.evaluation));
+        entry.moves.sort_by_key(|m| std::cmp::Reverse(m.evaluation));
 
         // Keep only top 8 moves
         if entry.moves.len() > 8 {
@@ -133,6 +133,7 @@ mod tests {
                 evaluation: 50,
                 depth: 1,
                 nodes: 1000,
+                ..Default::default()
             }],
         };
 
@@ -3,10 +3,86 @@
 //! This module provides efficient hashing of Shogi positions for use in the
 //! opening book binary format. Uses Zobrist hashing for good distribution
 //! and collision avoidance.
+//!
+//! The key tables live in [`ZobristKeys`] so that [`crate::shogi::Position`]
+//! can maintain the same hash incrementally (`Position::book_key`).
+//!
+//! Books written before format version 2 hash only the board and hands, so a
+//! position with Black to move shares its key with the same position with
+//! White to move. [`HashScheme::WithSideToMove`] also mixes in the side to
+//! move; the book's header version records which scheme was used.
+//! [`HashScheme::PerCountHands`] (version 3) additionally replaces the rotated
+//! hand keys, which collide for large hands, with one key per count. Existing
+//! books keep their version; converting the SFEN source again with version 3
+//! migrates a book.
 
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 
+use crate::shogi::{PieceType, Player, Position, ZobristKeys, MAX_HAND_COUNT};
+
+/// How a position is turned into a book key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashScheme {
+    /// Board and hands only (format version 1)
+    #[default]
+    BoardAndHands,
+    /// Board, hands and side to move (format version 2)
+    WithSideToMove,
+    /// Board, side to move and per-count hand keys (format version 3)
+    PerCountHands,
+}
+
+impl HashScheme {
+    /// Binary format version written for books using this scheme
+    pub fn version(self) -> u32 {
+        match self {
+            HashScheme::BoardAndHands => 1,
+            HashScheme::WithSideToMove => 2,
+            HashScheme::PerCountHands => 3,
+        }
+    }
+
+    /// Whether the side to move is part of the key
+    pub fn includes_side_to_move(self) -> bool {
+        self != HashScheme::BoardAndHands
+    }
+
+    /// Scheme used by books of the given format version
+    pub fn from_version(version: u32) -> Option<HashScheme> {
+        match version {
+            1 => Some(HashScheme::BoardAndHands),
+            2 => Some(HashScheme::WithSideToMove),
+            3 => Some(HashScheme::PerCountHands),
+            _ => None,
+        }
+    }
+
+    /// Key of a position under this scheme, from its incrementally kept hash
+    pub fn position_key(self, position: &Position) -> u64 {
+        let keys = ZobristKeys::get();
+        let mut key = position.book_key();
+
+        if self.includes_side_to_move() && position.side_to_move() == Player::White {
+            key ^= keys.turn;
+        }
+
+        if self == HashScheme::PerCountHands {
+            // Swap the rotated hand keys for the per-count ones
+            for player in Player::ALL {
+                let hand = position.hand(player);
+                for piece_type in PieceType::HAND {
+                    let count = hand.count(piece_type);
+                    key ^= keys.hand_total(player, piece_type, count)
+                        ^ keys.hand_count(player, piece_type, count);
+                }
+            }
+        }
+
+        key
+    }
+}
+
 /// Statistics about hashing performance
 #[derive(Debug, Clone)]
 pub struct HashStatistics {
@@ -17,14 +93,8 @@ pub struct HashStatistics {
 
 /// Position hasher using Zobrist hashing
 pub struct PositionHasher {
-    /// Zobrist hash tables for pieces at each square
-    zobrist_pieces: [[u64; 32]; 81], // 81 squares, 32 piece types (including promoted)
-    /// Zobrist hash for turn (future use)
-    #[allow(dead_code)]
-    zobrist_turn: u64,
-    /// Zobrist hash for hand pieces (future use)
-    #[allow(dead_code)]
-    zobrist_hand: [u64; 14], // 7 piece types * 2 players
+    /// How positions are turned into keys
+    scheme: HashScheme,
     /// Tracked positions for collision detection
     position_tracker: HashMap<String, u64>,
     /// Statistics
@@ -32,30 +102,15 @@ pub struct PositionHasher {
 }
 
 impl PositionHasher {
-    /// Create a new position hasher
+    /// Create a new position hasher using the legacy board-and-hands scheme
     pub fn new() -> Self {
-        // Initialize Zobrist tables with pseudo-random values
-        let mut zobrist_pieces = [[0u64; 32]; 81];
-        let mut rng_state = 0x123456789abcdef0u64;
-
-        // Fill piece tables
-        for square_pieces in &mut zobrist_pieces {
-            for piece_value in square_pieces {
-                *piece_value = Self::next_random(&mut rng_state);
-            }
-        }
-
-        let zobrist_turn = Self::next_random(&mut rng_state);
-
-        let mut zobrist_hand = [0u64; 14];
-        for value in &mut zobrist_hand {
-            *value = Self::next_random(&mut rng_state);
-        }
+        Self::with_scheme(HashScheme::BoardAndHands)
+    }
 
+    /// Create a new position hasher using the given scheme
+    pub fn with_scheme(scheme: HashScheme) -> Self {
         Self {
-            zobrist_pieces,
-            zobrist_turn,
-            zobrist_hand,
+            scheme,
             position_tracker: HashMap::new(),
             stats: HashStatistics {
                 total_positions: 0,
@@ -67,10 +122,20 @@ impl PositionHasher {
 
     /// Hash a position (static method)
     pub fn hash_position(position: &str) -> Result<u64> {
-        let hasher = Self::new();
+        Self::hash_position_with(position, HashScheme::BoardAndHands)
+    }
+
+    /// Hash a position with the given scheme (static method)
+    pub fn hash_position_with(position: &str, scheme: HashScheme) -> Result<u64> {
+        let hasher = Self::with_scheme(scheme);
         hasher.hash_sfen_position(position)
     }
 
+    /// Scheme used by this hasher
+    pub fn scheme(&self) -> HashScheme {
+        self.scheme
+    }
+
     /// Hash a position and track for collision detection
     pub fn hash_and_track(&mut self, position: &str) -> Result<u64> {
         let hash = self.hash_sfen_position(position)?;
@@ -97,52 +162,30 @@ impl PositionHasher {
     }
 
     /// Hash a SFEN position string
+    ///
+    /// The position is parsed with [`Position::from_sfen_unchecked`], so a
+    /// well-formed SFEN gets a key even if it is not a reachable position.
     fn hash_sfen_position(&self, position: &str) -> Result<u64> {
-        let parts: Vec<&str> = position.split_whitespace().collect();
-
-        if parts.is_empty() {
-            return Err(anyhow!("Empty position"));
-        }
-
-        // Need at least board, turn, and hands
-        if parts.len() < 3 {
-            return Err(anyhow!("Invalid SFEN: expected at least 3 parts, got {}", parts.len()));
-        }
-
-        let board = parts[0];
-        // let turn = parts[1];
-        let hands = parts[2];
-
         #[cfg(target_arch = "wasm32")]
         {
-            web_sys::console::log_1(
-                &format!(
-                    "[Rust PositionHasher] Parts: board={}, hands={}, move_count={}",
-                    board,
-                    hands,
-                    parts.get(3).unwrap_or(&"none")
-                )
-                .into(),
-            );
+            web_sys::console::log_1(&format!("[Rust PositionHasher] Hashing: {}", position).into());
         }
 
-        // Start with board position hash
-        let mut hash = self.hash_board_position(board)?;
+        let parsed = Position::from_sfen_unchecked(position)?;
 
-        // 手番によらず、盤面・手駒だけでハッシュを計算するので十分
-        // match turn {
-        //     "b" => {
-        //         // 先手の場合は基準値なので追加のXORは不要
-        //     }
-        //     "w" => {
-        //         // 後手の場合は手番を区別するためのハッシュ値をXOR
-        //         hash ^= self.zobrist_turn;
-        //     }
-        //     _ => return Err(anyhow!("Invalid turn: {}", turn)),
-        // }
+        if self.scheme == HashScheme::PerCountHands {
+            // Per-count keys only go up to the most pieces of a kind
+            for player in Player::ALL {
+                for piece_type in PieceType::HAND {
+                    let count = parsed.hand(player).count(piece_type);
+                    if count as usize > MAX_HAND_COUNT {
+                        return Err(anyhow!("Too many pieces in hand: {}{:?}", count, piece_type));
+                    }
+                }
+            }
+        }
 
-        // XOR with hands hash
-        hash ^= self.hash_hands(hands)?;
+        let hash = self.scheme.position_key(&parsed);
 
         #[cfg(target_arch = "wasm32")]
         {
@@ -153,174 +196,6 @@ impl PositionHasher {
 
         Ok(hash)
     }
-
-    /// Hash the board position part of SFEN
-    fn hash_board_position(&self, board_position: &str) -> Result<u64> {
-        let ranks: Vec<&str> = board_position.split('/').collect();
-
-        if ranks.len() != 9 {
-            return Err(anyhow!("Invalid rank count: expected 9, got {}", ranks.len()));
-        }
-
-        let mut hash = 0u64;
-
-        for (rank_idx, rank) in ranks.iter().enumerate() {
-            hash ^= self.hash_rank(rank, rank_idx)?;
-        }
-
-        Ok(hash)
-    }
-
-    /// Hash a single rank
-    fn hash_rank(&self, rank: &str, rank_idx: usize) -> Result<u64> {
-        let mut hash = 0u64;
-        let mut file_idx = 0;
-        let mut chars = rank.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            if file_idx >= 9 {
-                return Err(anyhow!("Rank too long: {}", rank));
-            }
-
-            if ch.is_ascii_digit() {
-                // Empty squares
-                let empty_count = ch.to_digit(10).unwrap() as usize;
-                if file_idx + empty_count > 9 {
-                    return Err(anyhow!("Invalid empty square count in rank: {}", rank));
-                }
-                file_idx += empty_count;
-            } else {
-                // Piece
-                let (piece_type, promoted) = if ch == '+' {
-                    // Promoted piece
-                    if let Some(next_ch) = chars.next() {
-                        (next_ch, true)
-                    } else {
-                        return Err(anyhow!("Invalid promoted piece in rank: {}", rank));
-                    }
-                } else {
-                    (ch, false)
-                };
-
-                let piece_index = self.get_piece_index(piece_type, promoted)?;
-                let square_index = rank_idx * 9 + file_idx;
-
-                hash ^= self.zobrist_pieces[square_index][piece_index];
-                file_idx += 1;
-            }
-        }
-
-        if file_idx != 9 {
-            return Err(anyhow!("Rank too short: expected 9 squares, got {}", file_idx));
-        }
-
-        Ok(hash)
-    }
-
-    /// Get piece index for Zobrist table
-    fn get_piece_index(&self, piece_type: char, promoted: bool) -> Result<usize> {
-        let base_index = match piece_type.to_ascii_uppercase() {
-            'P' => 0, // Pawn
-            'L' => 1, // Lance
-            'N' => 2, // Knight
-            'S' => 3, // Silver
-            'G' => 4, // Gold
-            'B' => 5, // Bishop
-            'R' => 6, // Rook
-            'K' => 7, // King
-            _ => return Err(anyhow!("Invalid piece type: {}", piece_type)),
-        };
-
-        let player_offset = if piece_type.is_ascii_uppercase() {
-            0
-        } else {
-            8
-        };
-        let promotion_offset = if promoted { 16 } else { 0 };
-
-        Ok(base_index + player_offset + promotion_offset)
-    }
-
-    /// Hash the hands (captured pieces) part of SFEN
-    fn hash_hands(&self, hands: &str) -> Result<u64> {
-        if hands == "-" {
-            return Ok(0); // No pieces in hand
-        }
-
-        let mut hash = 0u64;
-        let mut chars = hands.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            let (count, piece_ch) = if ch.is_ascii_digit() {
-                // Multiple pieces (e.g., "2P" for 2 pawns, "10P" for 10 pawns)
-                let mut count_str = String::new();
-                count_str.push(ch);
-
-                // Collect all consecutive digits
-                while let Some(&next_ch) = chars.peek() {
-                    if next_ch.is_ascii_digit() {
-                        count_str.push(chars.next().unwrap());
-                    } else {
-                        break;
-                    }
-                }
-
-                let count: usize =
-                    count_str.parse().map_err(|_| anyhow!("Invalid piece count: {}", count_str))?;
-
-                if let Some(piece_ch) = chars.next() {
-                    (count, piece_ch)
-                } else {
-                    return Err(anyhow!("Invalid hand format: digit without piece"));
-                }
-            } else {
-                // Single piece (e.g., "P" for 1 pawn)
-                (1, ch)
-            };
-
-            // Get hand piece index (0-13: 7 piece types * 2 players)
-            let hand_index = self.get_hand_piece_index(piece_ch)?;
-
-            // XOR hash for each piece in hand
-            // Use different hash for different counts
-            for i in 0..count {
-                // Rotate the base hash to get different values for different counts
-                let piece_hash = self.zobrist_hand[hand_index].rotate_left((i * 7) as u32);
-                hash ^= piece_hash;
-            }
-        }
-
-        Ok(hash)
-    }
-
-    /// Get hand piece index for Zobrist table
-    fn get_hand_piece_index(&self, piece_type: char) -> Result<usize> {
-        let base_index = match piece_type.to_ascii_uppercase() {
-            'P' => 0, // Pawn
-            'L' => 1, // Lance
-            'N' => 2, // Knight
-            'S' => 3, // Silver
-            'G' => 4, // Gold
-            'B' => 5, // Bishop
-            'R' => 6, // Rook
-            _ => return Err(anyhow!("Invalid hand piece type: {}", piece_type)),
-        };
-
-        let player_offset = if piece_type.is_ascii_uppercase() {
-            0
-        } else {
-            7
-        };
-        Ok(base_index + player_offset)
-    }
-
-    /// Simple pseudo-random number generator for Zobrist table initialization
-    fn next_random(state: &mut u64) -> u64 {
-        *state ^= *state << 13;
-        *state ^= *state >> 7;
-        *state ^= *state << 17;
-        *state
-    }
 }
 
 impl Default for PositionHasher {
@@ -333,30 +208,25 @@ impl Default for PositionHasher {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_zobrist_random_generation() {
-        let mut state = 0x123456789abcdef0u64;
-        let val1 = PositionHasher::next_random(&mut state);
-        let val2 = PositionHasher::next_random(&mut state);
-
-        assert_ne!(val1, val2);
-        assert_ne!(val1, 0);
-        assert_ne!(val2, 0);
-    }
-
     #[test]
     fn test_piece_index_calculation() {
         let hasher = PositionHasher::new();
+        let keys = ZobristKeys::get();
+
+        // A single piece on 9a hashes to its table entry
+        let piece_hash = |piece: &str| {
+            hasher.hash_sfen_position(&format!("{piece}8/9/9/9/9/9/9/9/9 b - 1")).unwrap()
+        };
 
         // Test basic pieces
-        assert_eq!(hasher.get_piece_index('P', false).unwrap(), 0); // Black pawn
-        assert_eq!(hasher.get_piece_index('p', false).unwrap(), 8); // White pawn
-        assert_eq!(hasher.get_piece_index('K', false).unwrap(), 7); // Black king
-        assert_eq!(hasher.get_piece_index('k', false).unwrap(), 15); // White king
+        assert_eq!(piece_hash("P"), keys.pieces[0][0]); // Black pawn
+        assert_eq!(piece_hash("p"), keys.pieces[0][8]); // White pawn
+        assert_eq!(piece_hash("K"), keys.pieces[0][7]); // Black king
+        assert_eq!(piece_hash("k"), keys.pieces[0][15]); // White king
 
         // Test promoted pieces
-        assert_eq!(hasher.get_piece_index('P', true).unwrap(), 16); // Promoted black pawn
-        assert_eq!(hasher.get_piece_index('p', true).unwrap(), 24); // Promoted white pawn
+        assert_eq!(piece_hash("+P"), keys.pieces[0][16]); // Promoted black pawn
+        assert_eq!(piece_hash("+p"), keys.pieces[0][24]); // Promoted white pawn
     }
 
     #[test]
@@ -375,6 +245,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_side_to_move_scheme() {
+        let hasher = PositionHasher::with_scheme(HashScheme::WithSideToMove);
+
+        let sfen_black = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+        let sfen_white = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1";
+
+        let hash_black = hasher.hash_sfen_position(sfen_black).unwrap();
+        let hash_white = hasher.hash_sfen_position(sfen_white).unwrap();
+        assert_ne!(hash_black, hash_white);
+
+        // Black to move keeps the legacy key
+        assert_eq!(hash_black, PositionHasher::hash_position(sfen_black).unwrap());
+        assert!(hasher
+            .hash_sfen_position("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL x - 1")
+            .is_err());
+
+        for scheme in [
+            HashScheme::BoardAndHands,
+            HashScheme::WithSideToMove,
+            HashScheme::PerCountHands,
+        ] {
+            assert_eq!(HashScheme::from_version(scheme.version()), Some(scheme));
+            let position = Position::from_sfen(sfen_white).unwrap();
+            assert_eq!(
+                scheme.position_key(&position),
+                PositionHasher::hash_position_with(sfen_white, scheme).unwrap()
+            );
+        }
+        assert_eq!(HashScheme::from_version(0), None);
+    }
+
+    #[test]
+    fn test_per_count_hand_scheme() {
+        let hasher = PositionHasher::with_scheme(HashScheme::PerCountHands);
+        let sfen = "+B+B1Sg2nl/5kg2/4p2p1/3pspP1p/p6PP/1p1rP4/P1p2P2N/1PG1G4/LNK5R w 2P2p 30";
+
+        // The board and turn keys are shared with version 2, the hands are not
+        let hash = hasher.hash_sfen_position(sfen).unwrap();
+        assert_ne!(
+            hash,
+            PositionHasher::hash_position_with(sfen, HashScheme::WithSideToMove).unwrap()
+        );
+
+        let position = Position::from_sfen_unchecked(sfen).unwrap();
+        assert_eq!(HashScheme::PerCountHands.position_key(&position), hash);
+
+        let sfen_18 = "lnsgkgsnl/1r5b1/9/9/9/9/9/1B5R1/LNSGKGSNL b 18P 1";
+        let sfen_19 = "lnsgkgsnl/1r5b1/9/9/9/9/9/1B5R1/LNSGKGSNL b 19P 1";
+        assert!(hasher.hash_sfen_position(sfen_18).is_ok());
+        assert!(hasher.hash_sfen_position(sfen_19).is_err());
+    }
+
     #[test]
     fn test_different_hands_different_hash() {
         let hasher = PositionHasher::new();
@@ -6,6 +6,7 @@
 // SFEN (Shogi Forsyth-Edwards Notation) is a standard notation for representing shogi positions.
 
 use crate::opening_book::{RawMove, RawSfenEntry};
+use crate::shogi::Position;
 use anyhow::{anyhow, Result};
 
 /// Parser for YaneuraOu SFEN format files
@@ -55,7 +56,9 @@ impl SfenParser {
         // Otherwise, it should be a move line
         if self.current_position.is_some() {
             let move_data = self.parse_move_line(line)?;
-            self.current_position.as_mut().unwrap().moves.push(move_data);
+            if let Some(current) = self.current_position.as_mut() {
+                current.moves.push(move_data);
+            }
             Ok(None)
         } else {
             Err(anyhow!("Move line without position: {}", line))
@@ -75,21 +78,19 @@ impl SfenParser {
             return Err(anyhow!("Position line must start with 'sfen': {}", line));
         }
 
-        let position = parts[1].to_string();
-        let turn = parts[2]
-            .chars()
-            .next()
-            .ok_or_else(|| anyhow!("Invalid turn indicator: {}", parts[2]))?;
-        let hand = parts[3].to_string();
-        let move_count = parts[4]
-            .parse::<u32>()
-            .map_err(|_| anyhow!("Invalid move count: {}", parts[4]))?;
+        // Books may hold positions that fail validation; they are still keyed
+        let position = Position::from_sfen_unchecked(&parts[1..5].join(" "))
+            .map_err(|e| anyhow!("Invalid position line ({}): {}", e, line))?;
+
+        // Fields are taken from the canonical SFEN of the parsed position
+        let sfen = position.to_sfen();
+        let fields: Vec<&str> = sfen.split(' ').collect();
 
         Ok(RawSfenEntry {
-            position,
-            turn,
-            hand,
-            move_count,
+            position: fields[0].to_string(),
+            turn: fields[1].chars().next().expect("turn is b or w"),
+            hand: fields[2].to_string(),
+            move_count: position.ply(),
             moves: Vec::new(),
         })
     }
@@ -117,6 +118,7 @@ impl SfenParser {
             evaluation,
             depth,
             nodes,
+            ..Default::default()
         })
     }
 }
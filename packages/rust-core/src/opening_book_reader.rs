@@ -6,9 +6,18 @@ use std::collections::HashMap;
 use std::io::{self, Cursor, Read};
 use wasm_bindgen::prelude::*;
 
+use crate::opening_book::{
+    BookMoveSelector, CompactMove, HashScheme, IndexedBook, MoveEncoder, MoveStats,
+};
+
 pub struct OpeningBookReader {
+    /// 索引のない古い形式の定跡（読み込み時にすべて展開する）
     positions: HashMap<u64, Vec<BookMove>>,
+    /// 索引付きの定跡は解凍したバイト列のまま持ち、引くたびに二分探索する
+    indexed: Option<Vec<u8>>,
     loaded: bool,
+    /// ファイルのバージョンから決まるハッシュ方式
+    scheme: HashScheme,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +25,63 @@ pub struct BookMove {
     pub notation: String,
     pub evaluation: i16,
     pub depth: u8,
+    /// 指された回数（回数を持たない定跡ではどの手も 1）
+    #[serde(default = "default_play_count")]
+    pub play_count: u32,
+    /// 指した側から見た勝ち・引き分け・負けの局数（棋譜から作った定跡のみ）
+    #[serde(default)]
+    pub wins: u32,
+    #[serde(default)]
+    pub draws: u32,
+    #[serde(default)]
+    pub losses: u32,
+}
+
+fn default_play_count() -> u32 {
+    1
+}
+
+impl BookMove {
+    // 符号化された手を文字列に戻す（読めない手は invalid_<値> にする）
+    fn from_compact(mov: &CompactMove, stats: Option<&MoveStats>) -> Self {
+        let stats = stats.copied().unwrap_or(MoveStats {
+            play_count: 1,
+            ..MoveStats::default()
+        });
+        BookMove {
+            notation: MoveEncoder::decode_move(mov.move_encoded)
+                .unwrap_or_else(|_| format!("invalid_{}", mov.move_encoded)),
+            evaluation: mov.evaluation,
+            depth: mov.depth,
+            play_count: stats.play_count,
+            wins: stats.wins,
+            draws: stats.draws,
+            losses: stats.losses,
+        }
+    }
+
+    /// 指した側の勝率（結果の記録がなければ None）
+    pub fn win_rate(&self) -> Option<f64> {
+        let games = self.wins as u64 + self.draws as u64 + self.losses as u64;
+        (games > 0).then(|| self.wins as f64 / games as f64)
+    }
+}
+
+// 索引付きの定跡から 1 局面分の手だけを取り出す（壊れた局面は手なしとして扱う）
+pub(crate) fn find_indexed_moves(book: &IndexedBook<'_>, hash: u64) -> Vec<BookMove> {
+    match book.find_entry(hash) {
+        Ok(entry) => entry
+            .map(|entry| {
+                let stats = |i| entry.move_stats.get(i);
+                let moves = entry.moves.iter().enumerate();
+                moves.map(|(i, mov)| BookMove::from_compact(mov, stats(i))).collect()
+            })
+            .unwrap_or_default(),
+        Err(e) => {
+            log::warn!("Broken book entry for hash {hash}: {e:#}");
+            vec![]
+        }
+    }
 }
 
 impl Default for OpeningBookReader {
@@ -28,18 +94,28 @@ impl OpeningBookReader {
     pub fn new() -> Self {
         Self {
             positions: HashMap::new(),
+            indexed: None,
             loaded: false,
+            scheme: HashScheme::default(),
         }
     }
 
     pub fn position_count(&self) -> usize {
-        self.positions.len()
+        match self.indexed_book() {
+            Some(book) => book.len(),
+            None => self.positions.len(),
+        }
     }
 
     pub fn is_loaded(&self) -> bool {
         self.loaded
     }
 
+    /// Hashing scheme of the loaded book, used by the lookups
+    pub fn hash_scheme(&self) -> HashScheme {
+        self.scheme
+    }
+
     pub fn load_data(&mut self, compressed_data: &[u8]) -> Result<String, String> {
         if compressed_data.is_empty() {
             self.loaded = true;
@@ -52,10 +128,10 @@ impl OpeningBookReader {
             .map_err(|e| format!("Failed to decompress: {e}"))?;
 
         // バイナリデータをパース
-        self.parse_binary_data(&decompressed)?;
+        self.load_binary_data(decompressed)?;
 
         self.loaded = true;
-        Ok(format!("Loaded {} positions", self.positions.len()))
+        Ok(format!("Loaded {} positions", self.position_count()))
     }
 
     fn decompress_data(&self, compressed: &[u8]) -> Result<Vec<u8>, io::Error> {
@@ -65,11 +141,37 @@ impl OpeningBookReader {
         Ok(decompressed)
     }
 
-    fn parse_binary_data(&mut self, data: &[u8]) -> Result<(), String> {
-        use crate::opening_book::MoveEncoder;
+    // 索引付きの定跡の検索用ビュー
+    fn indexed_book(&self) -> Option<IndexedBook<'_>> {
+        self.indexed.as_deref().and_then(|data| IndexedBook::parse(data).ok())
+    }
+
+    // 索引付きならヘッダーと索引の範囲だけ確かめてそのまま持つ
+    fn load_binary_data(&mut self, data: Vec<u8>) -> Result<(), String> {
+        self.positions.clear();
+        self.indexed = None;
+
+        if !IndexedBook::is_indexed(&data) {
+            return self.parse_binary_data(&data);
+        }
 
+        let book = IndexedBook::parse(&data).map_err(|e| format!("Invalid book: {e:#}"))?;
+        log::debug!(
+            "Found indexed book: scheme={:?}, position_count={}",
+            book.scheme(),
+            book.len()
+        );
+        self.scheme = book.scheme();
+        self.indexed = Some(data);
+        Ok(())
+    }
+
+    fn parse_binary_data(&mut self, data: &[u8]) -> Result<(), String> {
         let mut cursor = Cursor::new(data);
 
+        // ヘッダーのない古いファイルは手番を含まないハッシュ
+        self.scheme = HashScheme::BoardAndHands;
+
         // ファイルヘッダーを読み込み（16バイト）
         if data.len() >= 16 {
             let mut file_header = [0u8; 16];
@@ -82,11 +184,17 @@ impl OpeningBookReader {
                 // ファイルヘッダーが存在する場合はスキップ済み
                 let version = u32::from_le_bytes(file_header[4..8].try_into().unwrap());
                 let position_count = u32::from_le_bytes(file_header[8..12].try_into().unwrap());
-                // ファイルヘッダー情報（必要に応じてログ出力）
-                println!("Found SFEN header: version={version}, position_count={position_count}");
+                // ファイルヘッダー情報（標準出力は USI エンジンが使うのでログに出す）
+                log::debug!(
+                    "Found SFEN header: version={version}, position_count={position_count}"
+                );
+
+                // バージョンからハッシュ方式を決める
+                self.scheme = HashScheme::from_version(version)
+                    .ok_or_else(|| format!("Unsupported book version: {version}"))?;
             } else {
                 // ファイルヘッダーがない場合は位置を戻す
-                println!("No SFEN header found, parsing from beginning");
+                log::debug!("No SFEN header found, parsing from beginning");
                 cursor.set_position(0);
             }
         }
@@ -107,7 +215,7 @@ impl OpeningBookReader {
 
             // デバッグ用（必要に応じてコメントアウト）
             if positions_read < 3 {
-                println!(
+                log::debug!(
                     "Position {}: hash={}, move_count={}, cursor_pos={}",
                     positions_read,
                     position_hash,
@@ -134,41 +242,45 @@ impl OpeningBookReader {
                     )
                 })?;
 
-                let move_encoded = u16::from_le_bytes(move_buf[0..2].try_into().unwrap());
-                let evaluation = i16::from_le_bytes(move_buf[2..4].try_into().unwrap());
-                let depth = move_buf[4];
-
-                let move_notation = MoveEncoder::decode_move(move_encoded)
-                    .unwrap_or_else(|_| format!("invalid_{move_encoded}"));
-
-                moves.push(BookMove {
-                    notation: move_notation,
-                    evaluation,
-                    depth,
-                });
+                let mov = CompactMove {
+                    move_encoded: u16::from_le_bytes(move_buf[0..2].try_into().unwrap()),
+                    evaluation: i16::from_le_bytes(move_buf[2..4].try_into().unwrap()),
+                    depth: move_buf[4],
+                    reserved: move_buf[5],
+                };
+                moves.push(BookMove::from_compact(&mov, None));
             }
 
             self.positions.insert(position_hash, moves);
             positions_read += 1;
         }
 
-        println!("Successfully parsed {positions_read} positions");
+        log::debug!("Successfully parsed {positions_read} positions");
 
         Ok(())
     }
 
     pub fn find_moves_by_hash(&self, hash: u64) -> Vec<BookMove> {
-        self.positions.get(&hash).cloned().unwrap_or_default()
+        match self.indexed_book() {
+            Some(book) => find_indexed_moves(&book, hash),
+            None => self.positions.get(&hash).cloned().unwrap_or_default(),
+        }
     }
 
     pub fn find_moves(&self, sfen: &str) -> Vec<BookMove> {
         use crate::opening_book::PositionHasher;
 
-        match PositionHasher::hash_position(sfen) {
+        match PositionHasher::hash_position_with(sfen, self.scheme) {
             Ok(hash) => self.find_moves_by_hash(hash),
             Err(_) => vec![],
         }
     }
+
+    /// Probe with the position's incrementally maintained key, without
+    /// going through SFEN
+    pub fn find_moves_for_position(&self, position: &crate::shogi::Position) -> Vec<BookMove> {
+        self.find_moves_by_hash(self.scheme.position_key(position))
+    }
 }
 
 // WebAssembly bindings
@@ -203,6 +315,14 @@ impl OpeningBookReaderWasm {
         serde_json::to_string(&moves).unwrap_or_else(|_| "[]".to_string())
     }
 
+    // 定跡手から selector の方針で 1 手選ぶ（JSON、定跡手がなければ undefined）
+    #[wasm_bindgen]
+    pub fn select_move(&self, sfen: &str, selector: &mut BookMoveSelector) -> Option<String> {
+        let moves = self.inner.find_moves(sfen);
+        let chosen = selector.select(&moves)?;
+        serde_json::to_string(chosen).ok()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn position_count(&self) -> usize {
         self.inner.position_count()
@@ -247,9 +367,12 @@ mod tests {
         data
     }
 
+    /// テスト用の位置データ (hash, [(move, eval, depth)])
+    type TestPosition = (u64, Vec<(u16, i16, u8)>);
+
     /// テスト用の完全なバイナリデータを作成
     fn create_test_binary_data(
-        positions: Vec<(u64, Vec<(u16, i16, u8)>)>, // (hash, moves)
+        positions: Vec<TestPosition>, // (hash, moves)
         with_file_header: bool,
     ) -> Vec<u8> {
         let mut data = Vec::new();
@@ -381,6 +504,10 @@ mod tests {
                 notation: "7g7f".to_string(),
                 evaluation: 50,
                 depth: 10,
+                play_count: 1,
+                wins: 0,
+                draws: 0,
+                losses: 0,
             }],
         );
 
@@ -392,6 +519,36 @@ mod tests {
         assert_eq!(moves[0].notation, "7g7f");
     }
 
+    #[test]
+    fn test_find_moves_for_position() {
+        use crate::opening_book::PositionHasher;
+        use crate::shogi::Position;
+
+        // Arrange
+        let mut reader = OpeningBookReader::new();
+        let position = Position::startpos();
+        let hash = PositionHasher::hash_position(&position.to_sfen()).unwrap();
+        reader.positions.insert(
+            hash,
+            vec![BookMove {
+                notation: "2g2f".to_string(),
+                evaluation: 40,
+                depth: 8,
+                play_count: 1,
+                wins: 0,
+                draws: 0,
+                losses: 0,
+            }],
+        );
+
+        // Act
+        let moves = reader.find_moves_for_position(&position);
+
+        // Assert
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].notation, "2g2f");
+    }
+
     #[test]
     fn test_find_moves_with_real_hash() {
         use crate::opening_book::PositionHasher;
@@ -409,6 +566,10 @@ mod tests {
                 notation: "7g7f".to_string(),
                 evaluation: 50,
                 depth: 10,
+                play_count: 1,
+                wins: 0,
+                draws: 0,
+                losses: 0,
             }],
         );
 
@@ -472,6 +633,144 @@ mod tests {
         assert_eq!(moves[0].depth, 10);
     }
 
+    #[test]
+    fn test_hash_scheme_selected_by_file_version() {
+        use crate::opening_book::{MoveEncoder, PositionHasher};
+        use crate::shogi::Position;
+
+        let sfen_black = "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL b - 1";
+        let sfen_white = "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 1";
+        let move_3c3d = MoveEncoder::encode_move("3c3d").unwrap();
+
+        // 手番込みのハッシュ（バージョン2）
+        let hash =
+            PositionHasher::hash_position_with(sfen_white, HashScheme::WithSideToMove).unwrap();
+        let mut data = create_test_binary_data(vec![(hash, vec![(move_3c3d, 30, 10)])], true);
+        data[4..8].copy_from_slice(&2u32.to_le_bytes());
+
+        let mut reader = OpeningBookReader::new();
+        reader.parse_binary_data(&data).unwrap();
+        assert_eq!(reader.hash_scheme(), HashScheme::WithSideToMove);
+        assert_eq!(reader.find_moves(sfen_white).len(), 1);
+        assert!(reader.find_moves(sfen_black).is_empty());
+        let position = Position::from_sfen(sfen_white).unwrap();
+        assert_eq!(reader.find_moves_for_position(&position).len(), 1);
+
+        // 旧形式（バージョン1）は手番を区別しない
+        let hash = PositionHasher::hash_position(sfen_white).unwrap();
+        let data = create_test_binary_data(vec![(hash, vec![(move_3c3d, 30, 10)])], true);
+        let mut reader = OpeningBookReader::new();
+        reader.parse_binary_data(&data).unwrap();
+        assert_eq!(reader.hash_scheme(), HashScheme::BoardAndHands);
+        assert_eq!(reader.find_moves(sfen_white).len(), 1);
+        assert_eq!(reader.find_moves(sfen_black).len(), 1);
+
+        // 未知のバージョンは拒否
+        let mut data = create_test_binary_data(vec![], true);
+        data[4..8].copy_from_slice(&99u32.to_le_bytes());
+        assert!(OpeningBookReader::new().parse_binary_data(&data).is_err());
+    }
+
+    #[test]
+    fn test_indexed_book_is_searched_in_place() {
+        use crate::opening_book::{BinaryConverter, RawMove, RawSfenEntry};
+
+        let board = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL";
+        let entries: Vec<RawSfenEntry> = [('b', "7g7f", 50), ('w', "3c3d", -30)]
+            .into_iter()
+            .map(|(turn, notation, evaluation)| RawSfenEntry {
+                position: board.to_string(),
+                turn,
+                hand: "-".to_string(),
+                move_count: 1,
+                moves: vec![RawMove {
+                    move_notation: notation.to_string(),
+                    move_type: "none".to_string(),
+                    evaluation,
+                    depth: 10,
+                    nodes: 1000,
+                    ..Default::default()
+                }],
+            })
+            .collect();
+        let converter = BinaryConverter::with_scheme(HashScheme::WithSideToMove);
+        let mut data = Vec::new();
+        converter.write_binary(&entries, &mut data).unwrap();
+
+        let mut reader = OpeningBookReader::new();
+        // 古い形式を読んだ後でも索引付きの定跡に置き換わる
+        reader
+            .parse_binary_data(&create_test_binary_data(vec![(1, vec![])], false))
+            .unwrap();
+        reader.load_data(&converter.compress_data(&data).unwrap()).unwrap();
+
+        // 局面は展開せずに二分探索で引く
+        assert!(reader.positions.is_empty());
+        assert_eq!(reader.position_count(), 2);
+        assert_eq!(reader.hash_scheme(), HashScheme::WithSideToMove);
+        assert!(reader.find_moves_by_hash(1).is_empty());
+
+        let moves = reader.find_moves(&format!("{board} w - 2"));
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].notation, "3c3d");
+        assert_eq!(moves[0].evaluation, -30);
+        let moves = reader.find_moves(&format!("{board} b - 1"));
+        assert_eq!(moves[0].notation, "7g7f");
+
+        // 索引が収まらないファイルは拒否
+        data[8..12].copy_from_slice(&1000u32.to_le_bytes());
+        let compressed = converter.compress_data(&data).unwrap();
+        assert!(OpeningBookReader::new().load_data(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_move_stats_surface_in_book_moves() {
+        use crate::opening_book::{BinaryConverter, MoveStats, RawMove, RawSfenEntry};
+
+        let raw_move = |notation: &str, play_count, wins, draws, losses| RawMove {
+            move_notation: notation.to_string(),
+            move_type: "none".to_string(),
+            evaluation: 0,
+            depth: 0,
+            nodes: 0,
+            stats: Some(MoveStats {
+                play_count,
+                wins,
+                draws,
+                losses,
+            }),
+        };
+        let entry = RawSfenEntry {
+            position: "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL".to_string(),
+            turn: 'b',
+            hand: "-".to_string(),
+            move_count: 1,
+            moves: vec![
+                raw_move("7g7f", 1234, 666, 12, 556),
+                raw_move("2g2f", 10, 0, 0, 0),
+            ],
+        };
+        let converter = BinaryConverter::new();
+        let mut data = Vec::new();
+        converter.write_binary(&[entry], &mut data).unwrap();
+
+        let mut reader = OpeningBookReader::new();
+        reader.load_data(&converter.compress_data(&data).unwrap()).unwrap();
+        let moves =
+            reader.find_moves("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1");
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].play_count, 1234);
+        assert_eq!((moves[0].wins, moves[0].draws, moves[0].losses), (666, 12, 556));
+        assert!((moves[0].win_rate().unwrap() - 666.0 / 1234.0).abs() < 1e-9);
+        // 結果のない対局だけなら勝率は出さない
+        assert_eq!(moves[1].play_count, 10);
+        assert_eq!(moves[1].win_rate(), None);
+
+        // JSON にも載る
+        let json = serde_json::to_string(&moves[0]).unwrap();
+        assert!(json.contains("\"play_count\":1234") && json.contains("\"wins\":666"));
+    }
+
     #[test]
     fn test_initial_position_hash_calculation() {
         use crate::opening_book::PositionHasher;
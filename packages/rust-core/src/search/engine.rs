@@ -0,0 +1,868 @@
+use crate::clock::{Clock, DefaultClock};
+use crate::evaluation::{evaluate, NnueEvaluator, NnueNetwork};
+use crate::search::ordering::{pick_next, MoveOrdering};
+use crate::search::strength::EngineStrength;
+use crate::search::tt::{Bound, TranspositionTable};
+use crate::shogi::{Move, Piece, PieceType, Player, Position, ZobristKeys};
+use crate::util::rng::{mix64, Rng};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+// 評価値は歩 1 枚 = 100。詰みは ±(MATE_SCORE - 詰みまでの手数)
+pub const MATE_SCORE: i32 = 32_000;
+// 探索する最大の手数（root からの距離）
+pub const MAX_PLY: usize = 128;
+// 絶対値がこれ以上の評価値は詰み
+pub const MATE_IN_MAX_PLY: i32 = MATE_SCORE - MAX_PLY as i32;
+// 反復深化の最大深さ
+pub const MAX_DEPTH: u8 = 64;
+// 置換表の既定サイズ
+pub const DEFAULT_HASH_MB: usize = 16;
+
+const INFINITE: i32 = MATE_SCORE + 1;
+const DRAW_SCORE: i32 = 0;
+// 時計を確認する間隔（ノード数）
+const CHECK_INTERVAL: u64 = 1024;
+
+// 探索の打ち切り条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    // 反復深化の最大深さ
+    pub max_depth: u8,
+    // 持ち時間（None なら停止フラグが立つか最大深さまで）
+    pub time_ms: Option<u32>,
+    pub node_limit: Option<u64>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            max_depth: MAX_DEPTH,
+            time_ms: None,
+            node_limit: None,
+        }
+    }
+}
+
+// 探索結果（反復深化で最後に完了した深さのもの）
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    // 手番側から見た評価値
+    pub score: i32,
+    pub depth: u8,
+    pub node_count: u32,
+    pub elapsed_ms: u32,
+    best_move: Option<Move>,
+    pv: Vec<Move>,
+}
+
+#[wasm_bindgen]
+impl SearchResult {
+    // 最善手（USI形式、合法手がなければ undefined）
+    #[wasm_bindgen(getter)]
+    pub fn best_move(&self) -> Option<String> {
+        self.best_move.map(|mv| mv.to_usi())
+    }
+
+    // 読み筋（USI形式）
+    #[wasm_bindgen(getter)]
+    pub fn pv(&self) -> Vec<String> {
+        self.pv.iter().map(|mv| mv.to_usi()).collect()
+    }
+
+    // 詰みまでの手数（手番側が詰ますなら正、詰まされるなら負、詰みでなければ undefined）
+    #[wasm_bindgen(getter)]
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score >= MATE_IN_MAX_PLY {
+            Some(MATE_SCORE - self.score)
+        } else if self.score <= -MATE_IN_MAX_PLY {
+            Some(-(MATE_SCORE + self.score))
+        } else {
+            None
+        }
+    }
+}
+
+impl SearchResult {
+    pub fn best(&self) -> Option<Move> {
+        self.best_move
+    }
+
+    pub fn pv_moves(&self) -> &[Move] {
+        &self.pv
+    }
+}
+
+// 反復深化のアルファベータ探索
+//
+// PVS（主要変化以外は null window で探索し、超えたら再探索）、置換表、
+// キラー手・ヒストリーによる手の並べ替え、null move pruning、
+// late move reduction、駒を取る手だけの静止探索を行う。
+// 置換表とヒストリーは探索をまたいで保持する。
+// NNUE を設定すればそれで、なければ手作りの評価関数で評価する。
+// EngineStrength で深さを抑えたり、最善手以外を選ばせたりできる
+//
+// 読み筋の中で同じ局面が現れたら千日手（引き分け）とするが、その間ずっと
+// 王手をかけ続けていた側の負け（連続王手の千日手）とする。root より前の
+// 棋譜は分からないので、対局中の千日手は検出しない
+pub struct SearchEngine<C: Clock = DefaultClock> {
+    tt: TranspositionTable,
+    ordering: MoveOrdering,
+    nnue: Option<NnueEvaluator>,
+    strength: EngineStrength,
+    rng: Rng,
+    // 評価値のノイズの種（探索ごとに変える）
+    noise_seed: u64,
+    clock: C,
+    stop: Arc<AtomicBool>,
+    start_time: f64,
+    deadline: Option<f64>,
+    node_limit: Option<u64>,
+    nodes: u64,
+    stopped: bool,
+    // 深さ 1 を終えるまでは打ち切らない（必ず手を返すため）
+    can_stop: bool,
+    // 手数ごとの読み筋
+    pv: Vec<Vec<Move>>,
+    // root からの各局面のキー（千日手の検出用）
+    keys: Vec<u64>,
+    // root からの各局面で手番側が王手されているか（連続王手の千日手の判定用）
+    checks: Vec<bool>,
+}
+
+impl SearchEngine {
+    // プラットフォーム既定の時計を使う（ネイティブは Instant、wasm は performance）
+    pub fn new(hash_mb: usize) -> Self {
+        Self::with_clock(hash_mb, DefaultClock::default())
+    }
+}
+
+impl Default for SearchEngine {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl<C: Clock> SearchEngine<C> {
+    pub fn with_clock(hash_mb: usize, clock: C) -> Self {
+        SearchEngine {
+            tt: TranspositionTable::new(hash_mb),
+            ordering: MoveOrdering::new(),
+            nnue: None,
+            strength: EngineStrength::default(),
+            rng: Rng::from_entropy(),
+            noise_seed: 0,
+            clock,
+            stop: Arc::new(AtomicBool::new(false)),
+            start_time: 0.0,
+            deadline: None,
+            node_limit: None,
+            nodes: 0,
+            stopped: false,
+            can_stop: false,
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            keys: vec![0; MAX_PLY + 1],
+            checks: vec![false; MAX_PLY + 1],
+        }
+    }
+
+    // 外部から探索を止めるフラグ（USI の stop など）。探索側では戻さない
+    pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    // 置換表を作り直す（内容は失われる）
+    pub fn resize_hash(&mut self, hash_mb: usize) {
+        self.tt = TranspositionTable::new(hash_mb);
+    }
+
+    // 評価に使う NNUE（None なら手作りの評価関数）
+    pub fn set_nnue(&mut self, network: Option<Arc<NnueNetwork>>) {
+        self.nnue = network.map(NnueEvaluator::new);
+    }
+
+    // 棋力の設定（既定は手加減なし）
+    pub fn set_strength(&mut self, strength: EngineStrength) {
+        self.strength = strength;
+    }
+
+    pub fn strength(&self) -> &EngineStrength {
+        &self.strength
+    }
+
+    // 手の選択とノイズの乱数を固定する（既定は起動ごとに変わる）
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    // 新しい対局の前に置換表とヒストリーを消す
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.ordering.clear();
+    }
+
+    pub fn search(&mut self, position: &Position, limits: &SearchLimits) -> SearchResult {
+        self.search_with_info(position, limits, |_| {})
+    }
+
+    // 深さを 1 つ終えるごとに on_iteration にその時点の結果を渡す
+    pub fn search_with_info(
+        &mut self,
+        position: &Position,
+        limits: &SearchLimits,
+        mut on_iteration: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.start(limits);
+        let mut root = position.clone();
+        let root_moves = root.legal_moves();
+        if let Some(nnue) = &mut self.nnue {
+            nnue.reset(&root);
+        }
+
+        let mut result = SearchResult {
+            score: 0,
+            depth: 0,
+            node_count: 0,
+            elapsed_ms: 0,
+            best_move: root_moves.first().copied(),
+            pv: Vec::new(),
+        };
+        if root_moves.is_empty() {
+            result.score = -MATE_SCORE;
+            return result;
+        }
+
+        let max_depth = limits.max_depth.min(self.strength.max_depth);
+        for depth in 1..=max_depth.clamp(1, MAX_DEPTH) {
+            let score = self.alpha_beta(&mut root, depth as i32, -INFINITE, INFINITE, 0, false);
+            if self.stopped {
+                break;
+            }
+            self.can_stop = true;
+
+            result.score = score;
+            result.depth = depth;
+            result.pv = self.pv[0].clone();
+            result.best_move = result.pv.first().copied().or(result.best_move);
+            result.node_count = self.nodes.min(u32::MAX as u64) as u32;
+            result.elapsed_ms = self.elapsed_ms();
+            on_iteration(&result);
+
+            // 詰みを読み切った、手が 1 つしかない、次の深さを終える時間がない
+            let mate_found =
+                score.abs() >= MATE_IN_MAX_PLY && MATE_SCORE - score.abs() <= depth as i32;
+            let half_time_used = self
+                .deadline
+                .is_some_and(|deadline| self.now() * 2.0 >= deadline + self.start_time);
+            if mate_found || root_moves.len() == 1 || half_time_used {
+                break;
+            }
+        }
+        self.choose_move(&mut root, &root_moves, &mut result);
+
+        result.node_count = self.nodes.min(u32::MAX as u64) as u32;
+        result.elapsed_ms = self.elapsed_ms();
+        result
+    }
+
+    fn start(&mut self, limits: &SearchLimits) {
+        self.start_time = self.now();
+        self.deadline = limits.time_ms.map(|time_ms| self.start_time + time_ms as f64);
+        let strength_limit = self.strength.node_limit.map(u64::from);
+        self.node_limit = limits.node_limit.into_iter().chain(strength_limit).min();
+        self.noise_seed = self.rng.next_u64();
+        self.nodes = 0;
+        self.stopped = false;
+        self.can_stop = false;
+        self.tt.new_search();
+        self.ordering.clear_killers();
+    }
+
+    fn now(&self) -> f64 {
+        self.clock.now_ms()
+    }
+
+    fn elapsed_ms(&self) -> u32 {
+        (self.now() - self.start_time) as u32
+    }
+
+    fn check_stop(&mut self) {
+        if !self.can_stop || self.stopped {
+            return;
+        }
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            self.stopped = true;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        if self.nodes.is_multiple_of(CHECK_INTERVAL)
+            && self.deadline.is_some_and(|deadline| self.now() >= deadline)
+        {
+            self.stopped = true;
+        }
+    }
+
+    fn alpha_beta(
+        &mut self,
+        position: &mut Position,
+        mut depth: i32,
+        mut alpha: i32,
+        mut beta: i32,
+        ply: usize,
+        allow_null: bool,
+    ) -> i32 {
+        let in_check = position.in_check();
+        // 王手をかけられたら 1 手延長
+        if in_check {
+            depth += 1;
+        }
+        if depth <= 0 {
+            return self.quiescence(position, alpha, beta, ply);
+        }
+
+        self.nodes += 1;
+        self.check_stop();
+        if self.stopped {
+            return 0;
+        }
+        self.pv[ply].clear();
+
+        let key = search_key(position);
+        self.checks[ply] = in_check;
+        if ply > 0 {
+            if let Some(score) = self.repetition_score(key, ply) {
+                return score;
+            }
+            // これより短い詰みが見つかっていれば探索しない
+            alpha = alpha.max(-MATE_SCORE + ply as i32);
+            beta = beta.min(MATE_SCORE - ply as i32 - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(position);
+        }
+        self.keys[ply] = key;
+
+        let pv_node = beta - alpha > 1;
+        let entry = self.tt.probe(key);
+        let tt_move = entry.and_then(|entry| entry.best_move);
+        if let Some(entry) = entry.filter(|entry| !pv_node && entry.depth >= depth) {
+            let score = score_from_tt(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+
+        // null move: 手番を渡しても beta を超えるならこの局面は十分に良い
+        if !pv_node && !in_check && allow_null && depth >= 3 && self.evaluate(position) >= beta {
+            let reduction = 2 + depth / 4;
+            let us = position.side_to_move();
+            position.set_side_to_move(us.opponent());
+            let score =
+                -self.alpha_beta(position, depth - 1 - reduction, -beta, -beta + 1, ply + 1, false);
+            position.set_side_to_move(us);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                // 詰みの値は手番を渡した局面のものなので信用しない
+                return if score >= MATE_IN_MAX_PLY {
+                    beta
+                } else {
+                    score
+                };
+            }
+        }
+
+        let us = position.side_to_move();
+        let mut moves = position.pseudo_legal_moves();
+        let mut scores = self.ordering.score_moves(position, &moves, tt_move, ply);
+        let original_alpha = alpha;
+        let mut best_score = -INFINITE;
+        let mut best_move = None;
+        let mut legal = 0;
+
+        let mut index = 0;
+        while let Some(mv) = pick_next(&mut moves, &mut scores, index) {
+            index += 1;
+            let quiet = position.piece_at(mv.to()).is_none() && !mv.is_promotion();
+            let killer = self.ordering.is_killer(ply, mv);
+
+            let captured = self.do_move(position, mv);
+            if !is_legal_after(position, mv, us) {
+                self.undo_move(position, mv, captured);
+                continue;
+            }
+            legal += 1;
+
+            let gives_check = position.in_check();
+            let new_depth = depth - 1;
+            let score = if legal == 1 {
+                -self.alpha_beta(position, new_depth, -beta, -alpha, ply + 1, true)
+            } else {
+                // 後ろの方の静かな手は浅く読み、alpha を超えたら読み直す
+                let reduction =
+                    if depth >= 3 && legal > 3 && quiet && !in_check && !gives_check && !killer {
+                        (1 + (legal > 8) as i32 + (depth >= 6) as i32).min(new_depth - 1)
+                    } else {
+                        0
+                    };
+                let mut score = -self.alpha_beta(
+                    position,
+                    new_depth - reduction,
+                    -alpha - 1,
+                    -alpha,
+                    ply + 1,
+                    true,
+                );
+                if score > alpha && reduction > 0 {
+                    score =
+                        -self.alpha_beta(position, new_depth, -alpha - 1, -alpha, ply + 1, true);
+                }
+                if score > alpha && score < beta {
+                    score = -self.alpha_beta(position, new_depth, -beta, -alpha, ply + 1, true);
+                }
+                score
+            };
+            self.undo_move(position, mv, captured);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, mv);
+                    if alpha >= beta {
+                        if quiet {
+                            self.ordering.record_cutoff(position, mv, ply, depth);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        // 将棋では指せる手がなければ負け
+        if legal == 0 {
+            return -MATE_SCORE + ply as i32;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, best_move, score_to_tt(best_score, ply), depth, bound);
+
+        best_score
+    }
+
+    // 静止探索（駒を取る手だけ読む。王手されているときは全ての応手を読む）
+    fn quiescence(
+        &mut self,
+        position: &mut Position,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        self.nodes += 1;
+        self.check_stop();
+        if self.stopped {
+            return 0;
+        }
+        self.pv[ply].clear();
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(position);
+        }
+
+        let us = position.side_to_move();
+        let (mut moves, mut best_score) = if position.in_check() {
+            (position.evasion_moves(), -MATE_SCORE + ply as i32)
+        } else {
+            let stand_pat = self.evaluate(position);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            (position.capture_moves(), stand_pat)
+        };
+        let mut scores = self.ordering.score_moves(position, &moves, None, ply);
+
+        let mut index = 0;
+        while let Some(mv) = pick_next(&mut moves, &mut scores, index) {
+            index += 1;
+            let captured = self.do_move(position, mv);
+            if position.is_in_check(us) {
+                self.undo_move(position, mv, captured);
+                continue;
+            }
+            let score = -self.quiescence(position, -beta, -alpha, ply + 1);
+            self.undo_move(position, mv, captured);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, mv);
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        best_score
+    }
+
+    // 手番側から見た評価値（NNUE は玉が欠けた局面を評価できない）
+    fn evaluate(&self, position: &Position) -> i32 {
+        let score = self
+            .nnue
+            .as_ref()
+            .and_then(|nnue| nnue.evaluate(position))
+            .unwrap_or_else(|| evaluate(position));
+        (score + self.noise(position)).clamp(-MATE_IN_MAX_PLY + 1, MATE_IN_MAX_PLY - 1)
+    }
+
+    // 棋力を落とすためのノイズ（同じ探索の中では局面ごとに同じ値）
+    fn noise(&self, position: &Position) -> i32 {
+        let width = self.strength.eval_noise as u64;
+        if width == 0 {
+            return 0;
+        }
+        let hash = mix64(search_key(position) ^ self.noise_seed);
+        (hash % (2 * width + 1)) as i64 as i32 - width as i32
+    }
+
+    // 棋力の設定に従って、探索した最善手の代わりに別の手を選ぶことがある
+    fn choose_move(&mut self, root: &mut Position, root_moves: &[Move], result: &mut SearchResult) {
+        let Some(best) = result.best_move else {
+            return;
+        };
+        if root_moves.len() < 2 {
+            return;
+        }
+
+        let blunder_percent = self.strength.blunder_percent as u64;
+        if blunder_percent > 0 && self.rng.below(100) < blunder_percent {
+            let others: Vec<Move> = root_moves.iter().copied().filter(|&mv| mv != best).collect();
+            let mv = others[self.rng.below(others.len() as u64) as usize];
+            result.best_move = Some(mv);
+            result.pv = vec![mv];
+            return;
+        }
+
+        let out_of_time = self.stop.load(Ordering::Relaxed)
+            || self.deadline.is_some_and(|deadline| self.now() >= deadline);
+        if self.strength.random_margin == 0 || result.depth == 0 || out_of_time {
+            return;
+        }
+        let threshold = result.score - self.strength.random_margin as i32;
+        let candidates = self.near_best_moves(root, root_moves, best, threshold, result.depth);
+        let mv = candidates[self.rng.below(candidates.len() as u64) as usize];
+        if mv != best {
+            result.best_move = Some(mv);
+            result.pv = vec![mv];
+        }
+    }
+
+    // 最善手と、評価値が threshold 以上ある手（null window で確かめる）
+    fn near_best_moves(
+        &mut self,
+        root: &mut Position,
+        root_moves: &[Move],
+        best: Move,
+        threshold: i32,
+        depth: u8,
+    ) -> Vec<Move> {
+        // ノード数の上限で止まった後でも全ての手を比べられるように外す
+        self.stopped = false;
+        self.node_limit = None;
+
+        let mut candidates = vec![best];
+        for &mv in root_moves.iter().filter(|&&mv| mv != best) {
+            let captured = self.do_move(root, mv);
+            let score =
+                -self.alpha_beta(root, depth as i32 - 1, -threshold, -threshold + 1, 1, true);
+            self.undo_move(root, mv, captured);
+            if self.stopped {
+                break;
+            }
+            if score >= threshold {
+                candidates.push(mv);
+            }
+        }
+        candidates
+    }
+
+    // NNUE のアキュムレータも一緒に進める
+    fn do_move(&mut self, position: &mut Position, mv: Move) -> Option<Piece> {
+        match &mut self.nnue {
+            Some(nnue) => nnue.do_move(position, mv),
+            None => position.do_move(mv),
+        }
+    }
+
+    fn undo_move(&mut self, position: &mut Position, mv: Move, captured: Option<Piece>) {
+        match &mut self.nnue {
+            Some(nnue) => nnue.undo_move(position, mv, captured),
+            None => position.undo_move(mv, captured),
+        }
+    }
+
+    // 同じ手番の局面が読み筋の中に既に出ていれば千日手として扱う
+    // （王手をかけ続けた側の負け、それ以外は引き分け）
+    fn repetition_score(&self, key: u64, ply: usize) -> Option<i32> {
+        let earlier =
+            (0..ply).rev().skip(1).step_by(2).find(|&earlier| self.keys[earlier] == key)?;
+        let checked_throughout = |first: usize| (first..=ply).step_by(2).all(|p| self.checks[p]);
+        Some(if checked_throughout(earlier + 2) {
+            MATE_SCORE - ply as i32
+        } else if checked_throughout(earlier + 1) {
+            -MATE_SCORE + ply as i32
+        } else {
+            DRAW_SCORE
+        })
+    }
+
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        let line = &mut head[ply];
+        line.clear();
+        line.push(mv);
+        line.extend_from_slice(&tail[0]);
+    }
+}
+
+// 置換表のキー（opening book のキーに手番を加えたもの）
+fn search_key(position: &Position) -> u64 {
+    match position.side_to_move() {
+        Player::Black => position.book_key(),
+        Player::White => position.book_key() ^ ZobristKeys::get().turn,
+    }
+}
+
+// 自玉に王手がかかっておらず、打ち歩詰めでもないか（手を指した後の局面で判定）
+fn is_legal_after(position: &Position, mv: Move, us: Player) -> bool {
+    if position.is_in_check(us) {
+        return false;
+    }
+    let pawn_drop = matches!(
+        mv,
+        Move::Drop {
+            piece_type: PieceType::Pawn,
+            ..
+        }
+    );
+    !(pawn_drop && position.in_check() && !position.has_legal_move())
+}
+
+// 詰みの評価値を root からの手数と局面からの手数の間で換算する
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_IN_MAX_PLY {
+        score + ply as i32
+    } else if score <= -MATE_IN_MAX_PLY {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_IN_MAX_PLY {
+        score - ply as i32
+    } else if score <= -MATE_IN_MAX_PLY {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi::STARTPOS_SFEN;
+
+    fn search(sfen: &str, max_depth: u8) -> SearchResult {
+        let position = Position::from_sfen(sfen).unwrap();
+        let mut engine = SearchEngine::new(4);
+        let result = engine.search(
+            &position,
+            &SearchLimits {
+                max_depth,
+                ..SearchLimits::default()
+            },
+        );
+        assert_legal_line(&position, result.pv_moves());
+        result
+    }
+
+    fn assert_legal_line(position: &Position, line: &[Move]) {
+        let mut current = position.clone();
+        for mv in line {
+            assert!(current.is_legal(*mv), "illegal move {mv} in pv");
+            current.do_move(*mv);
+        }
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let result = search("4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1", 4);
+        assert_eq!(result.best_move(), Some("G*5b".to_string()));
+        assert_eq!(result.mate_in(), Some(1));
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn test_finds_mate_in_three() {
+        let result = search("6+B1k/6s2/9/9/9/9/9/9/9 b RBr4g3s4n4l18p 1", 5);
+        assert_eq!(result.mate_in(), Some(3), "pv {:?}", result.pv());
+    }
+
+    #[test]
+    fn test_wins_hanging_rook() {
+        // 後手の飛車が角の筋にただで置かれている
+        let result = search("4k4/9/9/9/9/2r6/9/4B4/4K4 b - 1", 3);
+        assert_eq!(result.best_move(), Some("5h7f".to_string()));
+        assert!(result.score > 800, "score {}", result.score);
+    }
+
+    #[test]
+    fn test_mated_position() {
+        let result = search("4k4/4G4/4P4/9/9/9/9/9/9 w 2r2b3g4s4n4l17p 1", 3);
+        assert_eq!(result.best_move(), None);
+        assert_eq!(result.score, -MATE_SCORE);
+        assert_eq!(result.mate_in(), Some(0));
+    }
+
+    #[test]
+    fn test_perpetual_check_loses() {
+        // 先手は竜で 3a と 3b から王手を続けないと詰まされるが、続ければ
+        // 連続王手の千日手で負け
+        let sfen = "5+P2k/5+P+R2/8p/9/9/9/1+r7/2g6/K8 b - 1";
+        let result = search(sfen, 6);
+        assert!(result.mate_in().is_some_and(|plies| plies < 0), "score {}", result.score);
+    }
+
+    #[test]
+    fn test_stop_flag_still_returns_a_move() {
+        let mut engine = SearchEngine::new(4);
+        engine.set_stop_flag(Arc::new(AtomicBool::new(true)));
+        let result = engine.search(&Position::startpos(), &SearchLimits::default());
+        assert_eq!(result.depth, 1);
+        assert!(result.best().is_some());
+    }
+
+    #[test]
+    fn test_time_and_node_limits() {
+        let position = Position::startpos();
+        let mut engine = SearchEngine::new(4);
+
+        let result = engine.search(
+            &position,
+            &SearchLimits {
+                time_ms: Some(200),
+                ..SearchLimits::default()
+            },
+        );
+        assert!(result.depth >= 2, "depth {}", result.depth);
+        assert!(result.elapsed_ms < 1000, "took {}ms", result.elapsed_ms);
+        assert_legal_line(&position, result.pv_moves());
+
+        let result = engine.search(
+            &position,
+            &SearchLimits {
+                node_limit: Some(5000),
+                ..SearchLimits::default()
+            },
+        );
+        assert!(result.best().is_some_and(|mv| position.is_legal(mv)));
+    }
+
+    fn search_with_strength(sfen: &str, strength: EngineStrength, seed: u64) -> SearchResult {
+        let position = Position::from_sfen(sfen).unwrap();
+        let mut engine = SearchEngine::new(4);
+        engine.set_strength(strength);
+        engine.set_seed(seed);
+        let result = engine.search(&position, &SearchLimits::default());
+        assert_legal_line(&position, result.pv_moves());
+        result
+    }
+
+    #[test]
+    fn test_strength_caps_depth_and_nodes() {
+        let strength = EngineStrength::from_level("intermediate").unwrap();
+        let result = search_with_strength(STARTPOS_SFEN, strength, 1);
+        assert!(result.depth <= strength.max_depth, "depth {}", result.depth);
+
+        let strength = EngineStrength {
+            node_limit: Some(3000),
+            ..EngineStrength::default()
+        };
+        let result = search_with_strength(STARTPOS_SFEN, strength, 1);
+        assert!(result.node_count < 10_000, "nodes {}", result.node_count);
+    }
+
+    #[test]
+    fn test_random_choice_among_near_best_moves() {
+        // 飛車をただで取れる手だけが 300 以内に入る
+        let sfen = "4k4/9/9/9/9/2r6/9/4B4/4K4 b - 1";
+        let strength = EngineStrength {
+            max_depth: 3,
+            random_margin: 300,
+            ..EngineStrength::default()
+        };
+        for seed in 0..5 {
+            let result = search_with_strength(sfen, strength, seed);
+            assert_eq!(result.best_move(), Some("5h7f".to_string()));
+        }
+
+        // 差を大きく取れば色々な手を選ぶ
+        let strength = EngineStrength {
+            max_depth: 2,
+            random_margin: 100_000,
+            ..EngineStrength::default()
+        };
+        let chosen: std::collections::HashSet<Option<String>> = (0..8)
+            .map(|seed| search_with_strength(STARTPOS_SFEN, strength, seed).best_move())
+            .collect();
+        assert!(chosen.len() > 1, "{chosen:?}");
+    }
+
+    #[test]
+    fn test_blunder_and_seed() {
+        let sfen = "4k4/9/9/9/9/2r6/9/4B4/4K4 b - 1";
+        let strength = EngineStrength {
+            max_depth: 3,
+            blunder_percent: 100,
+            ..EngineStrength::default()
+        };
+        let result = search_with_strength(sfen, strength, 7);
+        assert_ne!(result.best_move(), Some("5h7f".to_string()));
+
+        // 同じ種なら同じ手を選ぶ
+        let beginner = EngineStrength::from_level("beginner").unwrap();
+        let first = search_with_strength(STARTPOS_SFEN, beginner, 3);
+        let second = search_with_strength(STARTPOS_SFEN, beginner, 3);
+        assert_eq!(
+            (first.best_move(), first.score, first.pv()),
+            (second.best_move(), second.score, second.pv())
+        );
+    }
+}
@@ -0,0 +1,13 @@
+// Alpha-Beta Search Module
+pub mod engine;
+pub mod ordering;
+pub mod searcher;
+pub mod strength;
+pub mod tt;
+
+// Re-export for easier access
+pub use engine::*;
+pub use ordering::*;
+pub use searcher::*;
+pub use strength::*;
+pub use tt::*;
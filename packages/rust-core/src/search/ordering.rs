@@ -0,0 +1,154 @@
+use crate::evaluation::piece_value;
+use crate::search::engine::MAX_PLY;
+use crate::shogi::{Move, PieceType, Position, Square};
+
+// 並べ替えの優先度（置換表の手 > 駒を取る手 > 成る手 > キラー手 > ヒストリー）
+const TT_MOVE_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 24;
+const PROMOTION_SCORE: i32 = 1 << 23;
+const KILLER_SCORE: i32 = 1 << 22;
+// ヒストリーがこれを超えたら全体を半分にする
+const HISTORY_MAX: i32 = 1 << 20;
+
+const HISTORY_SIZE: usize = 2 * PieceType::ALL.len() * Square::NUM;
+
+// 手の並べ替えに使う探索中の統計（キラー手とヒストリー）
+pub struct MoveOrdering {
+    // 手数ごとにベータカットした駒を取らない手を 2 つまで
+    killers: Vec<[Option<Move>; 2]>,
+    // [手番][動かした駒][移動先] ごとのベータカットの重み
+    history: Vec<i32>,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        MoveOrdering {
+            killers: vec![[None; 2]; MAX_PLY + 1],
+            history: vec![0; HISTORY_SIZE],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.clear_killers();
+        self.history.fill(0);
+    }
+
+    // キラー手は局面が変わると意味がないので探索ごとに消す
+    pub fn clear_killers(&mut self) {
+        self.killers.fill([None; 2]);
+    }
+
+    // 指す前の局面で各手の優先度を付ける
+    pub fn score_moves(
+        &self,
+        position: &Position,
+        moves: &[Move],
+        tt_move: Option<Move>,
+        ply: usize,
+    ) -> Vec<i32> {
+        moves
+            .iter()
+            .map(|&mv| {
+                if Some(mv) == tt_move {
+                    return TT_MOVE_SCORE;
+                }
+                if let Some(victim) = position.piece_at(mv.to()) {
+                    // MVV-LVA: 価値の高い駒を価値の低い駒で取る手から
+                    let attacker = moved_piece_type(position, mv);
+                    return CAPTURE_SCORE + piece_value(victim.piece_type) * 16
+                        - piece_value(attacker) / 16;
+                }
+                if mv.is_promotion() {
+                    return PROMOTION_SCORE + piece_value(moved_piece_type(position, mv));
+                }
+                if let Some(index) = self.killers[ply].iter().position(|&k| k == Some(mv)) {
+                    return KILLER_SCORE - index as i32;
+                }
+                self.history[history_index(position, mv)]
+            })
+            .collect()
+    }
+
+    pub fn is_killer(&self, ply: usize, mv: Move) -> bool {
+        self.killers[ply].contains(&Some(mv))
+    }
+
+    // 駒を取らない手でベータカットした（position は指す前の局面）
+    pub fn record_cutoff(&mut self, position: &Position, mv: Move, ply: usize, depth: i32) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let index = history_index(position, mv);
+        self.history[index] += depth * depth;
+        if self.history[index] > HISTORY_MAX {
+            for value in &mut self.history {
+                *value /= 2;
+            }
+        }
+    }
+}
+
+// 残りの手から優先度が最大の手を index の位置に移して返す（選択ソート）
+pub fn pick_next(moves: &mut [Move], scores: &mut [i32], index: usize) -> Option<Move> {
+    let best = (index..moves.len()).max_by_key(|&i| scores[i])?;
+    moves.swap(index, best);
+    scores.swap(index, best);
+    Some(moves[index])
+}
+
+fn moved_piece_type(position: &Position, mv: Move) -> PieceType {
+    match mv {
+        Move::Normal { from, .. } => {
+            position.piece_at(from).expect("no piece on move origin").piece_type
+        }
+        Move::Drop { piece_type, .. } => piece_type,
+    }
+}
+
+fn history_index(position: &Position, mv: Move) -> usize {
+    let piece_type = moved_piece_type(position, mv);
+    (position.side_to_move().index() * PieceType::ALL.len() + piece_type.index()) * Square::NUM
+        + mv.to().index()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mv(position: &Position, usi: &str) -> Move {
+        let mv = Move::from_usi(usi).unwrap();
+        assert!(position.is_legal(mv), "{usi} is not legal");
+        mv
+    }
+
+    #[test]
+    fn test_move_priorities() {
+        // 先手の銀で飛車も歩も取れる
+        let position = Position::from_sfen("4k4/9/9/3r1p3/4S4/4P4/9/9/4K4 b P 1").unwrap();
+        let mut ordering = MoveOrdering::new();
+        let takes_rook = mv(&position, "5e6d");
+        let takes_pawn = mv(&position, "5e4d");
+        let quiet = mv(&position, "5i4h");
+        let drop = mv(&position, "P*2e");
+        let tt_move = mv(&position, "5i4i");
+
+        ordering.record_cutoff(&position, drop, 3, 4);
+        let mut moves = vec![quiet, drop, takes_pawn, tt_move, takes_rook];
+        let mut scores = ordering.score_moves(&position, &moves, Some(tt_move), 3);
+
+        let order: Vec<Move> =
+            (0..moves.len()).map_while(|i| pick_next(&mut moves, &mut scores, i)).collect();
+        assert_eq!(order, vec![tt_move, takes_rook, takes_pawn, drop, quiet]);
+        assert!(ordering.is_killer(3, drop));
+        assert!(!ordering.is_killer(2, drop));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::evaluation::NnueNetwork;
+use crate::search::engine::{SearchEngine, SearchLimits, SearchResult, DEFAULT_HASH_MB, MAX_DEPTH};
+use crate::search::strength::EngineStrength;
+use crate::shogi::{parse_player, position_from_json, Position};
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+// WASM インターフェース
+//
+// 置換表とヒストリーは search をまたいで保持するので、1 局の間は同じ
+// インスタンスを使い回す
+#[wasm_bindgen]
+pub struct Searcher {
+    engine: SearchEngine,
+}
+
+#[wasm_bindgen]
+impl Searcher {
+    // hash_mb 省略時は 16MB の置換表を確保する
+    #[wasm_bindgen(constructor)]
+    pub fn new(hash_mb: Option<u32>) -> Searcher {
+        Searcher {
+            engine: SearchEngine::new(hash_mb.map_or(DEFAULT_HASH_MB, |mb| mb as usize)),
+        }
+    }
+
+    // SFEN文字列で局面を受け取り、手番側の最善手を探索する
+    // （max_depth 省略時は時間いっぱいまで深くする）
+    pub fn search_from_sfen(
+        &mut self,
+        sfen: &str,
+        time_limit_ms: u32,
+        max_depth: Option<u8>,
+    ) -> Result<SearchResult, JsValue> {
+        let position = Position::from_sfen(sfen)
+            .map_err(|e| JsValue::from_str(&format!("Invalid SFEN: {e:#}")))?;
+
+        Ok(self.search_position(&position, time_limit_ms, max_depth))
+    }
+
+    // 盤面データをJSONで受け取り、player の手番として探索する
+    // スキーマは crate::shogi::json を参照
+    pub fn search_from_json(
+        &mut self,
+        board_json: &str,
+        hands_json: &str,
+        player: &str,
+        time_limit_ms: u32,
+        max_depth: Option<u8>,
+    ) -> Result<SearchResult, JsValue> {
+        let position = parse_player(player)
+            .and_then(|player| position_from_json(board_json, hands_json, player))
+            .map_err(|e| JsValue::from_str(&format!("{e:#}")))?;
+
+        Ok(self.search_position(&position, time_limit_ms, max_depth))
+    }
+
+    // 新しい対局の前に置換表とヒストリーを消す
+    pub fn clear(&mut self) {
+        self.engine.clear();
+    }
+
+    // YaneuraOu 形式の NNUE（halfKP 256x2-32-32 の nn.bin）で評価するようにする
+    // 戻り値はファイルに書かれた構造の説明
+    pub fn load_nnue(&mut self, data: &[u8]) -> Result<String, JsValue> {
+        let network = NnueNetwork::from_bytes(data)
+            .map_err(|e| JsValue::from_str(&format!("Invalid NNUE: {e:#}")))?;
+        let architecture = network.architecture().to_string();
+        self.engine.set_nnue(Some(Arc::new(network)));
+        Ok(architecture)
+    }
+
+    // 手作りの評価関数に戻す
+    pub fn unload_nnue(&mut self) {
+        self.engine.set_nnue(None);
+    }
+
+    // 棋力の設定（EngineStrength.preset("beginner") など）
+    pub fn set_strength(&mut self, strength: &EngineStrength) {
+        self.engine.set_strength(*strength);
+    }
+
+    // 手の選び方の乱数を固定する（テストや再現用）
+    pub fn set_seed(&mut self, seed: u32) {
+        self.engine.set_seed(seed as u64);
+    }
+}
+
+impl Searcher {
+    fn search_position(
+        &mut self,
+        position: &Position,
+        time_limit_ms: u32,
+        max_depth: Option<u8>,
+    ) -> SearchResult {
+        let limits = SearchLimits {
+            max_depth: max_depth.unwrap_or(MAX_DEPTH),
+            time_ms: Some(time_limit_ms),
+            node_limit: None,
+        };
+        self.engine.search(position, &limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_from_sfen() {
+        let mut searcher = Searcher::new(Some(4));
+        let result = searcher
+            .search_from_sfen("4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1", 1000, None)
+            .unwrap();
+        assert_eq!(result.best_move(), Some("G*5b".to_string()));
+        assert_eq!(result.pv(), vec!["G*5b"]);
+    }
+
+    #[test]
+    fn test_search_from_json() {
+        let board = r#"{"55": {"type": "king", "owner": "black", "promoted": false},
+                        "15": {"type": "king", "owner": "white", "promoted": false},
+                        "23": {"type": "rook", "owner": "white", "promoted": false}}"#;
+        let mut searcher = Searcher::new(Some(4));
+        let result = searcher.search_from_json(board, "{}", "black", 1000, Some(3)).unwrap();
+        assert!(result.best_move().is_some());
+        assert_eq!(result.depth, 3);
+    }
+
+    #[test]
+    fn test_strength_limits_depth() {
+        let mut searcher = Searcher::new(Some(4));
+        searcher.set_strength(&EngineStrength::preset("beginner").unwrap());
+        searcher.set_seed(1);
+        let result = searcher
+            .search_from_sfen(
+                "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+                1000,
+                None,
+            )
+            .unwrap();
+        assert!(result.depth <= 2);
+        assert!(result.best_move().is_some());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::search::engine::MAX_DEPTH;
+use anyhow::{anyhow, Result};
+use wasm_bindgen::prelude::*;
+
+// 棋力の設定（探索を弱めるための調整をまとめたもの）
+//
+// 既定値は手加減なし。web の AI の難易度と同じ名前のプリセットを
+// from_level で作れる
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineStrength {
+    // 反復深化の深さの上限
+    pub max_depth: u8,
+    // 1 回の探索のノード数の上限（None は制限なし）
+    pub node_limit: Option<u32>,
+    // 末端の評価値に加えるノイズの幅（±、歩 = 100）
+    pub eval_noise: u32,
+    // 最善手との差がこれ以内の手から等確率で選ぶ（0 なら常に最善手）
+    pub random_margin: u32,
+    // 最善手以外の合法手を無作為に指す確率（%）
+    pub blunder_percent: u8,
+    // 定跡を使う手数（SFEN の手数がこれ以下の間だけ。None は制限なし）
+    pub book_depth: Option<u32>,
+}
+
+impl Default for EngineStrength {
+    fn default() -> Self {
+        EngineStrength {
+            max_depth: MAX_DEPTH,
+            node_limit: None,
+            eval_noise: 0,
+            random_margin: 0,
+            blunder_percent: 0,
+            book_depth: None,
+        }
+    }
+}
+
+// プリセットの名前（web の AIDifficulty と同じ）
+pub const STRENGTH_LEVELS: [&str; 4] = ["beginner", "intermediate", "advanced", "expert"];
+
+impl EngineStrength {
+    // プリセットを名前で作る
+    pub fn from_level(level: &str) -> Result<EngineStrength> {
+        let strength = match level {
+            "beginner" => EngineStrength {
+                max_depth: 2,
+                node_limit: Some(2_000),
+                eval_noise: 200,
+                random_margin: 300,
+                blunder_percent: 15,
+                book_depth: Some(0),
+            },
+            "intermediate" => EngineStrength {
+                max_depth: 4,
+                node_limit: Some(50_000),
+                eval_noise: 80,
+                random_margin: 100,
+                blunder_percent: 5,
+                book_depth: Some(8),
+            },
+            "advanced" => EngineStrength {
+                max_depth: 6,
+                node_limit: Some(500_000),
+                eval_noise: 20,
+                random_margin: 30,
+                blunder_percent: 0,
+                book_depth: Some(16),
+            },
+            "expert" => EngineStrength::default(),
+            _ => {
+                return Err(anyhow!(
+                    "Invalid strength level: {:?} (expected one of {})",
+                    level,
+                    STRENGTH_LEVELS.join(", ")
+                ))
+            }
+        };
+        Ok(strength)
+    }
+
+    // 手加減なしか
+    pub fn is_full(&self) -> bool {
+        *self == EngineStrength::default()
+    }
+}
+
+#[wasm_bindgen]
+impl EngineStrength {
+    // 手加減なしの設定（各フィールドを書き換えて使う）
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> EngineStrength {
+        EngineStrength::default()
+    }
+
+    // "beginner" / "intermediate" / "advanced" / "expert" のプリセット
+    pub fn preset(level: &str) -> Result<EngineStrength, JsValue> {
+        EngineStrength::from_level(level).map_err(|e| JsValue::from_str(&format!("{e:#}")))
+    }
+
+    // SFEN の手数が ply の局面で定跡を使ってよいか
+    pub fn allows_book(&self, ply: u32) -> bool {
+        self.book_depth.is_none_or(|depth| ply <= depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        assert!(EngineStrength::from_level("expert").unwrap().is_full());
+        let levels: Vec<EngineStrength> = STRENGTH_LEVELS
+            .iter()
+            .map(|level| EngineStrength::from_level(level).unwrap())
+            .collect();
+        // 上のレベルほど深く読み、手加減が小さい
+        for pair in levels.windows(2) {
+            assert!(pair[0].max_depth < pair[1].max_depth);
+            assert!(pair[0].eval_noise > pair[1].eval_noise);
+            assert!(pair[0].random_margin > pair[1].random_margin);
+        }
+        assert!(EngineStrength::from_level("master").is_err());
+    }
+
+    #[test]
+    fn test_allows_book() {
+        let beginner = EngineStrength::from_level("beginner").unwrap();
+        assert!(!beginner.allows_book(1));
+        let intermediate = EngineStrength::from_level("intermediate").unwrap();
+        assert!(intermediate.allows_book(8));
+        assert!(!intermediate.allows_book(9));
+        assert!(EngineStrength::default().allows_book(200));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::shogi::Move;
+
+// 置換表に保存した評価値が真の値とどういう関係にあるか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    // 窓の内側で確定した値
+    Exact,
+    // ベータカットした値（真の値はこれ以上）
+    Lower,
+    // どの手もアルファを超えなかった値（真の値はこれ以下）
+    Upper,
+}
+
+// 置換表のエントリ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub key: u64,
+    pub best_move: Option<Move>,
+    // 詰みの評価値は root からではなくこの局面からの手数で保存する
+    pub score: i32,
+    pub depth: i32,
+    pub bound: Bound,
+    generation: u8,
+}
+
+// 置換表（キーの下位ビットで引く 1 スロット 1 エントリの固定サイズ表）
+//
+// 同じスロットに別の局面が来たら、前回までの探索のエントリか、残り深さが
+// 同じ以上のときに置き換える
+pub struct TranspositionTable {
+    entries: Vec<Option<TtEntry>>,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    // size_mb メガバイトに収まる最大の 2 の冪個のエントリを確保する
+    pub fn new(size_mb: usize) -> Self {
+        let bytes = size_mb.max(1) << 20;
+        let count = bytes / std::mem::size_of::<Option<TtEntry>>();
+        let count = 1usize << count.max(1).ilog2();
+
+        TranspositionTable {
+            entries: vec![None; count],
+            generation: 0,
+        }
+    }
+
+    // エントリ数
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    // 全エントリを消す（新しい対局の前など）
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    // 新しい探索を始める（前回までのエントリを優先的に置き換える）
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        self.entries[self.slot(key)].filter(|entry| entry.key == key)
+    }
+
+    pub fn store(
+        &mut self,
+        key: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: i32,
+        bound: Bound,
+    ) {
+        let generation = self.generation;
+        let slot = self.slot(key);
+        let old = self.entries[slot];
+
+        if let Some(old) = old {
+            if old.key != key && old.generation == generation && old.depth > depth {
+                return;
+            }
+        }
+
+        // 同じ局面で手が分からなかったときは前の手を残す
+        let best_move =
+            best_move.or(old.filter(|old| old.key == key).and_then(|old| old.best_move));
+        self.entries[slot] = Some(TtEntry {
+            key,
+            best_move,
+            score,
+            depth,
+            bound,
+            generation,
+        });
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi::Square;
+
+    fn mv(from: &str, to: &str) -> Move {
+        Move::Normal {
+            from: Square::from_usi(from).unwrap(),
+            to: Square::from_usi(to).unwrap(),
+            promote: false,
+        }
+    }
+
+    #[test]
+    fn test_capacity_is_power_of_two() {
+        let tt = TranspositionTable::new(1);
+        assert!(tt.capacity().is_power_of_two());
+        assert!(tt.capacity() * std::mem::size_of::<Option<TtEntry>>() <= 1 << 20);
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let mut tt = TranspositionTable::new(1);
+        tt.store(42, Some(mv("7g", "7f")), 120, 5, Bound::Exact);
+
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.best_move, Some(mv("7g", "7f")));
+        assert_eq!((entry.score, entry.depth, entry.bound), (120, 5, Bound::Exact));
+        assert!(tt.probe(43).is_none());
+
+        // 手の分からない上書きでも前の手は残る
+        tt.store(42, None, -30, 6, Bound::Upper);
+        assert_eq!(tt.probe(42).unwrap().best_move, Some(mv("7g", "7f")));
+
+        tt.clear();
+        assert!(tt.probe(42).is_none());
+    }
+
+    #[test]
+    fn test_replacement_prefers_depth_within_a_search() {
+        let mut tt = TranspositionTable::new(1);
+        let other = 7 + tt.capacity() as u64;
+
+        tt.store(7, None, 0, 8, Bound::Exact);
+        tt.store(other, None, 0, 3, Bound::Exact);
+        assert!(tt.probe(7).is_some());
+        assert!(tt.probe(other).is_none());
+
+        tt.new_search();
+        tt.store(other, None, 0, 3, Bound::Exact);
+        assert!(tt.probe(7).is_none());
+        assert!(tt.probe(other).is_some());
+    }
+}
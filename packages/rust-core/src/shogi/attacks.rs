@@ -0,0 +1,256 @@
+//! Precomputed attack tables
+//!
+//! Step attacks are tabulated per piece kind, owner and square. Sliding
+//! attacks use one ray table per direction: the nearest blocker on a ray is
+//! its lowest or highest set bit, depending on whether the direction
+//! increases the square index, and the squares past the blocker are removed
+//! with the blocker's own ray. The tables are built on first use.
+
+use std::sync::OnceLock;
+
+use crate::shogi::{Bitboard, Piece, PieceType, Player, Square};
+
+/// Direction as (file delta, rank delta) from Black's point of view
+type Dir = (i8, i8);
+
+/// The eight ray directions; the index is used for the ray tables
+const DIRS: [Dir; 8] = [
+    (0, -1),
+    (1, -1),
+    (-1, -1),
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (1, 1),
+    (-1, 1),
+];
+const KNIGHT_DIRS: [Dir; 2] = [(1, -2), (-1, -2)];
+const GOLD_DIRS: [Dir; 6] = [(0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0), (0, 1)];
+const SILVER_DIRS: [Dir; 5] = [(0, -1), (1, -1), (-1, -1), (1, 1), (-1, 1)];
+const DIAGONALS: [Dir; 4] = [(1, -1), (-1, -1), (1, 1), (-1, 1)];
+const ORTHOGONALS: [Dir; 4] = [(0, -1), (1, 0), (-1, 0), (0, 1)];
+
+/// Indices into `DIRS` of the sliding directions
+const FORWARD_RAYS: [[usize; 1]; 2] = [[0], [5]];
+const DIAGONAL_RAYS: [usize; 4] = [1, 2, 6, 7];
+const ORTHOGONAL_RAYS: [usize; 4] = [0, 3, 4, 5];
+
+/// One-square moves of a piece kind
+fn step_dirs(piece_type: PieceType) -> &'static [Dir] {
+    match piece_type {
+        PieceType::Pawn => &[(0, -1)],
+        PieceType::Knight => &KNIGHT_DIRS,
+        PieceType::Silver => &SILVER_DIRS,
+        PieceType::Gold
+        | PieceType::ProPawn
+        | PieceType::ProLance
+        | PieceType::ProKnight
+        | PieceType::ProSilver => &GOLD_DIRS,
+        PieceType::King => &DIRS,
+        PieceType::Horse => &ORTHOGONALS,
+        PieceType::Dragon => &DIAGONALS,
+        PieceType::Lance | PieceType::Bishop | PieceType::Rook => &[],
+    }
+}
+
+/// Ray directions (indices into `DIRS`) a piece slides along
+fn slide_rays(piece: Piece) -> &'static [usize] {
+    match piece.piece_type {
+        PieceType::Lance => &FORWARD_RAYS[piece.owner.index()],
+        PieceType::Bishop | PieceType::Horse => &DIAGONAL_RAYS,
+        PieceType::Rook | PieceType::Dragon => &ORTHOGONAL_RAYS,
+        _ => &[],
+    }
+}
+
+/// Whether moving along `DIRS[dir]` increases the square index
+fn is_increasing(dir: usize) -> bool {
+    let (df, dr) = DIRS[dir];
+    df * 9 + dr > 0
+}
+
+struct AttackTables {
+    /// `[piece kind][owner][square]`
+    steps: Vec<[[Bitboard; Square::NUM]; 2]>,
+    /// `[direction][square]`, excluding the square itself
+    rays: [[Bitboard; Square::NUM]; 8],
+    /// `[from * 81 + to]`, squares strictly between two aligned squares
+    between: Vec<Bitboard>,
+}
+
+impl AttackTables {
+    fn new() -> Self {
+        let mut steps = vec![[[Bitboard::EMPTY; Square::NUM]; 2]; PieceType::ALL.len()];
+        for piece_type in PieceType::ALL {
+            for owner in Player::ALL {
+                for square in Square::all() {
+                    let targets = &mut steps[piece_type.index()][owner.index()][square.index()];
+                    for &(df, dr) in step_dirs(piece_type) {
+                        let (df, dr) = match owner {
+                            Player::Black => (df, dr),
+                            Player::White => (-df, -dr),
+                        };
+                        if let Some(to) = square.offset(df, dr) {
+                            targets.set(to);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut rays = [[Bitboard::EMPTY; Square::NUM]; 8];
+        let mut between = vec![Bitboard::EMPTY; Square::NUM * Square::NUM];
+        for (dir, &(df, dr)) in DIRS.iter().enumerate() {
+            for from in Square::all() {
+                let mut passed = Bitboard::EMPTY;
+                let mut current = from.offset(df, dr);
+                while let Some(to) = current {
+                    between[from.index() * Square::NUM + to.index()] = passed;
+                    passed.set(to);
+                    current = to.offset(df, dr);
+                }
+                rays[dir][from.index()] = passed;
+            }
+        }
+
+        AttackTables {
+            steps,
+            rays,
+            between,
+        }
+    }
+
+    fn ray_attacks(&self, dir: usize, square: Square, occupied: Bitboard) -> Bitboard {
+        let ray = self.rays[dir][square.index()];
+        let blockers = ray & occupied;
+        let blocker = if is_increasing(dir) {
+            blockers.lsb()
+        } else {
+            blockers.msb()
+        };
+        match blocker {
+            Some(blocker) => ray ^ self.rays[dir][blocker.index()],
+            None => ray,
+        }
+    }
+}
+
+fn tables() -> &'static AttackTables {
+    static TABLES: OnceLock<AttackTables> = OnceLock::new();
+    TABLES.get_or_init(AttackTables::new)
+}
+
+/// One-square attacks of `piece` on `square` (the non-sliding part)
+pub fn step_attacks(piece: Piece, square: Square) -> Bitboard {
+    tables().steps[piece.piece_type.index()][piece.owner.index()][square.index()]
+}
+
+/// Sliding attacks of `piece` on `square`, stopping at (and including) the
+/// first occupied square in each direction
+pub fn slider_attacks(piece: Piece, square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    let mut attacks = Bitboard::EMPTY;
+    for &dir in slide_rays(piece) {
+        attacks |= tables.ray_attacks(dir, square, occupied);
+    }
+    attacks
+}
+
+/// All squares attacked by `piece` on `square`
+pub fn piece_attacks(piece: Piece, square: Square, occupied: Bitboard) -> Bitboard {
+    step_attacks(piece, square) | slider_attacks(piece, square, occupied)
+}
+
+/// Whether the piece kind slides (lance, bishop, rook, horse, dragon)
+pub fn is_slider(piece_type: PieceType) -> bool {
+    matches!(
+        piece_type,
+        PieceType::Lance
+            | PieceType::Bishop
+            | PieceType::Rook
+            | PieceType::Horse
+            | PieceType::Dragon
+    )
+}
+
+/// Squares strictly between two squares on a rank, file or diagonal; empty
+/// if they are not aligned
+pub fn between(from: Square, to: Square) -> Bitboard {
+    tables().between[from.index() * Square::NUM + to.index()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attacks computed by walking the board one square at a time
+    fn walk_attacks(piece: Piece, square: Square, occupied: Bitboard) -> Bitboard {
+        let orient = |(df, dr): Dir| match piece.owner {
+            Player::Black => (df, dr),
+            Player::White => (-df, -dr),
+        };
+        let mut attacks = Bitboard::EMPTY;
+        for &dir in step_dirs(piece.piece_type) {
+            let (df, dr) = orient(dir);
+            attacks |= square.offset(df, dr).into_iter().collect();
+        }
+        for &ray in slide_rays(piece) {
+            let (df, dr) = DIRS[ray];
+            let mut current = square.offset(df, dr);
+            while let Some(to) = current {
+                attacks.set(to);
+                if occupied.contains(to) {
+                    break;
+                }
+                current = to.offset(df, dr);
+            }
+        }
+        attacks
+    }
+
+    #[test]
+    fn test_attacks_match_board_walk() {
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next_random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u128
+        };
+
+        for _ in 0..64 {
+            // Roughly a quarter of the squares occupied
+            let occupied = Bitboard::from_bits(next_random() << 64 | next_random())
+                & Bitboard::from_bits(next_random() << 64 | next_random());
+
+            for piece_type in PieceType::ALL {
+                for owner in Player::ALL {
+                    let piece = Piece::new(piece_type, owner);
+                    for square in Square::all() {
+                        assert_eq!(
+                            piece_attacks(piece, square, occupied),
+                            walk_attacks(piece, square, occupied),
+                            "{piece_type:?} {owner:?} on {square}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_between() {
+        let sq = |usi| Square::from_usi(usi).unwrap();
+        assert_eq!(
+            between(sq("5a"), sq("5e")),
+            [sq("5b"), sq("5c"), sq("5d")].into_iter().collect()
+        );
+        assert_eq!(between(sq("1i"), sq("4f")), [sq("2h"), sq("3g")].into_iter().collect());
+        assert_eq!(between(sq("5e"), sq("5d")), Bitboard::EMPTY);
+        assert_eq!(between(sq("5e"), sq("4c")), Bitboard::EMPTY);
+        assert_eq!(
+            between(sq("9a"), sq("1a")),
+            Bitboard::rank(1) ^ [sq("9a"), sq("1a")].into_iter().collect()
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! 81-square bitboards
+//!
+//! Bit `n` stands for the square with index `n`, so with the file-major
+//! square layout each file is a run of 9 consecutive bits: 1a..1i are bits
+//! 0..8, 2a..2i are bits 9..17 and so on. Only the low 81 bits of the
+//! `u128` are ever set.
+
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use crate::shogi::{Player, Square};
+
+const MASK: u128 = (1 << Square::NUM) - 1;
+
+/// Bits of one rank (1-9) across all files
+const fn rank_bits(rank: u8) -> u128 {
+    let mut bits = 0;
+    let mut file = 0;
+    while file < 9 {
+        bits |= 1 << (file * 9 + rank as u32 - 1);
+        file += 1;
+    }
+    bits
+}
+
+/// A set of squares
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(u128);
+
+impl Bitboard {
+    /// No squares
+    pub const EMPTY: Bitboard = Bitboard(0);
+
+    /// All 81 squares
+    pub const ALL: Bitboard = Bitboard(MASK);
+
+    /// Bitboard holding a single square
+    pub fn from_square(square: Square) -> Bitboard {
+        Bitboard(1 << square.index())
+    }
+
+    /// All squares of a file (1-9)
+    pub fn file(file: u8) -> Bitboard {
+        debug_assert!((1..=9).contains(&file));
+        Bitboard(0x1ff << ((file as u32 - 1) * 9))
+    }
+
+    /// All squares of a rank (1-9)
+    pub fn rank(rank: u8) -> Bitboard {
+        debug_assert!((1..=9).contains(&rank));
+        Bitboard(rank_bits(rank))
+    }
+
+    /// The `count` ranks farthest from the given player (e.g. 3 for the
+    /// promotion zone)
+    pub fn far_ranks(player: Player, count: u8) -> Bitboard {
+        let mut bits = 0;
+        for rank in 1..=count {
+            bits |= match player {
+                Player::Black => rank_bits(rank),
+                Player::White => rank_bits(10 - rank),
+            };
+        }
+        Bitboard(bits)
+    }
+
+    /// Bitboard from raw bits; bits above the 81st are dropped
+    pub fn from_bits(bits: u128) -> Bitboard {
+        Bitboard(bits & MASK)
+    }
+
+    /// Raw bits (bit `n` = square index `n`)
+    pub fn bits(self) -> u128 {
+        self.0
+    }
+
+    /// Whether the square is in the set
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & (1 << square.index()) != 0
+    }
+
+    /// Add a square
+    pub fn set(&mut self, square: Square) {
+        self.0 |= 1 << square.index();
+    }
+
+    /// Remove a square
+    pub fn clear(&mut self, square: Square) {
+        self.0 &= !(1 << square.index());
+    }
+
+    /// Whether no square is set
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of squares in the set
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Square with the lowest index
+    pub fn lsb(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Square::from_index(self.0.trailing_zeros() as usize)
+        }
+    }
+
+    /// Square with the highest index
+    pub fn msb(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Square::from_index(127 - self.0.leading_zeros() as usize)
+        }
+    }
+
+    /// Remove and return the square with the lowest index
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let square = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+/// Squares of a bitboard in index order
+#[derive(Debug, Clone)]
+pub struct BitboardIter(Bitboard);
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        self.0.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for BitboardIter {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> BitboardIter {
+        BitboardIter(self)
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Bitboard {
+        let mut bitboard = Bitboard::EMPTY;
+        for square in iter {
+            bitboard.set(square);
+        }
+        bitboard
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+
+    fn bitor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+
+    fn bitxor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0 & MASK)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Bitboard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+/// Board diagram from Black's view (file 9 on the left), `*` for set squares
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Bitboard({:#x})", self.0)?;
+        for rank in 1..=9 {
+            for file in (1..=9).rev() {
+                let square = Square::new(file, rank).expect("file and rank in range");
+                f.write_str(if self.contains(square) { "*" } else { "." })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sq(usi: &str) -> Square {
+        Square::from_usi(usi).unwrap()
+    }
+
+    #[test]
+    fn test_set_clear_and_iterate() {
+        let mut bb = Bitboard::EMPTY;
+        bb.set(sq("9i"));
+        bb.set(sq("1a"));
+        bb.set(sq("5e"));
+        assert_eq!(bb.count(), 3);
+        assert!(bb.contains(sq("5e")));
+        assert_eq!(bb.lsb(), Some(sq("1a")));
+        assert_eq!(bb.msb(), Some(sq("9i")));
+        assert_eq!(bb.into_iter().collect::<Vec<_>>(), vec![sq("1a"), sq("5e"), sq("9i")]);
+
+        bb.clear(sq("5e"));
+        assert!(!bb.contains(sq("5e")));
+        assert_eq!(Bitboard::EMPTY.lsb(), None);
+    }
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(Bitboard::ALL.count(), 81);
+        assert_eq!((!Bitboard::EMPTY), Bitboard::ALL);
+        assert_eq!((!Bitboard::ALL), Bitboard::EMPTY);
+
+        for n in 1..=9 {
+            let file = Bitboard::file(n);
+            let rank = Bitboard::rank(n);
+            assert_eq!(file.count(), 9);
+            assert_eq!(rank.count(), 9);
+            assert!(file.into_iter().all(|s| s.file() == n));
+            assert!(rank.into_iter().all(|s| s.rank() == n));
+        }
+
+        for player in Player::ALL {
+            let zone = Bitboard::far_ranks(player, 3);
+            assert_eq!(zone, Square::all().filter(|s| s.is_promotion_zone(player)).collect());
+        }
+    }
+}
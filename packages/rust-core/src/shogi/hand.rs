@@ -0,0 +1,87 @@
+//! Pieces in hand (持ち駒)
+
+use crate::shogi::PieceType;
+
+/// Pieces held in hand by one player, counted per unpromoted kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Hand {
+    counts: [u8; 7],
+}
+
+impl Hand {
+    /// Create an empty hand
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of pieces of the given kind (promoted kinds count as unpromoted)
+    pub fn count(&self, piece_type: PieceType) -> u8 {
+        piece_type.hand_index().map_or(0, |i| self.counts[i])
+    }
+
+    /// Set the number of pieces of the given kind
+    pub fn set(&mut self, piece_type: PieceType, count: u8) {
+        if let Some(i) = piece_type.hand_index() {
+            self.counts[i] = count;
+        }
+    }
+
+    /// Add one piece (demoted to its hand kind)
+    pub fn add(&mut self, piece_type: PieceType) {
+        if let Some(i) = piece_type.hand_index() {
+            self.counts[i] += 1;
+        }
+    }
+
+    /// Remove one piece, returning false if none was held
+    pub fn remove(&mut self, piece_type: PieceType) -> bool {
+        match piece_type.hand_index() {
+            Some(i) if self.counts[i] > 0 => {
+                self.counts[i] -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether no pieces are held
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&c| c == 0)
+    }
+
+    /// Total number of pieces held
+    pub fn total(&self) -> u32 {
+        self.counts.iter().map(|&c| c as u32).sum()
+    }
+
+    /// Iterate over held kinds with their counts (zero counts skipped)
+    pub fn iter(&self) -> impl Iterator<Item = (PieceType, u8)> + '_ {
+        PieceType::HAND
+            .iter()
+            .zip(self.counts.iter())
+            .filter(|(_, &c)| c > 0)
+            .map(|(&pt, &c)| (pt, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_demotes_and_remove() {
+        let mut hand = Hand::new();
+        hand.add(PieceType::Dragon);
+        hand.add(PieceType::Pawn);
+        hand.add(PieceType::ProPawn);
+
+        assert_eq!(hand.count(PieceType::Rook), 1);
+        assert_eq!(hand.count(PieceType::Pawn), 2);
+        assert_eq!(hand.total(), 3);
+
+        assert!(hand.remove(PieceType::Rook));
+        assert!(!hand.remove(PieceType::Rook));
+        assert!(!hand.remove(PieceType::King));
+        assert_eq!(hand.iter().collect::<Vec<_>>(), vec![(PieceType::Pawn, 2)]);
+    }
+}
@@ -0,0 +1,203 @@
+//! JSON position format sent by the web frontend
+//!
+//! The web `MateSearchPanel` passes the game store's `board` and `hands`
+//! through `JSON.stringify`, so the schema mirrors the TypeScript model in
+//! `@shogi/core`.
+//!
+//! Board JSON is an object keyed by `"<row><column>"`, where row is the rank
+//! (段, 1 = top) and column is the file (筋, 1 = rightmost from Black's view).
+//! Values are a piece or `null`; missing keys are empty squares.
+//!
+//! ```json
+//! {
+//!   "15": { "type": "gyoku", "promoted": false, "owner": "white" },
+//!   "35": { "type": "pawn", "promoted": false, "owner": "black" },
+//!   "55": null
+//! }
+//! ```
+//!
+//! - `type`: `pawn`, `lance`, `knight`, `silver`, `gold`, `bishop`, `rook`,
+//!   `king` or `gyoku`
+//! - `promoted`: optional, defaults to `false`
+//! - `owner`: `black` or `white`
+//!
+//! Hands JSON holds piece counts per player. Keys are the kanji used by the
+//! web store (`歩 香 桂 銀 金 角 飛`) or the English type names above.
+//!
+//! ```json
+//! { "black": { "金": 1, "歩": 0 }, "white": { "飛": 2 } }
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::shogi::{Piece, PieceType, Player, Position, Square};
+
+#[derive(Debug, Deserialize)]
+struct JsonPiece {
+    #[serde(rename = "type")]
+    piece_type: String,
+    #[serde(default)]
+    promoted: bool,
+    owner: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonHands {
+    #[serde(default)]
+    black: HashMap<String, u32>,
+    #[serde(default)]
+    white: HashMap<String, u32>,
+}
+
+/// Parse a player name (`"black"` / `"white"`)
+pub fn parse_player(name: &str) -> Result<Player> {
+    match name {
+        "black" => Ok(Player::Black),
+        "white" => Ok(Player::White),
+        _ => Err(anyhow!("Invalid player: {:?} (expected \"black\" or \"white\")", name)),
+    }
+}
+
+/// Build a position from the web board/hands JSON
+pub fn position_from_json(
+    board_json: &str,
+    hands_json: &str,
+    side_to_move: Player,
+) -> Result<Position> {
+    let board: HashMap<String, Option<JsonPiece>> =
+        serde_json::from_str(board_json).context("Invalid board JSON")?;
+    let hands: JsonHands = serde_json::from_str(hands_json).context("Invalid hands JSON")?;
+
+    let mut position = Position::empty();
+    position.set_side_to_move(side_to_move);
+
+    for (key, piece) in &board {
+        let square = parse_square_key(key)?;
+        if let Some(piece) = piece {
+            let piece = parse_piece(piece).with_context(|| format!("Invalid piece at {key}"))?;
+            position.set_piece(square, Some(piece));
+        }
+    }
+
+    for (player, counts) in [(Player::Black, &hands.black), (Player::White, &hands.white)] {
+        for (name, &count) in counts {
+            let piece_type = parse_hand_piece(name)?;
+            let count = u8::try_from(count)
+                .ok()
+                .filter(|&c| c <= 18)
+                .ok_or_else(|| anyhow!("Invalid hand count for {}: {}", name, count))?;
+            position.set_hand_count(player, piece_type, count);
+        }
+    }
+
+    Ok(position)
+}
+
+/// Parse a `"<row><column>"` board key
+fn parse_square_key(key: &str) -> Result<Square> {
+    let bytes = key.as_bytes();
+    if bytes.len() != 2 {
+        return Err(anyhow!("Invalid square key: {:?}", key));
+    }
+    let row = bytes[0].wrapping_sub(b'0');
+    let column = bytes[1].wrapping_sub(b'0');
+    Square::new(column, row).ok_or_else(|| anyhow!("Invalid square key: {:?}", key))
+}
+
+fn parse_piece(piece: &JsonPiece) -> Result<Piece> {
+    let base = parse_piece_type(&piece.piece_type)
+        .ok_or_else(|| anyhow!("Unknown piece type: {:?}", piece.piece_type))?;
+    let piece_type = if piece.promoted {
+        base.promote()
+            .ok_or_else(|| anyhow!("Piece cannot be promoted: {}", piece.piece_type))?
+    } else {
+        base
+    };
+    Ok(Piece::new(piece_type, parse_player(&piece.owner)?))
+}
+
+fn parse_piece_type(name: &str) -> Option<PieceType> {
+    match name {
+        "pawn" => Some(PieceType::Pawn),
+        "lance" => Some(PieceType::Lance),
+        "knight" => Some(PieceType::Knight),
+        "silver" => Some(PieceType::Silver),
+        "gold" => Some(PieceType::Gold),
+        "bishop" => Some(PieceType::Bishop),
+        "rook" => Some(PieceType::Rook),
+        "king" | "gyoku" => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn parse_hand_piece(name: &str) -> Result<PieceType> {
+    let piece_type = match name {
+        "歩" => Some(PieceType::Pawn),
+        "香" => Some(PieceType::Lance),
+        "桂" => Some(PieceType::Knight),
+        "銀" => Some(PieceType::Silver),
+        "金" => Some(PieceType::Gold),
+        "角" => Some(PieceType::Bishop),
+        "飛" => Some(PieceType::Rook),
+        _ => parse_piece_type(name).filter(|pt| *pt != PieceType::King),
+    };
+    piece_type.ok_or_else(|| anyhow!("Unknown hand piece: {:?}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HANDS: &str = r#"{
+        "black": { "歩": 0, "香": 0, "桂": 0, "銀": 0, "金": 1, "角": 0, "飛": 0 },
+        "white": { "歩": 17, "rook": 2 }
+    }"#;
+
+    #[test]
+    fn test_parse_web_board_and_hands() {
+        let board = r#"{
+            "15": { "type": "gyoku", "promoted": false, "owner": "white" },
+            "35": { "type": "pawn", "promoted": false, "owner": "black" },
+            "88": { "type": "bishop", "promoted": true, "owner": "black" },
+            "95": { "type": "king", "owner": "black" },
+            "55": null
+        }"#;
+
+        let position = position_from_json(board, HANDS, Player::Black).unwrap();
+
+        assert_eq!(position.to_sfen(), "4k4/9/4P4/9/9/9/9/1+B7/4K4 b G2r17p 1");
+    }
+
+    #[test]
+    fn test_descriptive_errors() {
+        let cases = [
+            ("not json", HANDS, "Invalid board JSON"),
+            (r#"{"0a": null}"#, HANDS, "Invalid square key"),
+            (r#"{"15": {"type": "queen", "owner": "white"}}"#, HANDS, "Unknown piece type"),
+            (
+                r#"{"15": {"type": "gold", "promoted": true, "owner": "white"}}"#,
+                HANDS,
+                "cannot be promoted",
+            ),
+            (r#"{"15": {"type": "king", "owner": "red"}}"#, HANDS, "Invalid player"),
+            ("{}", r#"{"black": {"玉": 1}}"#, "Unknown hand piece"),
+            ("{}", r#"{"black": {"歩": 300}}"#, "Invalid hand count"),
+        ];
+
+        for (board, hands, expected) in cases {
+            let err = position_from_json(board, hands, Player::Black).unwrap_err();
+            let message = format!("{err:#}");
+            assert!(message.contains(expected), "{message:?} should contain {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_player() {
+        assert_eq!(parse_player("black").unwrap(), Player::Black);
+        assert_eq!(parse_player("white").unwrap(), Player::White);
+        assert!(parse_player("Black").is_err());
+    }
+}
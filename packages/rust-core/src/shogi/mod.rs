@@ -0,0 +1,27 @@
+// Shogi Position Model
+pub mod attacks;
+pub mod bitboard;
+pub mod hand;
+pub mod json;
+pub mod movegen;
+pub mod moves;
+pub mod notation;
+pub mod piece;
+pub mod position;
+pub mod sfen;
+pub mod square;
+pub mod zobrist;
+
+// Re-export for easier access
+pub use attacks::*;
+pub use bitboard::*;
+pub use hand::*;
+pub use json::*;
+pub use movegen::*;
+pub use moves::*;
+pub use notation::*;
+pub use piece::*;
+pub use position::*;
+pub use sfen::*;
+pub use square::*;
+pub use zobrist::*;
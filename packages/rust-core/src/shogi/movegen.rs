@@ -0,0 +1,588 @@
+//! Attack detection and move generation
+//!
+//! Generation works in two stages: pseudo-legal moves follow piece movement,
+//! promotion and drop rules (forced promotion, dead pieces, nifu), and legal
+//! moves additionally reject moves that leave the own king in check and pawn
+//! drops that give checkmate (uchifuzume).
+//!
+//! Mate solvers only need two subsets, which are generated directly instead
+//! of filtering every legal move: checks for the attacker and check evasions
+//! for the defender. Quiescence search likewise only asks for captures.
+//!
+//! All attack queries go through the bitboard tables in
+//! [`crate::shogi::attacks`]. Since every piece attacks symmetrically, the
+//! pieces of one player attacking a square are found by looking from that
+//! square with the other player's pieces.
+
+use crate::shogi::{
+    between, is_slider, piece_attacks, slider_attacks, step_attacks, Bitboard, Move, Piece,
+    PieceType, Player, Position, Square,
+};
+
+/// Whether a piece would have no legal move if left (or dropped) on `to`
+pub fn is_dead_placement(piece_type: PieceType, owner: Player, to: Square) -> bool {
+    match piece_type {
+        PieceType::Pawn | PieceType::Lance => to.relative_rank(owner) == 1,
+        PieceType::Knight => to.relative_rank(owner) <= 2,
+        _ => false,
+    }
+}
+
+/// Squares where a piece of the given kind would have no legal move
+fn dead_zone(piece_type: PieceType, owner: Player) -> Bitboard {
+    match piece_type {
+        PieceType::Pawn | PieceType::Lance => Bitboard::far_ranks(owner, 1),
+        PieceType::Knight => Bitboard::far_ranks(owner, 2),
+        _ => Bitboard::EMPTY,
+    }
+}
+
+/// Push a board move with every promotion choice the rules allow, keeping
+/// only the choices for which `keep` accepts the resulting piece kind
+fn push_board_moves(
+    moves: &mut Vec<Move>,
+    piece: Piece,
+    from: Square,
+    to: Square,
+    keep: impl Fn(PieceType) -> bool,
+) {
+    let us = piece.owner;
+    if let Some(promoted) = piece.piece_type.promote() {
+        if (from.is_promotion_zone(us) || to.is_promotion_zone(us)) && keep(promoted) {
+            moves.push(Move::Normal {
+                from,
+                to,
+                promote: true,
+            });
+        }
+    }
+    if !is_dead_placement(piece.piece_type, us, to) && keep(piece.piece_type) {
+        moves.push(Move::Normal {
+            from,
+            to,
+            promote: false,
+        });
+    }
+}
+
+impl Position {
+    /// Squares attacked by `piece` standing on `square`
+    pub fn attacks(&self, square: Square, piece: Piece) -> Bitboard {
+        piece_attacks(piece, square, self.occupied())
+    }
+
+    /// Squares attacked by `piece` standing on `square`, as a list
+    pub fn attacks_from(&self, square: Square, piece: Piece) -> Vec<Square> {
+        self.attacks(square, piece).into_iter().collect()
+    }
+
+    /// Pieces of `by` that attack `square`, with sliders blocked by `occupied`
+    pub fn attackers_to(&self, square: Square, by: Player, occupied: Bitboard) -> Bitboard {
+        let mut attackers = Bitboard::EMPTY;
+        for piece_type in PieceType::ALL {
+            let pieces = self.pieces_of(piece_type, by);
+            if !pieces.is_empty() {
+                let reversed = Piece::new(piece_type, by.opponent());
+                attackers |= piece_attacks(reversed, square, occupied) & pieces;
+            }
+        }
+        attackers
+    }
+
+    /// Whether any piece of `by` attacks `square`
+    pub fn is_attacked(&self, square: Square, by: Player) -> bool {
+        !self.attackers_to(square, by, self.occupied()).is_empty()
+    }
+
+    /// Squares of the pieces of `by` that attack `square`
+    pub fn attackers_of(&self, square: Square, by: Player) -> Vec<Square> {
+        self.attackers_to(square, by, self.occupied()).into_iter().collect()
+    }
+
+    /// Whether the given player's king is attacked
+    pub fn is_in_check(&self, player: Player) -> bool {
+        self.king_square(player)
+            .is_some_and(|king| self.is_attacked(king, player.opponent()))
+    }
+
+    /// Whether the side to move is in check
+    pub fn in_check(&self) -> bool {
+        self.is_in_check(self.side_to_move())
+    }
+
+    /// Whether the side to move is checkmated
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.evasion_moves().is_empty()
+    }
+
+    /// Whether `mv` (assumed legal) puts the opponent in check
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let mut after = self.clone();
+        after.do_move(mv);
+        after.in_check()
+    }
+
+    /// Generate moves that obey piece movement and drop rules, without
+    /// checking king safety or uchifuzume
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let us = self.side_to_move();
+        let ours = self.occupied_by(us);
+        let mut moves = Vec::new();
+
+        for from in ours {
+            let piece = self.piece_at(from).expect("occupied square");
+            for to in self.attacks(from, piece) & !ours {
+                push_board_moves(&mut moves, piece, from, to, |_| true);
+            }
+        }
+
+        self.generate_drops(us, Bitboard::ALL, &mut moves);
+
+        moves
+    }
+
+    /// Generate the pseudo-legal captures of the side to move, including
+    /// their promotion choices
+    pub fn capture_moves(&self) -> Vec<Move> {
+        let us = self.side_to_move();
+        let theirs = self.occupied_by(us.opponent());
+        let mut moves = Vec::new();
+
+        for from in self.occupied_by(us) {
+            let piece = self.piece_at(from).expect("occupied square");
+            for to in self.attacks(from, piece) & theirs {
+                push_board_moves(&mut moves, piece, from, to, |_| true);
+            }
+        }
+
+        moves
+    }
+
+    /// Push the drops of every piece in hand onto the empty squares among
+    /// `targets`
+    fn generate_drops(&self, us: Player, targets: Bitboard, moves: &mut Vec<Move>) {
+        for (piece_type, _) in self.hand(us).iter() {
+            for to in self.drop_targets(piece_type, us, targets) {
+                moves.push(Move::Drop { piece_type, to });
+            }
+        }
+    }
+
+    /// Squares among `targets` where `us` may drop the given piece: empty,
+    /// not dead, and for pawns not on a file that already has one (nifu)
+    fn drop_targets(&self, piece_type: PieceType, us: Player, targets: Bitboard) -> Bitboard {
+        let mut squares = targets & !self.occupied() & !dead_zone(piece_type, us);
+        if piece_type == PieceType::Pawn {
+            for pawn in self.pieces_of(PieceType::Pawn, us) {
+                squares &= !Bitboard::file(pawn.file());
+            }
+        }
+        squares
+    }
+
+    /// Generate the legal moves of the side to move that give check
+    ///
+    /// Covers direct checks (including promotions), discovered checks and
+    /// checking drops. Uchifuzume is excluded like in [`Position::legal_moves`].
+    pub fn check_moves(&self) -> Vec<Move> {
+        let us = self.side_to_move();
+        let them = us.opponent();
+        let Some(king) = self.king_square(them) else {
+            return Vec::new();
+        };
+
+        let ours = self.occupied_by(us);
+        let occupied = self.occupied();
+        let discoverers = self.discovered_check_lines(us, king);
+        let mut candidates = Vec::new();
+
+        for from in ours {
+            let piece = self.piece_at(from).expect("occupied square");
+            let line = discoverers.iter().find(|(sq, _)| *sq == from).map(|(_, line)| *line);
+            let vacated = occupied & !Bitboard::from_square(from);
+
+            for to in self.attacks(from, piece) & !ours {
+                if line.is_some_and(|line| !line.contains(to)) {
+                    push_board_moves(&mut candidates, piece, from, to, |_| true);
+                    continue;
+                }
+                let after = vacated | Bitboard::from_square(to);
+                push_board_moves(&mut candidates, piece, from, to, |piece_type| {
+                    piece_attacks(Piece::new(piece_type, us), to, after).contains(king)
+                });
+            }
+        }
+
+        // A piece dropped where the king would attack it with the same kind
+        // attacks the king
+        for (piece_type, _) in self.hand(us).iter() {
+            let checks = piece_attacks(Piece::new(piece_type, them), king, occupied);
+            for to in self.drop_targets(piece_type, us, checks) {
+                candidates.push(Move::Drop { piece_type, to });
+            }
+        }
+
+        let mut scratch = self.clone();
+        candidates.into_iter().filter(|&mv| scratch.is_legal_pseudo(mv, true)).collect()
+    }
+
+    /// Generate the legal moves of the side to move when in check
+    ///
+    /// Only king moves, captures of a single checker and interpositions
+    /// against a sliding checker are considered. When not in check this is
+    /// the same as [`Position::legal_moves`].
+    pub fn evasion_moves(&self) -> Vec<Move> {
+        let us = self.side_to_move();
+        let Some(king) = self.king_square(us) else {
+            return self.legal_moves();
+        };
+        let checkers = self.attackers_to(king, us.opponent(), self.occupied());
+        if checkers.is_empty() {
+            return self.legal_moves();
+        }
+
+        let mut scratch = self.clone();
+        self.evasion_candidates(king, checkers)
+            .into_iter()
+            .filter(|&mv| scratch.is_legal_pseudo(mv, true))
+            .collect()
+    }
+
+    /// Pseudo-legal evasions of the side to move from the given checkers
+    fn evasion_candidates(&self, king: Square, checkers: Bitboard) -> Vec<Move> {
+        let us = self.side_to_move();
+        let ours = self.occupied_by(us);
+        let king_piece = Piece::new(PieceType::King, us);
+
+        let mut candidates: Vec<Move> = (self.attacks(king, king_piece) & !ours)
+            .into_iter()
+            .map(|to| Move::Normal {
+                from: king,
+                to,
+                promote: false,
+            })
+            .collect();
+
+        // Double check: only the king can move
+        if let (1, Some(checker)) = (checkers.count(), checkers.lsb()) {
+            let blocks = between(checker, king);
+            let targets = blocks | checkers;
+
+            for from in ours & !Bitboard::from_square(king) {
+                let piece = self.piece_at(from).expect("occupied square");
+                for to in self.attacks(from, piece) & targets {
+                    push_board_moves(&mut candidates, piece, from, to, |_| true);
+                }
+            }
+
+            self.generate_drops(us, blocks, &mut candidates);
+        }
+
+        candidates
+    }
+
+    /// Pieces of `us` that would give a discovered check by leaving their
+    /// line to `king`, with the squares of that line they may stay on
+    fn discovered_check_lines(&self, us: Player, king: Square) -> Vec<(Square, Bitboard)> {
+        let ours = self.occupied_by(us);
+        let occupied = self.occupied();
+        let mut lines = Vec::new();
+
+        for slider in ours {
+            let piece = self.piece_at(slider).expect("occupied square");
+            if !is_slider(piece.piece_type)
+                || !slider_attacks(piece, slider, Bitboard::EMPTY).contains(king)
+            {
+                continue;
+            }
+            let line = between(slider, king);
+            let blockers = line & occupied;
+            if blockers.count() == 1 && !(blockers & ours).is_empty() {
+                let blocker = blockers.lsb().expect("one blocker");
+                lines.push((blocker, line));
+            }
+        }
+
+        lines
+    }
+
+    /// Generate all legal moves for the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut scratch = self.clone();
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|&mv| scratch.is_legal_pseudo(mv, true))
+            .collect()
+    }
+
+    /// Whether the side to move has at least one legal move
+    pub fn has_legal_move(&self) -> bool {
+        let mut scratch = self.clone();
+        self.pseudo_legal_moves()
+            .into_iter()
+            .any(|mv| scratch.is_legal_pseudo(mv, true))
+    }
+
+    /// Whether `mv` is legal in this position
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.pseudo_legal_moves().contains(&mv) && self.clone().is_legal_pseudo(mv, true)
+    }
+
+    /// Legality test for a pseudo-legal move. The position is restored
+    /// before returning.
+    fn is_legal_pseudo(&mut self, mv: Move, check_uchifuzume: bool) -> bool {
+        let us = self.side_to_move();
+        let captured = self.do_move(mv);
+        let mut legal = !self.is_in_check(us);
+
+        if legal && check_uchifuzume {
+            if let Move::Drop {
+                piece_type: PieceType::Pawn,
+                to,
+            } = mv
+            {
+                let pawn = Piece::new(PieceType::Pawn, us);
+                let gives_check = self
+                    .king_square(us.opponent())
+                    .is_some_and(|king| step_attacks(pawn, to).contains(king));
+                if gives_check && !self.has_evasion() {
+                    legal = false;
+                }
+            }
+        }
+
+        self.undo_move(mv, captured);
+        legal
+    }
+
+    /// Whether the side to move has any move that does not leave its king in
+    /// check. Uchifuzume is not considered for these replies.
+    fn has_evasion(&mut self) -> bool {
+        let us = self.side_to_move();
+        let candidates = match self.king_square(us) {
+            Some(king) => {
+                let checkers = self.attackers_to(king, us.opponent(), self.occupied());
+                if checkers.is_empty() {
+                    self.pseudo_legal_moves()
+                } else {
+                    self.evasion_candidates(king, checkers)
+                }
+            }
+            None => self.pseudo_legal_moves(),
+        };
+        candidates.into_iter().any(|mv| self.is_legal_pseudo(mv, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(sfen: &str) -> Position {
+        Position::from_sfen(sfen).unwrap()
+    }
+
+    fn has_move(position: &Position, usi: &str) -> bool {
+        position.legal_moves().contains(&Move::from_usi(usi).unwrap())
+    }
+
+    fn perft(position: &mut Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for mv in position.legal_moves() {
+            let captured = position.do_move(mv);
+            nodes += perft(position, depth - 1);
+            position.undo_move(mv, captured);
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_startpos_perft() {
+        let mut position = Position::startpos();
+        assert_eq!(perft(&mut position, 1), 30);
+        assert_eq!(perft(&mut position, 2), 900);
+        assert_eq!(perft(&mut position, 3), 25470);
+        assert_eq!(position, Position::startpos());
+    }
+
+    #[test]
+    fn test_maximum_move_position() {
+        // Well-known position with the maximum number of legal moves
+        let position = pos("R8/2K1S1SSk/4B4/9/9/9/9/9/1L1L1L3 b RBGSNLP3g3n17p 1");
+        assert_eq!(position.legal_moves().len(), 593);
+    }
+
+    #[test]
+    fn test_forced_promotion() {
+        let position = pos("4k4/9/4P4/9/9/9/9/9/4K4 b - 1");
+        // 5c5b may or may not promote, but the pawn must promote on 1st rank
+        assert!(has_move(&position, "5c5b+"));
+        assert!(has_move(&position, "5c5b"));
+
+        let position = pos("8k/4P4/9/9/9/9/9/9/4K4 b - 1");
+        assert!(has_move(&position, "5b5a+"));
+        assert!(!has_move(&position, "5b5a"));
+
+        let position = pos("8k/9/9/4N4/9/9/9/9/4K4 b - 1");
+        assert!(has_move(&position, "5d4b+"));
+        assert!(!has_move(&position, "5d4b"));
+    }
+
+    #[test]
+    fn test_drop_restrictions() {
+        let position = pos("8k/9/9/9/9/9/4P4/9/4K4 b PNL 1");
+        // Nifu: no second pawn on file 5
+        assert!(!has_move(&position, "P*5e"));
+        assert!(has_move(&position, "P*4e"));
+        // Dead pieces
+        assert!(!has_move(&position, "P*4a"));
+        assert!(!has_move(&position, "L*4a"));
+        assert!(!has_move(&position, "N*4b"));
+        assert!(has_move(&position, "N*4c"));
+    }
+
+    #[test]
+    fn test_uchifuzume() {
+        // P*1b would mate the king on 1a: forbidden
+        let position = pos("8k/6S2/7G1/9/9/9/9/9/4K4 b P 1");
+        assert!(!has_move(&position, "P*1b"));
+        assert!(has_move(&position, "P*1c"));
+
+        // Without the silver the king escapes to 2a, so the drop is legal
+        let position = pos("8k/9/7G1/9/9/9/9/9/4K4 b P 1");
+        assert!(has_move(&position, "P*1b"));
+    }
+
+    #[test]
+    fn test_pinned_piece_and_check_evasion() {
+        // The gold on 5h is pinned by the rook on 5a
+        let position = pos("4r4/9/9/9/9/9/9/4G4/4K4 b - 1");
+        assert!(!has_move(&position, "5h4h"));
+        assert!(has_move(&position, "5h5g"));
+
+        // In check from the rook: only evasions are legal
+        let position = pos("4r4/9/9/9/9/9/9/9/4K4 b G 1");
+        assert!(position.in_check());
+        for mv in position.legal_moves() {
+            let mut after = position.clone();
+            after.do_move(mv);
+            assert!(!after.is_in_check(Player::Black), "{mv} leaves king in check");
+        }
+        assert!(has_move(&position, "G*5h"));
+        assert!(!has_move(&position, "G*4h"));
+    }
+
+    /// Positions from deterministic random games that favour checks, so
+    /// both check and evasion generation get exercised
+    fn random_positions(games: usize, plies: usize) -> Vec<Position> {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next_random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let mut positions = Vec::new();
+        for _ in 0..games {
+            let mut position = Position::startpos();
+            for _ in 0..plies {
+                let moves = position.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let checks: Vec<Move> =
+                    moves.iter().copied().filter(|&mv| position.gives_check(mv)).collect();
+                let candidates = if !checks.is_empty() && next_random() % 2 == 0 {
+                    checks
+                } else {
+                    moves
+                };
+                let mv = candidates[(next_random() % candidates.len() as u64) as usize];
+                position.do_move(mv);
+                positions.push(position.clone());
+            }
+        }
+        positions
+    }
+
+    fn sorted_usi(moves: impl IntoIterator<Item = Move>) -> Vec<String> {
+        let mut usi: Vec<String> = moves.into_iter().map(|mv| mv.to_usi()).collect();
+        usi.sort();
+        usi
+    }
+
+    #[test]
+    fn test_check_and_evasion_moves_match_legal_moves() {
+        let positions = random_positions(8, 120);
+        let in_check = positions.iter().filter(|p| p.in_check()).count();
+        assert!(in_check > 50, "only {in_check} positions in check");
+
+        for position in &positions {
+            let legal = position.legal_moves();
+            let expected_checks = legal.iter().copied().filter(|&mv| position.gives_check(mv));
+            assert_eq!(
+                sorted_usi(position.check_moves()),
+                sorted_usi(expected_checks),
+                "check moves differ in {}",
+                position.to_sfen()
+            );
+            assert_eq!(
+                sorted_usi(position.evasion_moves()),
+                sorted_usi(legal),
+                "evasions differ in {}",
+                position.to_sfen()
+            );
+        }
+    }
+
+    #[test]
+    fn test_capture_moves_match_pseudo_legal_captures() {
+        for position in &random_positions(4, 80) {
+            let expected = position
+                .pseudo_legal_moves()
+                .into_iter()
+                .filter(|mv| !mv.is_drop() && position.piece_at(mv.to()).is_some());
+            assert_eq!(
+                sorted_usi(position.capture_moves()),
+                sorted_usi(expected),
+                "captures differ in {}",
+                position.to_sfen()
+            );
+        }
+    }
+
+    #[test]
+    fn test_discovered_check() {
+        // Moving the silver off the file uncovers the rook on 5i
+        let position = pos("4k4/9/9/9/4S4/9/9/9/4R3K b - 1");
+        let checks = sorted_usi(position.check_moves());
+        assert!(checks.contains(&"5e4d".to_string()));
+        assert!(checks.contains(&"5e6f".to_string()));
+        assert!(!checks.contains(&"5e5d".to_string()));
+
+        // Promotion turns a non-checking move into a check
+        let position = pos("4k4/6S2/9/9/9/9/9/9/8K b - 1");
+        let checks = sorted_usi(position.check_moves());
+        assert!(checks.contains(&"3b4a+".to_string()));
+        assert!(!checks.contains(&"3b4a".to_string()));
+    }
+
+    #[test]
+    fn test_double_check_evasions_are_king_moves() {
+        // Rook on 5i and bishop on 1e both check the king on 5a
+        let position = pos("4k4/9/9/9/8B/9/9/9/4R3K w G 1");
+        assert_eq!(
+            position
+                .attackers_of(position.king_square(Player::White).unwrap(), Player::Black)
+                .len(),
+            2
+        );
+        let evasions = position.evasion_moves();
+        assert!(!evasions.is_empty());
+        assert!(evasions.iter().all(|mv| mv.from() == Some(Square::new(5, 1).unwrap())));
+    }
+}
@@ -0,0 +1,116 @@
+//! Moves and their USI notation
+
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+use crate::shogi::{PieceType, Square};
+
+/// A move: either a board move (with optional promotion) or a drop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Normal {
+        from: Square,
+        to: Square,
+        promote: bool,
+    },
+    Drop {
+        piece_type: PieceType,
+        to: Square,
+    },
+}
+
+impl Move {
+    /// Destination square
+    pub fn to(self) -> Square {
+        match self {
+            Move::Normal { to, .. } | Move::Drop { to, .. } => to,
+        }
+    }
+
+    /// Origin square, or `None` for drops
+    pub fn from(self) -> Option<Square> {
+        match self {
+            Move::Normal { from, .. } => Some(from),
+            Move::Drop { .. } => None,
+        }
+    }
+
+    /// Whether the move is a drop
+    pub fn is_drop(self) -> bool {
+        matches!(self, Move::Drop { .. })
+    }
+
+    /// Whether the move promotes the moving piece
+    pub fn is_promotion(self) -> bool {
+        matches!(self, Move::Normal { promote: true, .. })
+    }
+
+    /// Parse USI move notation ("7g7f", "8h2b+", "P*5e")
+    pub fn from_usi(notation: &str) -> Result<Move> {
+        if let Some((piece, square)) = notation.split_once('*') {
+            let mut chars = piece.chars();
+            let piece_type = match (chars.next(), chars.next()) {
+                (Some(ch), None) if ch.is_ascii_uppercase() => PieceType::from_sfen_char(ch),
+                _ => None,
+            }
+            .filter(|pt| *pt != PieceType::King)
+            .ok_or_else(|| anyhow!("Invalid drop piece: {}", notation))?;
+            let to = Square::from_usi(square)
+                .ok_or_else(|| anyhow!("Invalid drop square: {}", notation))?;
+            return Ok(Move::Drop { piece_type, to });
+        }
+
+        let (body, promote) = match notation.strip_suffix('+') {
+            Some(body) => (body, true),
+            None => (notation, false),
+        };
+        if body.len() != 4 || !body.is_ascii() {
+            return Err(anyhow!("Invalid move notation: {}", notation));
+        }
+        let from = Square::from_usi(&body[0..2])
+            .ok_or_else(|| anyhow!("Invalid from square: {}", notation))?;
+        let to = Square::from_usi(&body[2..4])
+            .ok_or_else(|| anyhow!("Invalid to square: {}", notation))?;
+
+        Ok(Move::Normal { from, to, promote })
+    }
+
+    /// USI notation of the move
+    pub fn to_usi(self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Move::Normal { from, to, promote } => {
+                write!(f, "{from}{to}{}", if promote { "+" } else { "" })
+            }
+            Move::Drop { piece_type, to } => write!(f, "{}*{to}", piece_type.sfen_char()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usi_roundtrip() {
+        for notation in ["7g7f", "8h2b+", "P*5e", "R*1a", "1i1h"] {
+            let mv = Move::from_usi(notation).unwrap();
+            assert_eq!(mv.to_usi(), notation);
+        }
+    }
+
+    #[test]
+    fn test_invalid_usi() {
+        for notation in [
+            "", "7g7", "K*5e", "p*5e", "0a1a", "7g7j+", "P*5", "7g7`", "P*5`", "7g歩",
+        ] {
+            assert!(Move::from_usi(notation).is_err(), "{notation} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Japanese KIF move notation
+//!
+//! Moves are written the way KIF files record them: destination as a
+//! full-width file and kanji rank, the piece name, then `成`/`不成`/`打`
+//! and the origin square in parentheses, e.g. `７六歩(77)`, `２二角成(88)`,
+//! `５二金打`. A move landing on the previous move's square is written
+//! `同　` instead of the destination.
+
+use crate::shogi::{Move, PieceType, Position, Square};
+
+const FILES: [&str; 9] = ["１", "２", "３", "４", "５", "６", "７", "８", "９"];
+const RANKS: [&str; 9] = ["一", "二", "三", "四", "五", "六", "七", "八", "九"];
+
+/// KIF name of a piece type
+pub fn kif_piece_name(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "歩",
+        PieceType::Lance => "香",
+        PieceType::Knight => "桂",
+        PieceType::Silver => "銀",
+        PieceType::Gold => "金",
+        PieceType::Bishop => "角",
+        PieceType::Rook => "飛",
+        PieceType::King => "玉",
+        PieceType::ProPawn => "と",
+        PieceType::ProLance => "成香",
+        PieceType::ProKnight => "成桂",
+        PieceType::ProSilver => "成銀",
+        PieceType::Horse => "馬",
+        PieceType::Dragon => "龍",
+    }
+}
+
+/// KIF notation of `mv` played from `position`
+///
+/// `previous_to` is the destination of the preceding move, used for `同`.
+/// Returns `None` if there is no piece to move on the origin square.
+pub fn move_to_kif(position: &Position, mv: Move, previous_to: Option<Square>) -> Option<String> {
+    let to = mv.to();
+    let mut notation = if previous_to == Some(to) {
+        "同　".to_string()
+    } else {
+        format!("{}{}", FILES[to.file() as usize - 1], RANKS[to.rank() as usize - 1])
+    };
+
+    match mv {
+        Move::Drop { piece_type, .. } => {
+            notation.push_str(kif_piece_name(piece_type));
+            notation.push('打');
+        }
+        Move::Normal { from, promote, .. } => {
+            let piece = position.piece_at(from)?;
+            notation.push_str(kif_piece_name(piece.piece_type));
+            if promote {
+                notation.push('成');
+            } else if piece.piece_type.can_promote()
+                && (from.is_promotion_zone(piece.owner) || to.is_promotion_zone(piece.owner))
+            {
+                notation.push_str("不成");
+            }
+            notation.push_str(&format!("({}{})", from.file(), from.rank()));
+        }
+    }
+
+    Some(notation)
+}
+
+/// KIF notation of a move sequence starting from `position`
+///
+/// Stops at the first move that cannot be played on the board.
+pub fn moves_to_kif(position: &Position, moves: &[Move]) -> Vec<String> {
+    let mut position = position.clone();
+    let mut previous_to = None;
+    let mut notations = Vec::with_capacity(moves.len());
+
+    for &mv in moves {
+        let Some(notation) = move_to_kif(&position, mv, previous_to) else {
+            break;
+        };
+        notations.push(notation);
+        position.do_move(mv);
+        previous_to = Some(mv.to());
+    }
+
+    notations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usi_moves(moves: &[&str]) -> Vec<Move> {
+        moves.iter().map(|m| Move::from_usi(m).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_kif_line_from_startpos() {
+        let position = Position::startpos();
+        let moves = usi_moves(&["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+
+        assert_eq!(
+            moves_to_kif(&position, &moves),
+            vec![
+                "７六歩(77)",
+                "３四歩(33)",
+                "２二角成(88)",
+                "同　銀(31)",
+                "４五角打"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kif_non_promotion_and_promoted_pieces() {
+        let position = Position::from_sfen("4k4/9/9/3S5/9/9/9/9/+R3K4 b - 1").unwrap();
+        let moves = usi_moves(&["6d5c", "5a5b", "9i9b"]);
+
+        assert_eq!(
+            moves_to_kif(&position, &moves),
+            vec!["５三銀不成(64)", "５二玉(51)", "９二龍(99)"]
+        );
+    }
+
+    #[test]
+    fn test_kif_stops_at_unplayable_move() {
+        let position = Position::startpos();
+        let moves = usi_moves(&["7g7f", "5e5d"]);
+
+        assert_eq!(moves_to_kif(&position, &moves), vec!["７六歩(77)"]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Players, piece kinds and pieces
+//!
+//! Promoted pieces are distinct piece kinds (14 in total), which keeps the
+//! board representation to a single value per square.
+
+use wasm_bindgen::prelude::*;
+
+/// Side of the game (先手 = Black, 後手 = White)
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Player {
+    Black,
+    White,
+}
+
+impl Player {
+    /// Both players, indexable by `Player::index`
+    pub const ALL: [Player; 2] = [Player::Black, Player::White];
+
+    /// The other player
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::Black => Player::White,
+            Player::White => Player::Black,
+        }
+    }
+
+    /// Index for per-player arrays (Black = 0, White = 1)
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// All 14 kinds of shogi pieces, including promoted ones
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PieceType {
+    Pawn,      // 歩
+    Lance,     // 香
+    Knight,    // 桂
+    Silver,    // 銀
+    Gold,      // 金
+    Bishop,    // 角
+    Rook,      // 飛
+    King,      // 玉
+    ProPawn,   // と
+    ProLance,  // 成香
+    ProKnight, // 成桂
+    ProSilver, // 成銀
+    Horse,     // 馬
+    Dragon,    // 龍
+}
+
+impl PieceType {
+    /// All piece kinds in discriminant order
+    pub const ALL: [PieceType; 14] = [
+        PieceType::Pawn,
+        PieceType::Lance,
+        PieceType::Knight,
+        PieceType::Silver,
+        PieceType::Gold,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::King,
+        PieceType::ProPawn,
+        PieceType::ProLance,
+        PieceType::ProKnight,
+        PieceType::ProSilver,
+        PieceType::Horse,
+        PieceType::Dragon,
+    ];
+
+    /// Piece kinds that can be held in hand, indexable by `hand_index`
+    pub const HAND: [PieceType; 7] = [
+        PieceType::Pawn,
+        PieceType::Lance,
+        PieceType::Knight,
+        PieceType::Silver,
+        PieceType::Gold,
+        PieceType::Bishop,
+        PieceType::Rook,
+    ];
+
+    /// Index for per-kind arrays (0-13)
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Whether this is a promoted kind
+    pub fn is_promoted(self) -> bool {
+        self as u8 >= PieceType::ProPawn as u8
+    }
+
+    /// Whether this kind can still promote
+    pub fn can_promote(self) -> bool {
+        self.promote().is_some()
+    }
+
+    /// Promoted kind, or `None` for gold, king and already promoted kinds
+    pub fn promote(self) -> Option<PieceType> {
+        match self {
+            PieceType::Pawn => Some(PieceType::ProPawn),
+            PieceType::Lance => Some(PieceType::ProLance),
+            PieceType::Knight => Some(PieceType::ProKnight),
+            PieceType::Silver => Some(PieceType::ProSilver),
+            PieceType::Bishop => Some(PieceType::Horse),
+            PieceType::Rook => Some(PieceType::Dragon),
+            _ => None,
+        }
+    }
+
+    /// Unpromoted kind (identity for unpromoted kinds)
+    pub fn unpromote(self) -> PieceType {
+        match self {
+            PieceType::ProPawn => PieceType::Pawn,
+            PieceType::ProLance => PieceType::Lance,
+            PieceType::ProKnight => PieceType::Knight,
+            PieceType::ProSilver => PieceType::Silver,
+            PieceType::Horse => PieceType::Bishop,
+            PieceType::Dragon => PieceType::Rook,
+            other => other,
+        }
+    }
+
+    /// Index into `PieceType::HAND`, or `None` for the king
+    ///
+    /// Promoted kinds map to their unpromoted hand kind.
+    pub fn hand_index(self) -> Option<usize> {
+        match self.unpromote() {
+            PieceType::King => None,
+            base => Some(base as usize),
+        }
+    }
+
+    /// SFEN letter of the unpromoted kind (uppercase)
+    pub fn sfen_char(self) -> char {
+        match self.unpromote() {
+            PieceType::Pawn => 'P',
+            PieceType::Lance => 'L',
+            PieceType::Knight => 'N',
+            PieceType::Silver => 'S',
+            PieceType::Gold => 'G',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            _ => 'K',
+        }
+    }
+
+    /// Unpromoted kind for an SFEN letter (case-insensitive)
+    pub fn from_sfen_char(ch: char) -> Option<PieceType> {
+        match ch.to_ascii_uppercase() {
+            'P' => Some(PieceType::Pawn),
+            'L' => Some(PieceType::Lance),
+            'N' => Some(PieceType::Knight),
+            'S' => Some(PieceType::Silver),
+            'G' => Some(PieceType::Gold),
+            'B' => Some(PieceType::Bishop),
+            'R' => Some(PieceType::Rook),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+}
+
+/// A piece on the board
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub piece_type: PieceType,
+    pub owner: Player,
+}
+
+#[wasm_bindgen]
+impl Piece {
+    #[wasm_bindgen(constructor)]
+    pub fn new(piece_type: PieceType, owner: Player) -> Piece {
+        Piece { piece_type, owner }
+    }
+
+    /// Whether the piece is promoted
+    #[wasm_bindgen(getter)]
+    pub fn promoted(&self) -> bool {
+        self.piece_type.is_promoted()
+    }
+}
+
+impl Piece {
+    /// SFEN token for this piece (e.g. "P", "+b")
+    pub fn to_sfen(self) -> String {
+        let ch = match self.owner {
+            Player::Black => self.piece_type.sfen_char(),
+            Player::White => self.piece_type.sfen_char().to_ascii_lowercase(),
+        };
+        if self.piece_type.is_promoted() {
+            format!("+{ch}")
+        } else {
+            ch.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_promote_unpromote_roundtrip() {
+        for pt in PieceType::ALL {
+            if let Some(promoted) = pt.promote() {
+                assert!(promoted.is_promoted());
+                assert_eq!(promoted.unpromote(), pt);
+            } else {
+                assert!(matches!(pt, PieceType::Gold | PieceType::King) || pt.is_promoted());
+            }
+        }
+    }
+
+    #[test]
+    fn test_hand_index() {
+        assert_eq!(PieceType::Pawn.hand_index(), Some(0));
+        assert_eq!(PieceType::Dragon.hand_index(), Some(6));
+        assert_eq!(PieceType::King.hand_index(), None);
+        for (i, pt) in PieceType::HAND.iter().enumerate() {
+            assert_eq!(pt.hand_index(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_piece_sfen() {
+        assert_eq!(Piece::new(PieceType::Pawn, Player::Black).to_sfen(), "P");
+        assert_eq!(Piece::new(PieceType::Horse, Player::White).to_sfen(), "+b");
+        assert_eq!(Piece::new(PieceType::King, Player::White).to_sfen(), "k");
+    }
+}
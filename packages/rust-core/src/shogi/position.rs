@@ -0,0 +1,320 @@
+//! Shogi position: board, both hands, side to move and ply
+//!
+//! The board is kept twice: a square-indexed array for piece lookups and
+//! bitboards per owner and per piece kind for attack generation. Both are
+//! updated together by `set_piece`, `do_move` and `undo_move`, which also
+//! keep the opening book key ([`Position::book_key`]) current. SFEN reading
+//! and writing lives in [`crate::shogi::sfen`].
+
+use crate::shogi::{Bitboard, Hand, Move, Piece, PieceType, Player, Square, ZobristKeys};
+
+/// SFEN of the standard initial position
+pub const STARTPOS_SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+/// A complete shogi position
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    board: [Option<Piece>; Square::NUM],
+    by_owner: [Bitboard; 2],
+    by_type: [Bitboard; PieceType::ALL.len()],
+    hands: [Hand; 2],
+    side_to_move: Player,
+    ply: u32,
+    key: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Position {
+    /// Empty board, empty hands, Black to move at ply 1
+    pub fn empty() -> Self {
+        Self {
+            board: [None; Square::NUM],
+            by_owner: [Bitboard::EMPTY; 2],
+            by_type: [Bitboard::EMPTY; PieceType::ALL.len()],
+            hands: [Hand::new(); 2],
+            side_to_move: Player::Black,
+            ply: 1,
+            key: 0,
+        }
+    }
+
+    /// Standard initial position
+    pub fn startpos() -> Self {
+        Self::from_sfen(STARTPOS_SFEN).expect("startpos SFEN is valid")
+    }
+
+    /// Piece on the given square
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[square.index()]
+    }
+
+    /// Place or remove a piece
+    pub fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        let keys = ZobristKeys::get();
+        if let Some(old) = self.board[square.index()] {
+            self.by_owner[old.owner.index()].clear(square);
+            self.by_type[old.piece_type.index()].clear(square);
+            self.key ^= keys.piece(square, old);
+        }
+        if let Some(new) = piece {
+            self.by_owner[new.owner.index()].set(square);
+            self.by_type[new.piece_type.index()].set(square);
+            self.key ^= keys.piece(square, new);
+        }
+        self.board[square.index()] = piece;
+    }
+
+    /// All occupied squares
+    pub fn occupied(&self) -> Bitboard {
+        self.by_owner[0] | self.by_owner[1]
+    }
+
+    /// Squares occupied by the given player's pieces
+    pub fn occupied_by(&self, player: Player) -> Bitboard {
+        self.by_owner[player.index()]
+    }
+
+    /// Squares holding pieces of the given kind, of either player
+    pub fn pieces_of_type(&self, piece_type: PieceType) -> Bitboard {
+        self.by_type[piece_type.index()]
+    }
+
+    /// Squares holding the given player's pieces of the given kind
+    pub fn pieces_of(&self, piece_type: PieceType, player: Player) -> Bitboard {
+        self.by_type[piece_type.index()] & self.by_owner[player.index()]
+    }
+
+    /// Hand of the given player
+    pub fn hand(&self, player: Player) -> &Hand {
+        &self.hands[player.index()]
+    }
+
+    /// Set how many pieces of a kind the player holds in hand
+    pub fn set_hand_count(&mut self, player: Player, piece_type: PieceType, count: u8) {
+        let keys = ZobristKeys::get();
+        let hand = &mut self.hands[player.index()];
+        self.key ^= keys.hand_total(player, piece_type, hand.count(piece_type))
+            ^ keys.hand_total(player, piece_type, count);
+        hand.set(piece_type, count);
+    }
+
+    /// Put a (captured) piece into the player's hand, demoted
+    fn add_to_hand(&mut self, player: Player, piece_type: PieceType) {
+        let hand = &mut self.hands[player.index()];
+        self.key ^= ZobristKeys::get().hand(player, piece_type, hand.count(piece_type));
+        hand.add(piece_type);
+    }
+
+    /// Take a piece out of the player's hand, returning false if none was held
+    fn remove_from_hand(&mut self, player: Player, piece_type: PieceType) -> bool {
+        let hand = &mut self.hands[player.index()];
+        let removed = hand.remove(piece_type);
+        if removed {
+            self.key ^= ZobristKeys::get().hand(player, piece_type, hand.count(piece_type));
+        }
+        removed
+    }
+
+    /// Opening book key: board and hands, ignoring side to move and ply
+    ///
+    /// Equal to `PositionHasher::hash_position` of [`Position::to_sfen`], but
+    /// maintained incrementally.
+    pub fn book_key(&self) -> u64 {
+        self.key
+    }
+
+    /// Player to move
+    pub fn side_to_move(&self) -> Player {
+        self.side_to_move
+    }
+
+    /// Set the player to move
+    pub fn set_side_to_move(&mut self, player: Player) {
+        self.side_to_move = player;
+    }
+
+    /// Move number as written in SFEN (starts at 1)
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    /// Set the move number
+    pub fn set_ply(&mut self, ply: u32) {
+        self.ply = ply;
+    }
+
+    /// Iterate over occupied squares
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        self.occupied()
+            .into_iter()
+            .filter_map(move |sq| self.piece_at(sq).map(|p| (sq, p)))
+    }
+
+    /// Square of the given player's king, if on the board
+    pub fn king_square(&self, player: Player) -> Option<Square> {
+        self.pieces_of(PieceType::King, player).lsb()
+    }
+
+    /// Make a move in place, returning the captured piece (if any)
+    ///
+    /// The move must be pseudo-legal for the side to move. Captured pieces go
+    /// to the mover's hand unpromoted.
+    pub fn do_move(&mut self, mv: Move) -> Option<Piece> {
+        let us = self.side_to_move;
+        let captured = match mv {
+            Move::Normal { from, to, promote } => {
+                let piece = self.piece_at(from).expect("no piece on move origin");
+                let captured = self.piece_at(to);
+                if let Some(captured) = captured {
+                    self.add_to_hand(us, captured.piece_type);
+                }
+                let piece_type = if promote {
+                    piece.piece_type.promote().expect("piece cannot promote")
+                } else {
+                    piece.piece_type
+                };
+                self.set_piece(from, None);
+                self.set_piece(to, Some(Piece::new(piece_type, us)));
+                captured
+            }
+            Move::Drop { piece_type, to } => {
+                let removed = self.remove_from_hand(us, piece_type);
+                debug_assert!(removed, "dropped piece not in hand");
+                self.set_piece(to, Some(Piece::new(piece_type, us)));
+                None
+            }
+        };
+
+        self.side_to_move = us.opponent();
+        self.ply += 1;
+        captured
+    }
+
+    /// Take back a move made with `do_move`
+    pub fn undo_move(&mut self, mv: Move, captured: Option<Piece>) {
+        let us = self.side_to_move.opponent();
+        match mv {
+            Move::Normal { from, to, promote } => {
+                let piece = self.piece_at(to).expect("no piece on move destination");
+                let piece_type = if promote {
+                    piece.piece_type.unpromote()
+                } else {
+                    piece.piece_type
+                };
+                self.set_piece(from, Some(Piece::new(piece_type, us)));
+                self.set_piece(to, captured);
+                if let Some(captured) = captured {
+                    self.remove_from_hand(us, captured.piece_type);
+                }
+            }
+            Move::Drop { piece_type, to } => {
+                self.set_piece(to, None);
+                self.add_to_hand(us, piece_type);
+            }
+        }
+
+        self.side_to_move = us;
+        self.ply -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startpos() {
+        let pos = Position::startpos();
+        assert_eq!(pos.side_to_move(), Player::Black);
+        assert_eq!(pos.ply(), 1);
+        assert_eq!(pos.pieces().count(), 40);
+        assert_eq!(pos.king_square(Player::Black), Square::from_usi("5i"));
+        assert_eq!(pos.king_square(Player::White), Square::from_usi("5a"));
+        assert_eq!(
+            pos.piece_at(Square::from_usi("8h").unwrap()),
+            Some(Piece::new(PieceType::Bishop, Player::Black))
+        );
+        assert_eq!(
+            pos.piece_at(Square::from_usi("8b").unwrap()),
+            Some(Piece::new(PieceType::Rook, Player::White))
+        );
+        assert_eq!(pos.to_sfen(), STARTPOS_SFEN);
+    }
+
+    #[test]
+    fn test_sfen_roundtrip_with_hands_and_promotions() {
+        // Not a reachable position (too many pawns and lances), only the syntax matters
+        let sfen = "+B+B1Sg2nl/5kg2/4p2p1/3pspP1p/p6PP/1p1rP4/P1p2P2N/1PG1G4/LNK5R b NL2Ps2l2p 42";
+        let pos = Position::from_sfen_unchecked(sfen).unwrap();
+
+        assert_eq!(pos.hand(Player::Black).count(PieceType::Pawn), 2);
+        assert_eq!(pos.hand(Player::White).count(PieceType::Lance), 2);
+        assert_eq!(
+            pos.piece_at(Square::from_usi("9a").unwrap()),
+            Some(Piece::new(PieceType::Horse, Player::Black))
+        );
+        assert_eq!(pos.to_sfen(), sfen);
+    }
+
+    #[test]
+    fn test_do_undo_capture_and_promotion() {
+        let mut pos = Position::from_sfen("4k4/4r4/9/9/9/9/9/1B7/4K4 b - 1").unwrap();
+        let original = pos.clone();
+
+        // Bishop enters the promotion zone and promotes
+        let mv = Move::from_usi("8h3c+").unwrap();
+        let captured = pos.do_move(mv);
+        assert_eq!(captured, None);
+        assert_eq!(
+            pos.piece_at(Square::from_usi("3c").unwrap()),
+            Some(Piece::new(PieceType::Horse, Player::Black))
+        );
+        assert_eq!(pos.side_to_move(), Player::White);
+        pos.undo_move(mv, captured);
+        assert_eq!(pos, original);
+
+        let mut pos = Position::from_sfen("4k4/4+r4/9/9/9/9/9/4R4/4K4 b - 1").unwrap();
+        let original = pos.clone();
+        let mv = Move::from_usi("5h5b").unwrap();
+        let captured = pos.do_move(mv);
+        assert_eq!(captured, Some(Piece::new(PieceType::Dragon, Player::White)));
+        assert_eq!(pos.hand(Player::Black).count(PieceType::Rook), 1);
+        assert_eq!(pos.ply(), 2);
+        pos.undo_move(mv, captured);
+        assert_eq!(pos, original);
+
+        let mv = Move::from_usi("R*5e").unwrap();
+        let mut pos = Position::from_sfen("4k4/9/9/9/9/9/9/9/4K4 b R 1").unwrap();
+        let original = pos.clone();
+        pos.do_move(mv);
+        assert!(pos.hand(Player::Black).is_empty());
+        pos.undo_move(mv, None);
+        assert_eq!(pos, original);
+    }
+
+    #[test]
+    fn test_invalid_sfen() {
+        assert!(Position::from_sfen("9/9/9 b - 1").is_err());
+        assert!(Position::from_sfen(
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL x - 1"
+        )
+        .is_err());
+        assert!(Position::from_sfen(
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSN b - 1"
+        )
+        .is_err());
+        assert!(Position::from_sfen(
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b 2 1"
+        )
+        .is_err());
+        assert!(Position::from_sfen(
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNS+GKGSNL b - 1"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,542 @@
+//! SFEN and USI `position` command parsing and serialization
+//!
+//! [`Position::from_sfen`] reads `<board> <turn> <hands> [<ply>]` and checks
+//! that the result could occur in a game: no more pieces of a kind than the
+//! set holds, at most one king per side, no piece that can never move again
+//! and no two unpromoted pawns of one player on a file. A missing king is
+//! accepted, since tsume problems leave out the attacker's king.
+//!
+//! [`Position::from_sfen_unchecked`] stops after the syntax checks. Opening
+//! book hashing uses it so that any position a book file contains still gets
+//! a key.
+//!
+//! [`Position::from_usi_position`] reads the arguments of the USI `position`
+//! command, `startpos [moves ...]` or `sfen <sfen> [moves ...]`, and plays
+//! the listed moves, each of which has to be legal.
+
+use std::fmt;
+
+use crate::shogi::{Bitboard, Move, Piece, PieceType, Player, Position, Square, STARTPOS_SFEN};
+
+/// Why an SFEN string or `position` command was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SfenError {
+    /// A required field is missing (`board`, `turn` or `hands`)
+    MissingField(&'static str),
+    /// The board does not have 9 ranks
+    RankCount(usize),
+    /// A rank (1-9) does not describe exactly 9 squares
+    RankLength { rank: u8, squares: u32 },
+    /// Unknown or misplaced character in a rank
+    InvalidPiece { rank: u8, token: String },
+    /// Turn is neither `b` nor `w`
+    InvalidTurn(String),
+    /// Malformed hand field
+    InvalidHand(String),
+    /// The move number is not a non-negative integer
+    InvalidPly(String),
+    /// More pieces of a kind on the board and in hands than the set holds
+    TooManyPieces { piece_type: PieceType, count: u32 },
+    /// More than one king for a player
+    TooManyKings(Player),
+    /// A pawn, lance or knight that has no legal move left
+    DeadPiece { square: Square, piece: Piece },
+    /// Two unpromoted pawns of one player on a file (nifu)
+    DoublePawn { player: Player, file: u8 },
+    /// `position` arguments start with neither `startpos` nor `sfen`
+    InvalidCommand(String),
+    /// A move after `moves` (0-based index) is not valid USI notation
+    InvalidMove { index: usize, notation: String },
+    /// A move after `moves` (0-based index) is not legal where it is played
+    IllegalMove { index: usize, notation: String },
+}
+
+impl fmt::Display for SfenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SfenError::MissingField(field) => write!(f, "Invalid SFEN: missing {field} field"),
+            SfenError::RankCount(count) => {
+                write!(f, "Invalid rank count: expected 9, got {count}")
+            }
+            SfenError::RankLength { rank, squares } => {
+                write!(f, "Rank {rank} has {squares} squares, expected 9")
+            }
+            SfenError::InvalidPiece { rank, token } => {
+                write!(f, "Invalid piece '{token}' in rank {rank}")
+            }
+            SfenError::InvalidTurn(turn) => write!(f, "Invalid turn: {turn}"),
+            SfenError::InvalidHand(hand) => write!(f, "Invalid hand: {hand}"),
+            SfenError::InvalidPly(ply) => write!(f, "Invalid move count: {ply}"),
+            SfenError::TooManyPieces { piece_type, count } => write!(
+                f,
+                "Too many pieces of kind {}: {count}, at most {}",
+                piece_type.sfen_char(),
+                max_count(*piece_type)
+            ),
+            SfenError::TooManyKings(player) => write!(f, "{player:?} has more than one king"),
+            SfenError::DeadPiece { square, piece } => {
+                write!(f, "{} on {square} can never move", piece.to_sfen())
+            }
+            SfenError::DoublePawn { player, file } => {
+                write!(f, "{player:?} has two pawns on file {file}")
+            }
+            SfenError::InvalidCommand(command) => write!(f, "Invalid position command: {command}"),
+            SfenError::InvalidMove { index, notation } => {
+                write!(f, "Invalid move {} in move list: {notation}", index + 1)
+            }
+            SfenError::IllegalMove { index, notation } => {
+                write!(f, "Illegal move {} in move list: {notation}", index + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SfenError {}
+
+/// Pieces of a hand kind in a full set
+fn max_count(piece_type: PieceType) -> u32 {
+    match piece_type {
+        PieceType::Pawn => 18,
+        PieceType::Bishop | PieceType::Rook => 2,
+        _ => 4,
+    }
+}
+
+/// Owner of an SFEN piece letter (uppercase is Black)
+fn owner_of(ch: char) -> Player {
+    if ch.is_ascii_uppercase() {
+        Player::Black
+    } else {
+        Player::White
+    }
+}
+
+impl Position {
+    /// Parse and validate a position from SFEN (`<board> <turn> <hands> [<ply>]`),
+    /// optionally prefixed with `sfen`
+    pub fn from_sfen(sfen: &str) -> Result<Position, SfenError> {
+        let position = Self::from_sfen_unchecked(sfen)?;
+        position.validate()?;
+        Ok(position)
+    }
+
+    /// Parse a position from SFEN, checking the syntax only
+    pub fn from_sfen_unchecked(sfen: &str) -> Result<Position, SfenError> {
+        let sfen = sfen.trim();
+        let sfen = sfen.strip_prefix("sfen ").unwrap_or(sfen);
+        let mut parts = sfen.split_whitespace();
+
+        let mut position = Position::empty();
+        position.parse_board(parts.next().ok_or(SfenError::MissingField("board"))?)?;
+
+        let turn = parts.next().ok_or(SfenError::MissingField("turn"))?;
+        position.set_side_to_move(match turn {
+            "b" => Player::Black,
+            "w" => Player::White,
+            _ => return Err(SfenError::InvalidTurn(turn.to_string())),
+        });
+
+        position.parse_hands(parts.next().ok_or(SfenError::MissingField("hands"))?)?;
+
+        if let Some(ply) = parts.next() {
+            position.set_ply(ply.parse().map_err(|_| SfenError::InvalidPly(ply.to_string()))?);
+        }
+
+        Ok(position)
+    }
+
+    /// Parse the arguments of a USI `position` command (`startpos` or
+    /// `sfen <sfen>`, then optionally `moves <move>...`) and play the moves.
+    /// A leading `position` is accepted.
+    pub fn from_usi_position(command: &str) -> Result<Position, SfenError> {
+        let command = command.trim();
+        let args = command.strip_prefix("position").unwrap_or(command).trim_start();
+
+        let (setup, moves) = args.split_once(" moves").unwrap_or((args, ""));
+        let setup = setup.trim();
+
+        let mut position = if setup == "startpos" {
+            Self::from_sfen(STARTPOS_SFEN)?
+        } else if let Some(sfen) = setup.strip_prefix("sfen ") {
+            Self::from_sfen(sfen)?
+        } else {
+            return Err(SfenError::InvalidCommand(command.to_string()));
+        };
+
+        for (index, notation) in moves.split_whitespace().enumerate() {
+            let mv = Move::from_usi(notation).map_err(|_| SfenError::InvalidMove {
+                index,
+                notation: notation.to_string(),
+            })?;
+            if !position.is_legal(mv) {
+                return Err(SfenError::IllegalMove {
+                    index,
+                    notation: notation.to_string(),
+                });
+            }
+            position.do_move(mv);
+        }
+
+        Ok(position)
+    }
+
+    /// Check piece counts and placements (see the module docs)
+    pub fn validate(&self) -> Result<(), SfenError> {
+        for player in Player::ALL {
+            if self.pieces_of(PieceType::King, player).count() > 1 {
+                return Err(SfenError::TooManyKings(player));
+            }
+        }
+
+        let mut counts = [0u32; PieceType::ALL.len()];
+        for (_, piece) in self.pieces() {
+            counts[piece.piece_type.unpromote().index()] += 1;
+        }
+        for player in Player::ALL {
+            for piece_type in PieceType::HAND {
+                counts[piece_type.index()] += self.hand(player).count(piece_type) as u32;
+            }
+        }
+        for piece_type in PieceType::HAND {
+            let count = counts[piece_type.index()];
+            if count > max_count(piece_type) {
+                return Err(SfenError::TooManyPieces { piece_type, count });
+            }
+        }
+
+        for player in Player::ALL {
+            // Pawns and lances need a rank ahead, knights two
+            for (piece_type, ranks) in [
+                (PieceType::Pawn, 1),
+                (PieceType::Lance, 1),
+                (PieceType::Knight, 2),
+            ] {
+                let dead = self.pieces_of(piece_type, player) & Bitboard::far_ranks(player, ranks);
+                if let Some(square) = dead.lsb() {
+                    let piece = Piece::new(piece_type, player);
+                    return Err(SfenError::DeadPiece { square, piece });
+                }
+            }
+
+            let pawns = self.pieces_of(PieceType::Pawn, player);
+            for file in 1..=9 {
+                if (pawns & Bitboard::file(file)).count() > 1 {
+                    return Err(SfenError::DoublePawn { player, file });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_board(&mut self, board: &str) -> Result<(), SfenError> {
+        let ranks: Vec<&str> = board.split('/').collect();
+        if ranks.len() != 9 {
+            return Err(SfenError::RankCount(ranks.len()));
+        }
+
+        for (rank_idx, rank_str) in ranks.iter().enumerate() {
+            let rank = rank_idx as u8 + 1;
+            let invalid = |token: String| SfenError::InvalidPiece { rank, token };
+
+            // Squares described so far, counted from file 9
+            let mut squares = 0u32;
+            let mut chars = rank_str.chars();
+            while let Some(ch) = chars.next() {
+                if let Some(empty) = ch.to_digit(10) {
+                    if empty == 0 {
+                        return Err(invalid(ch.to_string()));
+                    }
+                    squares += empty;
+                    continue;
+                }
+
+                let (token, promoted, letter) = if ch == '+' {
+                    match chars.next() {
+                        Some(letter) => (format!("+{letter}"), true, letter),
+                        None => return Err(invalid("+".to_string())),
+                    }
+                } else {
+                    (ch.to_string(), false, ch)
+                };
+                let base =
+                    PieceType::from_sfen_char(letter).ok_or_else(|| invalid(token.clone()))?;
+                let piece_type = if promoted {
+                    base.promote().ok_or_else(|| invalid(token.clone()))?
+                } else {
+                    base
+                };
+
+                squares += 1;
+                if squares > 9 {
+                    continue;
+                }
+                let square = Square::new(10 - squares as u8, rank).expect("file and rank in range");
+                self.set_piece(square, Some(Piece::new(piece_type, owner_of(letter))));
+            }
+
+            if squares != 9 {
+                return Err(SfenError::RankLength { rank, squares });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_hands(&mut self, hands: &str) -> Result<(), SfenError> {
+        if hands == "-" {
+            return Ok(());
+        }
+
+        let invalid = || SfenError::InvalidHand(hands.to_string());
+        let mut count: Option<u32> = None;
+        for ch in hands.chars() {
+            if let Some(digit) = ch.to_digit(10) {
+                let value = count.unwrap_or(0) * 10 + digit;
+                if value > u8::MAX as u32 {
+                    return Err(invalid());
+                }
+                count = Some(value);
+                continue;
+            }
+            let piece_type = PieceType::from_sfen_char(ch)
+                .filter(|pt| *pt != PieceType::King)
+                .ok_or_else(invalid)?;
+            let count = match count.take() {
+                Some(0) => return Err(invalid()),
+                Some(count) => count,
+                None => 1,
+            };
+            let owner = owner_of(ch);
+            let total = self.hand(owner).count(piece_type) as u32 + count;
+            let total = u8::try_from(total).map_err(|_| invalid())?;
+            self.set_hand_count(owner, piece_type, total);
+        }
+        if hands.is_empty() || count.is_some() {
+            return Err(invalid());
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the position to SFEN (`<board> <turn> <hands> <ply>`)
+    pub fn to_sfen(&self) -> String {
+        let mut board = String::new();
+        for rank in 1..=9 {
+            if rank > 1 {
+                board.push('/');
+            }
+            let mut empty = 0;
+            for file in (1..=9).rev() {
+                let square = Square::new(file, rank).expect("file and rank in range");
+                match self.piece_at(square) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            board.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        board.push_str(&piece.to_sfen());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                board.push_str(&empty.to_string());
+            }
+        }
+
+        let turn = match self.side_to_move() {
+            Player::Black => "b",
+            Player::White => "w",
+        };
+
+        format!("{} {} {} {}", board, turn, self.hands_to_sfen(), self.ply())
+    }
+
+    /// SFEN hand field, in the conventional R B G S N L P order
+    fn hands_to_sfen(&self) -> String {
+        let mut hands = String::new();
+        for player in Player::ALL {
+            for &piece_type in PieceType::HAND.iter().rev() {
+                let count = self.hand(player).count(piece_type);
+                if count == 0 {
+                    continue;
+                }
+                if count > 1 {
+                    hands.push_str(&count.to_string());
+                }
+                let piece = Piece::new(piece_type, player);
+                hands.push_str(&piece.to_sfen());
+            }
+        }
+        if hands.is_empty() {
+            hands.push('-');
+        }
+        hands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTPOS_BOARD: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL";
+
+    fn sq(usi: &str) -> Square {
+        Square::from_usi(usi).unwrap()
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let cases = [
+            ("", SfenError::MissingField("board")),
+            (STARTPOS_BOARD, SfenError::MissingField("turn")),
+            (&format!("{STARTPOS_BOARD} b"), SfenError::MissingField("hands")),
+            ("9/9/9 b - 1", SfenError::RankCount(3)),
+            (
+                "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSN b - 1",
+                SfenError::RankLength {
+                    rank: 9,
+                    squares: 8,
+                },
+            ),
+            (
+                "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNLL b - 1",
+                SfenError::RankLength {
+                    rank: 9,
+                    squares: 10,
+                },
+            ),
+            (
+                "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNS+GKGSNL b - 1",
+                SfenError::InvalidPiece {
+                    rank: 9,
+                    token: "+G".to_string(),
+                },
+            ),
+            (
+                "lnsgkgsnl/1r5b1/ppppppppp/9/4x4/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+                SfenError::InvalidPiece {
+                    rank: 5,
+                    token: "x".to_string(),
+                },
+            ),
+            (&format!("{STARTPOS_BOARD} x - 1"), SfenError::InvalidTurn("x".to_string())),
+            (&format!("{STARTPOS_BOARD} b 2 1"), SfenError::InvalidHand("2".to_string())),
+            (&format!("{STARTPOS_BOARD} b 0P 1"), SfenError::InvalidHand("0P".to_string())),
+            (&format!("{STARTPOS_BOARD} b K 1"), SfenError::InvalidHand("K".to_string())),
+            (&format!("{STARTPOS_BOARD} b - one"), SfenError::InvalidPly("one".to_string())),
+        ];
+
+        for (sfen, expected) in cases {
+            assert_eq!(Position::from_sfen_unchecked(sfen), Err(expected), "{sfen}");
+        }
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases = [
+            (
+                format!("{STARTPOS_BOARD} b P 1"),
+                SfenError::TooManyPieces {
+                    piece_type: PieceType::Pawn,
+                    count: 19,
+                },
+            ),
+            (
+                "4k4/9/9/9/4+R4/9/9/9/4K4 b 2r 1".to_string(),
+                SfenError::TooManyPieces {
+                    piece_type: PieceType::Rook,
+                    count: 3,
+                },
+            ),
+            (
+                "4k4/9/9/9/4k4/9/9/9/4K4 b - 1".to_string(),
+                SfenError::TooManyKings(Player::White),
+            ),
+            (
+                "4k3P/9/9/9/9/9/9/9/4K4 b - 1".to_string(),
+                SfenError::DeadPiece {
+                    square: sq("1a"),
+                    piece: Piece::new(PieceType::Pawn, Player::Black),
+                },
+            ),
+            (
+                "4k4/9/9/9/9/9/9/n8/4K4 b - 1".to_string(),
+                SfenError::DeadPiece {
+                    square: sq("9h"),
+                    piece: Piece::new(PieceType::Knight, Player::White),
+                },
+            ),
+            (
+                "4k4/9/9/9/2P6/9/2P6/9/4K4 b - 1".to_string(),
+                SfenError::DoublePawn {
+                    player: Player::Black,
+                    file: 7,
+                },
+            ),
+        ];
+
+        for (sfen, expected) in cases {
+            assert_eq!(Position::from_sfen(&sfen), Err(expected), "{sfen}");
+        }
+
+        // Promoted pieces are not restricted, a missing king is allowed
+        assert!(Position::from_sfen("4k3+P/9/9/9/2P6/9/2+P6/9/9 b G 1").is_ok());
+        // The unchecked parser keeps invalid but well-formed positions
+        assert!(Position::from_sfen_unchecked(&format!("{STARTPOS_BOARD} b 10P 1")).is_ok());
+    }
+
+    #[test]
+    fn test_usi_position_command() {
+        let position = Position::from_usi_position("position startpos").unwrap();
+        assert_eq!(position, Position::startpos());
+
+        let position = Position::from_usi_position("startpos moves 7g7f 3c3d 8h2b+").unwrap();
+        assert_eq!(
+            position.to_sfen(),
+            "lnsgkgsnl/1r5+B1/pppppp1pp/6p2/9/2P6/PP1PPPPPP/7R1/LNSGKGSNL w B 4"
+        );
+
+        let position =
+            Position::from_usi_position(&format!("position sfen {STARTPOS_SFEN} moves 2g2f"))
+                .unwrap();
+        assert_eq!(position.side_to_move(), Player::White);
+        assert_eq!(position.ply(), 2);
+
+        assert_eq!(
+            Position::from_usi_position("position startpos moves 7g7f 7g7f"),
+            Err(SfenError::IllegalMove {
+                index: 1,
+                notation: "7g7f".to_string()
+            })
+        );
+        assert_eq!(
+            Position::from_usi_position("position startpos moves 7g7f xx"),
+            Err(SfenError::InvalidMove {
+                index: 1,
+                notation: "xx".to_string()
+            })
+        );
+        assert_eq!(
+            Position::from_usi_position("position startpos moves 7g7`"),
+            Err(SfenError::InvalidMove {
+                index: 0,
+                notation: "7g7`".to_string()
+            })
+        );
+        assert!(matches!(
+            Position::from_usi_position("position kifu 7g7f"),
+            Err(SfenError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let error = Position::from_sfen("4k4/9/9/9/2P6/9/2P6/9/4K4 b - 1").unwrap_err();
+        assert_eq!(error.to_string(), "Black has two pawns on file 7");
+
+        let error = Position::from_sfen("4k3P/9/9/9/9/9/9/9/4K4 b - 1").unwrap_err();
+        assert_eq!(error.to_string(), "P on 1a can never move");
+    }
+}
@@ -0,0 +1,131 @@
+//! Board squares
+//!
+//! Squares are indexed file-major: `(file - 1) * 9 + (rank - 1)`, so 1a is 0
+//! and 9i is 80. This matches the square encoding used by `MoveEncoder`.
+
+use std::fmt;
+
+use crate::shogi::Player;
+
+/// A square on the 9x9 board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// Number of squares on the board
+    pub const NUM: usize = 81;
+
+    /// Create a square from file (1-9, right to left from Black's view) and
+    /// rank (1-9, top to bottom)
+    pub fn new(file: u8, rank: u8) -> Option<Square> {
+        if (1..=9).contains(&file) && (1..=9).contains(&rank) {
+            Some(Square((file - 1) * 9 + (rank - 1)))
+        } else {
+            None
+        }
+    }
+
+    /// Create a square from its index (0-80)
+    pub fn from_index(index: usize) -> Option<Square> {
+        if index < Self::NUM {
+            Some(Square(index as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over all 81 squares in index order
+    pub fn all() -> impl Iterator<Item = Square> + Clone {
+        (0..Self::NUM as u8).map(Square)
+    }
+
+    /// Index of the square (0-80)
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// File (筋), 1-9
+    pub fn file(self) -> u8 {
+        self.0 / 9 + 1
+    }
+
+    /// Rank (段), 1-9
+    pub fn rank(self) -> u8 {
+        self.0 % 9 + 1
+    }
+
+    /// Rank counted from the given player's side (1 = farthest rank)
+    pub fn relative_rank(self, player: Player) -> u8 {
+        match player {
+            Player::Black => self.rank(),
+            Player::White => 10 - self.rank(),
+        }
+    }
+
+    /// Whether the square lies in the player's promotion zone (敵陣)
+    pub fn is_promotion_zone(self, player: Player) -> bool {
+        self.relative_rank(player) <= 3
+    }
+
+    /// Square shifted by the given file/rank deltas, if still on the board
+    pub fn offset(self, file_delta: i8, rank_delta: i8) -> Option<Square> {
+        let file = self.file() as i8 + file_delta;
+        let rank = self.rank() as i8 + rank_delta;
+        if (1..=9).contains(&file) && (1..=9).contains(&rank) {
+            Square::new(file as u8, rank as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Parse USI square notation (e.g. "7g")
+    pub fn from_usi(s: &str) -> Option<Square> {
+        match *s.as_bytes() {
+            [file @ b'1'..=b'9', rank @ b'a'..=b'i'] => Square::new(file - b'0', rank - b'a' + 1),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.file(), (b'a' + self.rank() - 1) as char)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_index_layout() {
+        assert_eq!(Square::new(1, 1).unwrap().index(), 0);
+        assert_eq!(Square::new(9, 9).unwrap().index(), 80);
+        assert_eq!(Square::new(7, 7).unwrap().index(), 60);
+        assert!(Square::new(0, 1).is_none());
+        assert!(Square::new(1, 10).is_none());
+    }
+
+    #[test]
+    fn test_usi_roundtrip() {
+        for sq in Square::all() {
+            assert_eq!(Square::from_usi(&sq.to_string()), Some(sq));
+        }
+        assert!(Square::from_usi("0a").is_none());
+        assert!(Square::from_usi("1j").is_none());
+        assert!(Square::from_usi("7").is_none());
+        for invalid in ["7`", "9j", "a7", "77", "７g", "歩"] {
+            assert!(Square::from_usi(invalid).is_none(), "{invalid} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_promotion_zone() {
+        let sq_3c = Square::from_usi("3c").unwrap();
+        let sq_3g = Square::from_usi("3g").unwrap();
+        assert!(sq_3c.is_promotion_zone(Player::Black));
+        assert!(!sq_3c.is_promotion_zone(Player::White));
+        assert!(sq_3g.is_promotion_zone(Player::White));
+        assert_eq!(sq_3g.relative_rank(Player::White), 3);
+    }
+}
@@ -0,0 +1,183 @@
+//! Zobrist keys of the opening book format
+//!
+//! The tables are filled from a fixed xorshift sequence, so every build
+//! derives the same values as the converter that wrote a book file. They are
+//! shared by `PositionHasher`, which hashes SFEN text, and by [`Position`],
+//! which keeps the same key up to date while moves are made and unmade.
+//!
+//! Indices follow the SFEN text rather than [`Square`] indices:
+//!
+//! - board square: `(rank - 1) * 9 + (9 - file)`, i.e. reading order
+//! - board piece: kind (`P L N S G B R K` = 0..7), +8 for White, +16 when
+//!   promoted
+//! - hand piece: kind (`P L N S G B R` = 0..6), +7 for White. Holding `n`
+//!   pieces XORs the key rotated left by `7 * i` for every `i < n`.
+//!
+//! The rotated hand keys are kept for books of format versions 1 and 2; the
+//! rotations wrap after about ten pieces and the XORed sums of many pawns are
+//! far from independent. Version 3 books instead use [`ZobristKeys::hand_count`],
+//! one independent key per (hand piece, count), drawn after all other tables so
+//! the older keys are unchanged.
+//!
+//! [`Position`]: crate::shogi::Position
+
+use std::sync::OnceLock;
+
+use crate::shogi::{Piece, PieceType, Player, Square};
+
+/// Zobrist key tables
+pub struct ZobristKeys {
+    /// `[board square][board piece]`
+    pub pieces: [[u64; 32]; 81],
+    /// Side to move (only part of the key in `HashScheme::WithSideToMove` books)
+    pub turn: u64,
+    /// `[hand piece]`
+    pub hands: [u64; 14],
+    /// `[hand piece][count]`, zero for a count of 0
+    pub hand_counts: [[u64; MAX_HAND_COUNT + 1]; 14],
+}
+
+/// Most pieces of one kind a player can hold (all 18 pawns)
+pub const MAX_HAND_COUNT: usize = 18;
+
+impl ZobristKeys {
+    /// The shared tables, generated on first use
+    pub fn get() -> &'static ZobristKeys {
+        static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+        KEYS.get_or_init(ZobristKeys::generate)
+    }
+
+    fn generate() -> Self {
+        let mut pieces = [[0u64; 32]; 81];
+        let mut rng_state = 0x123456789abcdef0u64;
+
+        for square_pieces in &mut pieces {
+            for piece_value in square_pieces {
+                *piece_value = next_random(&mut rng_state);
+            }
+        }
+
+        let turn = next_random(&mut rng_state);
+
+        let mut hands = [0u64; 14];
+        for value in &mut hands {
+            *value = next_random(&mut rng_state);
+        }
+
+        let mut hand_counts = [[0u64; MAX_HAND_COUNT + 1]; 14];
+        for counts in &mut hand_counts {
+            for value in &mut counts[1..] {
+                *value = next_random(&mut rng_state);
+            }
+        }
+
+        ZobristKeys {
+            pieces,
+            turn,
+            hands,
+            hand_counts,
+        }
+    }
+
+    /// Key of `piece` standing on `square`
+    pub fn piece(&self, square: Square, piece: Piece) -> u64 {
+        let sfen_square = (square.rank() as usize - 1) * 9 + (9 - square.file() as usize);
+        let mut sfen_piece = piece.piece_type.unpromote().index();
+        if piece.owner == Player::White {
+            sfen_piece += 8;
+        }
+        if piece.piece_type.is_promoted() {
+            sfen_piece += 16;
+        }
+        self.pieces[sfen_square][sfen_piece]
+    }
+
+    /// Key toggled when the player's count of `piece_type` in hand changes
+    /// between `count` and `count + 1`
+    pub fn hand(&self, player: Player, piece_type: PieceType, count: u8) -> u64 {
+        match piece_type.hand_index() {
+            Some(index) => {
+                let index = index + player.index() * 7;
+                self.hands[index].rotate_left(count as u32 * 7)
+            }
+            None => 0,
+        }
+    }
+
+    /// Combined key of holding `count` pieces of `piece_type`
+    pub fn hand_total(&self, player: Player, piece_type: PieceType, count: u8) -> u64 {
+        (0..count).fold(0, |key, i| key ^ self.hand(player, piece_type, i))
+    }
+
+    /// Independent key of holding exactly `count` pieces of `piece_type`
+    /// (format version 3)
+    pub fn hand_count(&self, player: Player, piece_type: PieceType, count: u8) -> u64 {
+        match piece_type.hand_index() {
+            Some(index) if (count as usize) <= MAX_HAND_COUNT => {
+                self.hand_counts[index + player.index() * 7][count as usize]
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Xorshift step used to fill the tables
+pub(crate) fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zobrist_random_generation() {
+        let mut state = 0x123456789abcdef0u64;
+        let val1 = next_random(&mut state);
+        let val2 = next_random(&mut state);
+
+        assert_ne!(val1, val2);
+        assert_ne!(val1, 0);
+        assert_ne!(val2, 0);
+    }
+
+    #[test]
+    fn test_sfen_indices() {
+        let keys = ZobristKeys::get();
+
+        // 9a is the first square of the SFEN board, 1i the last
+        let black_pawn = Piece::new(PieceType::Pawn, Player::Black);
+        assert_eq!(keys.piece(Square::from_usi("9a").unwrap(), black_pawn), keys.pieces[0][0]);
+        assert_eq!(keys.piece(Square::from_usi("1i").unwrap(), black_pawn), keys.pieces[80][0]);
+
+        let white_horse = Piece::new(PieceType::Horse, Player::White);
+        assert_eq!(keys.piece(Square::from_usi("1a").unwrap(), white_horse), keys.pieces[8][29]);
+
+        assert_eq!(keys.hand(Player::White, PieceType::Rook, 0), keys.hands[13]);
+        assert_eq!(keys.hand(Player::Black, PieceType::King, 0), 0);
+        assert_eq!(
+            keys.hand_total(Player::Black, PieceType::Pawn, 2),
+            keys.hands[0] ^ keys.hands[0].rotate_left(7)
+        );
+
+        assert_eq!(keys.hand_count(Player::White, PieceType::Pawn, 0), 0);
+        assert_eq!(keys.hand_count(Player::White, PieceType::Pawn, 18), keys.hand_counts[7][18]);
+    }
+
+    #[test]
+    fn test_hand_count_keys_are_distinct() {
+        let keys = ZobristKeys::get();
+        let mut seen = std::collections::HashSet::new();
+        for counts in &keys.hand_counts {
+            for &key in &counts[1..] {
+                assert!(seen.insert(key));
+            }
+        }
+        // Drawn after the older tables, which keep their values
+        assert!(!seen.contains(&keys.turn));
+        assert!(keys.hands.iter().all(|key| !seen.contains(key)));
+    }
+}
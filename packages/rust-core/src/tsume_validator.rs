@@ -0,0 +1,224 @@
+use crate::mate_search::MateSearchEngine;
+use crate::shogi::{Hand, Move, Position};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+// 詰将棋の検討結果
+//
+// 作意手順は最短の詰み手順（受け方は最長の応手）とし、その手順上で検討する
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TsumeReport {
+    // 出題時の手数
+    pub stated_length: usize,
+    // 最短の詰み手数（詰まない・時間切れで見つからない場合は None）
+    pub shortest_length: Option<usize>,
+    // 最短手数が出題手数と一致するか
+    pub length_matches: bool,
+    // 作意手順（USI形式）
+    pub solution: Vec<String>,
+    // 最終手以外に余詰がないか
+    pub unique: bool,
+    // 最終手以外の余詰（攻め方の手番ごとの別の詰め手）
+    pub redundant_mates: Vec<RedundantMate>,
+    // 最終手の余詰（別の詰め上がり）
+    pub final_move_alternatives: Vec<String>,
+    // 詰め上がりで攻め方に残った持ち駒（SFEN形式、例: "2P"。なければ空）
+    pub leftover_pieces: String,
+    // 時間切れなく全ての検討を終えたか
+    pub complete: bool,
+}
+
+// 余詰: 作意手順の ply 手目（0始まり）で、作意以外に詰む手
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RedundantMate {
+    pub ply: usize,
+    pub moves: Vec<String>,
+}
+
+impl TsumeReport {
+    // 手数一致・余詰なし・駒余りなしで、検討が完了している
+    pub fn is_sound(&self) -> bool {
+        self.complete && self.length_matches && self.unique && self.leftover_pieces.is_empty()
+    }
+}
+
+// 詰将棋の検討（余詰・駒余り・手数）
+#[wasm_bindgen]
+pub struct TsumeValidator {
+    engine: MateSearchEngine,
+}
+
+#[wasm_bindgen]
+impl TsumeValidator {
+    // timeout_ms は1回の探索ごとの制限時間
+    #[wasm_bindgen(constructor)]
+    pub fn new(timeout_ms: Option<u32>) -> TsumeValidator {
+        TsumeValidator {
+            engine: MateSearchEngine::new(timeout_ms.unwrap_or(30000)),
+        }
+    }
+
+    // SFEN（手番側が攻め方）で問題を受け取り、検討結果を JSON で返す
+    pub fn validate_sfen(&mut self, sfen: &str, stated_length: usize) -> Result<String, JsValue> {
+        let position = Position::from_sfen(sfen)
+            .map_err(|e| JsValue::from_str(&format!("Invalid SFEN: {e:#}")))?;
+        let report = self.validate(&position, stated_length);
+
+        serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+impl TsumeValidator {
+    // 手番側を攻め方として問題を検討
+    pub fn validate(&mut self, position: &Position, stated_length: usize) -> TsumeReport {
+        let attacker = position.side_to_move();
+        let (is_mate, solution) = self.engine.search_dfpn(position, u8::MAX);
+        let mut complete = !self.engine.timed_out();
+
+        // 作意手順に沿って、攻め方の各手番で別の詰め手がないか調べる
+        let mut redundant_mates = Vec::new();
+        let mut final_move_alternatives = Vec::new();
+        let mut current = position.clone();
+        for (ply, &mv) in solution.iter().enumerate() {
+            if ply % 2 == 0 {
+                let remaining = (solution.len() - ply) as u8;
+                let alternatives = self.alternative_mates(&current, remaining, mv);
+                complete &= !self.engine.timed_out();
+
+                if remaining == 1 {
+                    final_move_alternatives = alternatives;
+                } else if !alternatives.is_empty() {
+                    redundant_mates.push(RedundantMate {
+                        ply,
+                        moves: alternatives,
+                    });
+                }
+            }
+            current.do_move(mv);
+        }
+
+        let shortest_length = is_mate.then_some(solution.len());
+        TsumeReport {
+            stated_length,
+            shortest_length,
+            length_matches: shortest_length == Some(stated_length),
+            solution: solution.iter().map(|mv| mv.to_usi()).collect(),
+            unique: redundant_mates.is_empty(),
+            redundant_mates,
+            final_move_alternatives,
+            leftover_pieces: if is_mate {
+                hand_to_sfen(current.hand(attacker))
+            } else {
+                String::new()
+            },
+            complete,
+        }
+    }
+
+    // remaining 手以内に詰む、作意 mv 以外の手
+    fn alternative_mates(&mut self, position: &Position, remaining: u8, mv: Move) -> Vec<String> {
+        self.engine
+            .mating_moves(position, remaining)
+            .into_iter()
+            .filter(|&other| other != mv)
+            .map(|other| other.to_usi())
+            .collect()
+    }
+}
+
+// 持ち駒を SFEN の表記（大文字、飛角金銀桂香歩の順）で表す
+fn hand_to_sfen(hand: &Hand) -> String {
+    let mut pieces: Vec<_> = hand.iter().filter(|(_, count)| *count > 0).collect();
+    pieces.reverse();
+    pieces
+        .into_iter()
+        .map(|(piece_type, count)| match count {
+            1 => piece_type.sfen_char().to_string(),
+            _ => format!("{count}{}", piece_type.sfen_char()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mate_search::tsume;
+
+    fn validate(board: &str, attacker_hand: &str, stated_length: usize) -> TsumeReport {
+        let mut validator = TsumeValidator::new(Some(60_000));
+        validator.validate(&tsume(board, attacker_hand), stated_length)
+    }
+
+    #[test]
+    fn test_sound_problem() {
+        // 頭金
+        let report = validate("4k4/9/4P4/9/9/9/9/9/9", "G", 1);
+        assert!(report.is_sound(), "{report:?}");
+        assert_eq!(report.solution, ["G*5b"]);
+        assert!(report.final_move_alternatives.is_empty());
+    }
+
+    #[test]
+    fn test_redundant_mate() {
+        // 作意 B*2b の他に R*1c からも3手で詰む
+        let report = validate("6+B1k/6s2/9/9/9/9/9/9/9", "RB", 3);
+        assert!(report.length_matches);
+        assert!(!report.unique);
+        assert_eq!(
+            report.redundant_mates,
+            [RedundantMate {
+                ply: 0,
+                moves: vec!["R*1c".to_string()],
+            }]
+        );
+        assert!(!report.is_sound());
+    }
+
+    #[test]
+    fn test_final_move_alternatives_are_reported_separately() {
+        let report = validate("4+B4/6k2/7N1/5l2g/9/9/9/9/9", "2G", 3);
+        assert!(report.unique);
+        assert_eq!(report.solution[..2], ["G*3c", "3b2a"]);
+
+        // 最終手は G*2b と G*1a のどちらも詰み、片方が作意でもう片方が別の詰め上がり
+        let mut final_moves = vec![report.solution[2].clone()];
+        final_moves.extend(report.final_move_alternatives.iter().cloned());
+        final_moves.sort();
+        assert_eq!(final_moves, ["G*1a", "G*2b"]);
+        assert!(report.is_sound());
+    }
+
+    #[test]
+    fn test_leftover_pieces() {
+        let report = validate("4k4/9/4P4/9/9/9/9/9/9", "GS", 1);
+        assert_eq!(report.leftover_pieces, "S");
+        assert!(!report.is_sound());
+    }
+
+    #[test]
+    fn test_length_mismatch_and_no_mate() {
+        let report = validate("4k4/9/4P4/9/9/9/9/9/9", "G", 3);
+        assert_eq!(report.shortest_length, Some(1));
+        assert!(!report.length_matches);
+
+        let report = validate("3gkg3/9/4P4/9/9/9/9/9/9", "S", 1);
+        assert_eq!(report.shortest_length, None);
+        assert!(report.solution.is_empty());
+        assert!(report.complete);
+        assert!(!report.is_sound());
+    }
+
+    #[test]
+    fn test_validate_sfen_returns_json() {
+        let mut validator = TsumeValidator::new(None);
+        let json = validator
+            .validate_sfen("4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1", 1)
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(report["shortest_length"], 1);
+        assert_eq!(report["solution"][0], "G*5b");
+        assert_eq!(report["unique"], true);
+        assert_eq!(report["leftover_pieces"], "");
+    }
+}
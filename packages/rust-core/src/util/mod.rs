@@ -0,0 +1,3 @@
+//! Helpers shared by the search and the opening book
+
+pub(crate) mod rng;
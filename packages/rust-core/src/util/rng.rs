@@ -0,0 +1,70 @@
+//! Seedable pseudo-random numbers
+//!
+//! The engine's strength handicaps and the opening book's weighted move
+//! choice both need cheap randomness that can be replayed from a seed in
+//! tests, on native targets and on wasm alike.
+
+/// xorshift64* generator
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // A zero state would stay zero
+        Rng {
+            state: mix64(seed) | 1,
+        }
+    }
+
+    /// Generator seeded differently on every run
+    pub(crate) fn from_entropy() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let seed = (js_sys::Math::random() * (1u64 << 53) as f64) as u64;
+        #[cfg(not(target_arch = "wasm32"))]
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Rng::new(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0..n` (`n > 0`)
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Uniform in `[0, 1)` from the top 53 bits
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// 64-bit hash finalizer (the last step of splitmix64)
+pub(crate) fn mix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let values: Vec<u64> = (0..8).map(|_| a.below(10)).collect();
+        assert_eq!(values, (0..8).map(|_| b.below(10)).collect::<Vec<_>>());
+        assert!(values.iter().any(|&v| v != values[0]));
+    }
+}
@@ -17,6 +17,7 @@ mod binary_converter_tests {
                         evaluation: 50,
                         depth: 10,
                         nodes: 10000,
+                        ..Default::default()
                     },
                     RawMove {
                         move_notation: "2g2f".to_string(),
@@ -24,6 +25,7 @@ mod binary_converter_tests {
                         evaluation: 40,
                         depth: 8,
                         nodes: 8000,
+                        ..Default::default()
                     },
                 ],
             },
@@ -38,6 +40,7 @@ mod binary_converter_tests {
                     evaluation: -45,
                     depth: 9,
                     nodes: 9000,
+                    ..Default::default()
                 }],
             },
         ]
@@ -57,6 +60,22 @@ mod binary_converter_tests {
         assert_eq!(binary_entry.moves.len(), 2);
     }
 
+    #[test]
+    fn test_convert_entry_move_count_limit() {
+        let converter = BinaryConverter::new();
+        let mut entry = create_test_entries().into_iter().next().unwrap();
+        let filler = entry.moves[1].clone();
+
+        entry.moves.resize(255, filler.clone());
+        let binary_entry = converter.convert_entry(&entry).unwrap();
+        assert_eq!(binary_entry.header.move_count, 255);
+
+        // A 256th move no longer fits the one-byte count
+        entry.moves.push(filler);
+        assert!(converter.convert_entry(&entry).is_err());
+        assert!(converter.write_binary(&[entry], &mut Vec::new()).is_err());
+    }
+
     #[test]
     fn test_encode_decode_position_header() {
         let header = CompactPosition {
@@ -119,6 +138,240 @@ mod binary_converter_tests {
         assert_eq!(read_entries.len(), 2);
     }
 
+    #[test]
+    fn test_versioned_scheme_roundtrip() {
+        use shogi_core::opening_book_reader::OpeningBookReader;
+
+        for scheme in [HashScheme::WithSideToMove, HashScheme::PerCountHands] {
+            let converter = BinaryConverter::with_scheme(scheme);
+            let entries = create_test_entries();
+
+            let mut buffer = Vec::new();
+            converter.write_binary(&entries, &mut buffer).unwrap();
+
+            let (header, read_entries) =
+                converter.read_binary_with_header(&mut Cursor::new(&buffer)).unwrap();
+            assert_eq!(header.scheme(), Some(scheme));
+            assert!(header.is_indexed());
+            assert_eq!(read_entries.len(), 2);
+
+            // The reader picks the scheme from the header version
+            let mut reader = OpeningBookReader::new();
+            reader.load_data(&converter.compress_data(&buffer).unwrap()).unwrap();
+            assert_eq!(reader.hash_scheme(), scheme);
+
+            let board = "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL";
+            assert_eq!(reader.find_moves(&format!("{board} w - 2")).len(), 1);
+            assert!(reader.find_moves(&format!("{board} b - 2")).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_indexed_book_lookup() {
+        let converter = BinaryConverter::with_scheme(HashScheme::WithSideToMove);
+        let mut entries = create_test_entries();
+        // Same position as the first entry at a later move: the last one wins
+        let mut repeated = entries[0].clone();
+        repeated.move_count = 5;
+        repeated.moves.truncate(1);
+        entries.push(repeated);
+
+        let mut buffer = Vec::new();
+        converter.write_binary(&entries, &mut buffer).unwrap();
+
+        let book = IndexedBook::parse(&buffer).unwrap();
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.scheme(), HashScheme::WithSideToMove);
+
+        // The index is sorted and points at the matching entries
+        let hashes: Vec<u64> = (0..book.len()).map(|i| book.index_entry(i).hash).collect();
+        assert!(hashes.windows(2).all(|pair| pair[0] <= pair[1]));
+        for (i, &hash) in hashes.iter().enumerate() {
+            assert_eq!(book.entry(i).unwrap().header.position_hash, hash);
+        }
+
+        let hash_of = |entry| converter.convert_entry(entry).unwrap().header.position_hash;
+        let found = book.find_entry(hash_of(&entries[1])).unwrap().unwrap();
+        assert_eq!(found.moves.len(), 1);
+        assert_eq!(found.header.evaluation, -45);
+        let found = book.find_entry(hash_of(&entries[0])).unwrap().unwrap();
+        assert_eq!(found.moves.len(), 1, "the repeated entry should win");
+        assert!(book.find(0).is_none());
+        assert!(book.find(u64::MAX).is_none());
+
+        // An offset past the end of the book, even beyond 4 GiB, is an error
+        let mut corrupt = buffer.clone();
+        corrupt[FILE_HEADER_SIZE + 14..FILE_HEADER_SIZE + 16].copy_from_slice(&[1, 0]);
+        let corrupt_book = IndexedBook::parse(&corrupt).unwrap();
+        assert_eq!(corrupt_book.index_entry(0).offset >> 32, 1);
+        assert!(corrupt_book.entry(0).is_err());
+
+        // Unindexed or truncated data is rejected
+        assert!(IndexedBook::parse(&buffer[..20]).is_err());
+        let mut unindexed = buffer.clone();
+        unindexed[4..8].copy_from_slice(&2u32.to_le_bytes());
+        assert!(!IndexedBook::is_indexed(&unindexed));
+    }
+
+    #[test]
+    fn test_mapped_book_lookup() {
+        let converter = BinaryConverter::with_scheme(HashScheme::WithSideToMove);
+        let mut buffer = Vec::new();
+        converter.write_binary(&create_test_entries(), &mut buffer).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("binary_converter_test_{}.bin", std::process::id()));
+        std::fs::write(&path, &buffer).unwrap();
+        let book = MappedBook::open(&path).unwrap();
+        assert_eq!(book.len(), 2);
+        assert_eq!(book.scheme(), HashScheme::WithSideToMove);
+
+        let board = "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL";
+        let moves = book.find_moves(&format!("{board} w - 2"));
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].notation, "3c3d");
+        assert!(book.find_moves(&format!("{board} b - 2")).is_empty());
+        let position = shogi_core::shogi::Position::startpos();
+        assert_eq!(book.find_moves_for_position(&position).len(), 2);
+        drop(book);
+
+        // Compressed books have to be loaded by OpeningBookReader instead
+        std::fs::write(&path, converter.compress_data(&buffer).unwrap()).unwrap();
+        assert!(MappedBook::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_move_stats_roundtrip() {
+        let converter = BinaryConverter::new();
+        let mut entries = create_test_entries();
+        entries[0].moves[0].stats = Some(MoveStats {
+            play_count: 200,
+            wins: 110,
+            draws: 10,
+            losses: 80,
+        });
+        entries[0].moves[1].stats = Some(MoveStats {
+            play_count: 100,
+            wins: 40,
+            draws: 0,
+            losses: 55,
+        });
+
+        let mut buffer = Vec::new();
+        converter.write_binary(&entries, &mut buffer).unwrap();
+
+        let (header, read_entries) =
+            converter.read_binary_with_header(&mut Cursor::new(&buffer)).unwrap();
+        assert!(header.has_move_stats());
+        assert_eq!(header.scheme(), Some(HashScheme::BoardAndHands));
+        assert_eq!(header.move_size(), 22);
+
+        let hash = converter.convert_entry(&entries[0]).unwrap().header.position_hash;
+        let entry = read_entries.iter().find(|e| e.header.position_hash == hash).unwrap();
+        // 300 games saturate the one-byte popularity
+        assert_eq!(entry.header.popularity, 255);
+        assert_eq!(Some(entry.move_stats[0]), entries[0].moves[0].stats);
+        assert_eq!(Some(entry.move_stats[1]), entries[0].moves[1].stats);
+
+        // Moves without statistics are stored with zero counts
+        let other = read_entries.iter().find(|e| e.header.position_hash != hash).unwrap();
+        assert_eq!(other.header.popularity, 1);
+        assert_eq!(other.move_stats, vec![MoveStats::default()]);
+
+        let book = IndexedBook::parse(&buffer).unwrap();
+        assert!(book.has_move_stats());
+        let found = book.find_entry(hash).unwrap().unwrap();
+        assert_eq!(found.move_stats[1].losses, 55);
+
+        // Books without statistics keep 6-byte moves and popularity 1
+        let mut plain = Vec::new();
+        converter.write_binary(&create_test_entries(), &mut plain).unwrap();
+        let (header, read_entries) =
+            converter.read_binary_with_header(&mut Cursor::new(&plain)).unwrap();
+        assert!(!header.has_move_stats());
+        assert!(read_entries.iter().all(|e| e.header.popularity == 1));
+        assert!(read_entries.iter().all(|e| e.move_stats.is_empty()));
+    }
+
+    #[test]
+    fn test_move_stats_encoding() {
+        let stats = MoveStats {
+            play_count: 1234,
+            wins: 666,
+            draws: 12,
+            losses: 556,
+        };
+
+        let encoded = BinaryConverter::encode_move_stats(&stats);
+        assert_eq!(encoded.len(), MOVE_STATS_SIZE);
+        assert_eq!(BinaryConverter::decode_move_stats(&encoded).unwrap(), stats);
+        assert!(BinaryConverter::decode_move_stats(&encoded[..4]).is_err());
+    }
+
+    #[test]
+    fn test_read_unindexed_book() {
+        // Books written before the index was added: header followed by entries
+        let converter = BinaryConverter::new();
+        let binary_entries = converter.convert_entries(&create_test_entries()).unwrap();
+        let mut data = Vec::new();
+        for entry in &binary_entries {
+            data.extend(BinaryConverter::encode_position_header(&entry.header));
+            for mov in &entry.moves {
+                data.extend(BinaryConverter::encode_move(mov));
+            }
+        }
+        let checksum = data
+            .chunks(4)
+            .map(|chunk| chunk.iter().rev().fold(0u32, |value, &byte| value << 8 | byte as u32))
+            .fold(0u32, u32::wrapping_add);
+        let header = BinaryFileHeader {
+            magic: *b"SFEN",
+            version: 1,
+            position_count: binary_entries.len() as u32,
+            checksum,
+        };
+        let mut buffer = converter.encode_file_header(&header);
+        buffer.extend(data);
+
+        let (header, entries) =
+            converter.read_binary_with_header(&mut Cursor::new(&buffer)).unwrap();
+        assert!(!header.is_indexed());
+        assert_eq!(header.scheme(), Some(HashScheme::BoardAndHands));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].moves.len(), 2);
+    }
+
+    #[test]
+    fn test_position_index_encoding() {
+        let index = PositionIndex {
+            hash: 0x0123_4567_89AB_CDEF,
+            offset: 48,
+            length: 28,
+        };
+
+        let encoded = BinaryConverter::encode_position_index(&index);
+        assert_eq!(encoded.len(), POSITION_INDEX_SIZE);
+
+        let decoded = BinaryConverter::decode_position_index(&encoded).unwrap();
+        assert_eq!(decoded.hash, index.hash);
+        assert_eq!(decoded.offset, index.offset);
+        assert_eq!(decoded.length, index.length);
+        assert!(BinaryConverter::decode_position_index(&encoded[..8]).is_err());
+
+        // Offsets past 4 GiB keep their high bits after the length
+        let index = PositionIndex {
+            offset: 0x1234_5678_9ABC,
+            ..index
+        };
+        let encoded = BinaryConverter::encode_position_index(&index);
+        assert_eq!(&encoded[8..12], &0x5678_9ABCu32.to_le_bytes());
+        assert_eq!(&encoded[14..16], &0x1234u16.to_le_bytes());
+        let decoded = BinaryConverter::decode_position_index(&encoded).unwrap();
+        assert_eq!(decoded.offset, 0x1234_5678_9ABC);
+        assert_eq!(decoded.length, 28);
+    }
+
     #[test]
     fn test_file_header() {
         let converter = BinaryConverter::new();
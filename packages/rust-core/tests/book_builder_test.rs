@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod book_builder_tests {
+    use shogi_core::opening_book::*;
+    use shogi_core::opening_book_reader::OpeningBookReader;
+
+    const STARTPOS: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+    const AFTER_7G7F: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 2";
+
+    fn sample_games() -> Vec<GameRecord> {
+        let kif = "\
+手合割：平手
+   1 ７六歩(77)
+   2 ３四歩(33)
+   3 ２六歩(27)
+   4 投了
+";
+        let csa = "\
+PI
++
++7776FU
+-8384FU
+%TORYO
+/
+PI
++
++2726FU
+-8384FU
+%SENNICHITE
+";
+        let usi = "position startpos moves 7g7f 3c3d 6g6f\n";
+
+        let mut games = GameFormat::Kif.parse(kif).unwrap();
+        games.extend(GameFormat::Csa.parse(csa).unwrap());
+        games.extend(GameFormat::Usi.parse(usi).unwrap());
+        games
+    }
+
+    #[test]
+    fn test_build_book_from_games() {
+        let mut builder = BookBuilder::new(2);
+        for game in sample_games() {
+            builder.add_game(&game);
+        }
+        assert_eq!(builder.game_count(), 4);
+        // Start, after 7g7f and after 2g2f
+        assert_eq!(builder.len(), 3);
+
+        let entries = builder.entries(1);
+        let start = &entries[0];
+        assert_eq!(start.move_count, 1);
+        let notations: Vec<&str> = start.moves.iter().map(|m| m.move_notation.as_str()).collect();
+        assert_eq!(notations, ["7g7f", "2g2f"]);
+        // 7g7f: a win by Black, a loss by resignation after 8c8d, no result
+        assert_eq!(
+            start.moves[0].stats,
+            Some(MoveStats {
+                play_count: 3,
+                wins: 1,
+                draws: 0,
+                losses: 1,
+            })
+        );
+        assert_eq!(start.moves[0].evaluation, 0);
+
+        // Moves played once are dropped with a higher minimum
+        let entries = builder.entries(2);
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.moves.iter().all(|m| m.nodes >= 2)));
+    }
+
+    #[test]
+    fn test_built_book_is_readable() {
+        let mut builder = BookBuilder::new(8);
+        for game in sample_games() {
+            builder.add_game(&game);
+        }
+        let converter = BinaryConverter::with_scheme(HashScheme::PerCountHands);
+        let mut data = Vec::new();
+        let stats = converter.write_binary(&builder.entries(1), &mut data).unwrap();
+        assert_eq!(stats.positions_written, builder.len());
+
+        let mut reader = OpeningBookReader::new();
+        reader.load_data(&converter.compress_data(&data).unwrap()).unwrap();
+
+        let moves = reader.find_moves(STARTPOS);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].notation, "7g7f");
+        assert_eq!((moves[0].play_count, moves[0].wins, moves[0].losses), (3, 1, 1));
+
+        // White's replies to 7g7f: 3c3d twice (a loss and no result), 8c8d once
+        // (a win by resignation)
+        let moves = reader.find_moves(AFTER_7G7F);
+        assert_eq!(moves.len(), 2);
+        assert_eq!((moves[0].notation.as_str(), moves[0].play_count), ("3c3d", 2));
+        assert_eq!((moves[1].notation.as_str(), moves[1].wins), ("8c8d", 1));
+        assert!(moves[1].evaluation > moves[0].evaluation);
+    }
+}
@@ -22,6 +22,17 @@ mod move_encoder_tests {
         }
     }
 
+    #[test]
+    fn test_encode_uppercase_ranks() {
+        for (upper, lower) in [("7G7F", "7g7f"), ("3D3C+", "3d3c+"), ("P*5F", "P*5f")] {
+            assert_eq!(
+                MoveEncoder::encode_move(upper).unwrap(),
+                MoveEncoder::encode_move(lower).unwrap(),
+                "{upper}"
+            );
+        }
+    }
+
     #[test]
     fn test_encode_promotion_moves() {
         let test_cases = vec![
@@ -142,9 +153,8 @@ mod move_encoder_tests {
         for encoding in invalid_encodings {
             let result = MoveEncoder::decode_move(encoding);
             // Should either succeed with a valid move or fail gracefully
-            if result.is_ok() {
+            if let Ok(decoded) = result {
                 // If it succeeds, re-encoding should give the same result
-                let decoded = result.unwrap();
                 let re_encoded = MoveEncoder::encode_move(&decoded);
                 assert!(re_encoded.is_ok());
             }
@@ -8,8 +8,6 @@ use std::io::Read;
 
 // 初期盤面のSFEN（手数0）
 const INITIAL_SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 0";
-// 2六歩の後の盤面
-const AFTER_26_SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/7P1/PPPPPPP1P/1B5R1/LNSGKGSNL b - 1";
 // 7六歩の後の盤面
 const AFTER_76_SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL b - 2";
 
@@ -137,6 +135,6 @@ fn debug_initial_position_hash_parts() {
     println!("\nHash input (first 3 parts): {}", hash_input);
 
     // ハッシュを計算
-    let hash = PositionHasher::hash_position(&INITIAL_SFEN).unwrap();
+    let hash = PositionHasher::hash_position(INITIAL_SFEN).unwrap();
     println!("Calculated hash: {:#016x}", hash);
 }
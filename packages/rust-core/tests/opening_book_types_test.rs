@@ -27,6 +27,7 @@ mod data_structures_tests {
             evaluation: 50,
             depth: 2,
             nodes: 1000,
+            ..Default::default()
         };
 
         assert_eq!(raw_move.move_notation, "7g7f");
@@ -49,8 +50,8 @@ mod data_structures_tests {
 
     #[test]
     fn test_position_index_size() {
-        // Verify PositionIndex is exactly 16 bytes for efficient lookup
-        assert_eq!(std::mem::size_of::<PositionIndex>(), 16);
+        // Verify index entries are stored in exactly 16 bytes for efficient lookup
+        assert_eq!(POSITION_INDEX_SIZE, 16);
     }
 
     #[test]
@@ -95,12 +96,10 @@ mod data_structures_tests {
             hash: 0x1234567890ABCDEF,
             offset: 1024,
             length: 256,
-            reserved: 0,
         };
 
         assert_eq!(index.hash, 0x1234567890ABCDEF);
         assert_eq!(index.offset, 1024);
         assert_eq!(index.length, 256);
-        assert_eq!(index.reserved, 0);
     }
 }
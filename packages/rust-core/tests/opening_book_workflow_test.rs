@@ -248,6 +248,7 @@ N*4e none 140 14 190000
                         evaluation: 50,
                         depth: 10,
                         nodes: 10000,
+                        ..Default::default()
                     }],
                 };
                 chunk_entries.push(entry);
@@ -16,6 +16,7 @@ mod position_filter_tests {
                     evaluation: eval,
                     depth,
                     nodes: 1000,
+                    ..Default::default()
                 })
                 .collect(),
         }
@@ -110,6 +111,7 @@ mod position_filter_tests {
                     evaluation: 100,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "2g2f".to_string(),
@@ -117,6 +119,7 @@ mod position_filter_tests {
                     evaluation: 90,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "6g6f".to_string(),
@@ -124,6 +127,7 @@ mod position_filter_tests {
                     evaluation: 80,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "5g5f".to_string(),
@@ -131,6 +135,7 @@ mod position_filter_tests {
                     evaluation: 70,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "4g4f".to_string(),
@@ -138,6 +143,7 @@ mod position_filter_tests {
                     evaluation: 60,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "3g3f".to_string(),
@@ -145,6 +151,7 @@ mod position_filter_tests {
                     evaluation: 50,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "1g1f".to_string(),
@@ -152,6 +159,7 @@ mod position_filter_tests {
                     evaluation: 40,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "9g9f".to_string(),
@@ -159,6 +167,7 @@ mod position_filter_tests {
                     evaluation: 30,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "8g8f".to_string(),
@@ -166,6 +175,7 @@ mod position_filter_tests {
                     evaluation: 20,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "P*5f".to_string(),
@@ -173,6 +183,7 @@ mod position_filter_tests {
                     evaluation: 10,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
             ],
         };
@@ -222,6 +233,7 @@ mod position_filter_tests {
                     evaluation: 50,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "7g7f".to_string(),
@@ -229,6 +241,7 @@ mod position_filter_tests {
                     evaluation: 100,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "1g1f".to_string(),
@@ -236,6 +249,7 @@ mod position_filter_tests {
                     evaluation: 40,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "2g2f".to_string(),
@@ -243,6 +257,7 @@ mod position_filter_tests {
                     evaluation: 90,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "6g6f".to_string(),
@@ -250,6 +265,7 @@ mod position_filter_tests {
                     evaluation: 80,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
             ],
         };
@@ -286,6 +302,7 @@ mod position_filter_tests {
                     evaluation: 150,
                     depth: 5,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "2g2f".to_string(),
@@ -293,6 +310,7 @@ mod position_filter_tests {
                     evaluation: 100,
                     depth: 2,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "6g6f".to_string(),
@@ -300,6 +318,7 @@ mod position_filter_tests {
                     evaluation: -50,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
             ],
         };
@@ -319,6 +338,7 @@ mod position_filter_tests {
                     evaluation: 150,
                     depth: 2,
                     nodes: 1000,
+                    ..Default::default()
                 },
                 RawMove {
                     move_notation: "2g2f".to_string(),
@@ -326,6 +346,7 @@ mod position_filter_tests {
                     evaluation: 100,
                     depth: 1,
                     nodes: 1000,
+                    ..Default::default()
                 },
             ],
         };
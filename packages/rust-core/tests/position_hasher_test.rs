@@ -179,4 +179,158 @@ mod position_hasher_tests {
         assert!(stats.collision_count == 0); // No collisions expected with good hash
         assert!(stats.unique_positions >= 4);
     }
+
+    #[test]
+    fn test_incremental_key_matches_sfen_hash() {
+        use shogi_core::shogi::{Move, Position};
+
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next_random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..4 {
+            let mut position = Position::startpos();
+            let mut history: Vec<(Move, _, u64)> = Vec::new();
+
+            // Play long enough for captures, drops and promotions
+            for _ in 0..150 {
+                let moves = position.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let mv = moves[(next_random() % moves.len() as u64) as usize];
+                let key_before = position.book_key();
+                let captured = position.do_move(mv);
+
+                let sfen = position.to_sfen();
+                let expected = PositionHasher::hash_position(&sfen).unwrap();
+                assert_eq!(position.book_key(), expected, "after {mv} in {sfen}");
+                for scheme in [HashScheme::WithSideToMove, HashScheme::PerCountHands] {
+                    let expected = PositionHasher::hash_position_with(&sfen, scheme).unwrap();
+                    assert_eq!(scheme.position_key(&position), expected, "{scheme:?} in {sfen}");
+                }
+                history.push((mv, captured, key_before));
+            }
+
+            // Unmaking restores every earlier key
+            while let Some((mv, captured, key_before)) = history.pop() {
+                position.undo_move(mv, captured);
+                assert_eq!(position.book_key(), key_before);
+            }
+            assert_eq!(position, Position::startpos());
+        }
+    }
+
+    #[test]
+    fn test_parsed_position_key_matches_sfen_hash() {
+        use shogi_core::shogi::Position;
+
+        for sfen in [
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+            "+B+B1Sg2nl/5kg2/4p2p1/3pspP1p/p6PP/1p1rP4/P1p2P2N/1PG1G4/LNK5R w GSN3Prsl3p 120",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b 10P2n3s 1",
+        ] {
+            // Book positions are hashed without validation, like the hasher does
+            let position = Position::from_sfen_unchecked(sfen).unwrap();
+            assert_eq!(position.book_key(), PositionHasher::hash_position(sfen).unwrap());
+        }
+    }
+
+    /// Hand strings for every split of pawns, lances, golds and rooks between
+    /// the two players (pieces not in hand are assumed captured off the board)
+    fn hand_variations() -> Vec<String> {
+        fn splits(total: u8) -> Vec<(u8, u8)> {
+            (0..=total)
+                .flat_map(|black| (0..=total - black).map(move |white| (black, white)))
+                .collect()
+        }
+        fn push(hand: &mut String, count: u8, piece: char) {
+            match count {
+                0 => {}
+                1 => hand.push(piece),
+                _ => hand.push_str(&format!("{count}{piece}")),
+            }
+        }
+
+        let mut hands = Vec::new();
+        for (rook_b, rook_w) in splits(2) {
+            for (gold_b, gold_w) in splits(4) {
+                for (lance_b, lance_w) in splits(4) {
+                    for (pawn_b, pawn_w) in splits(18) {
+                        let mut hand = String::new();
+                        push(&mut hand, rook_b, 'R');
+                        push(&mut hand, gold_b, 'G');
+                        push(&mut hand, lance_b, 'L');
+                        push(&mut hand, pawn_b, 'P');
+                        push(&mut hand, rook_w, 'r');
+                        push(&mut hand, gold_w, 'g');
+                        push(&mut hand, lance_w, 'l');
+                        push(&mut hand, pawn_w, 'p');
+                        if hand.is_empty() {
+                            hand.push('-');
+                        }
+                        hands.push(hand);
+                    }
+                }
+            }
+        }
+        hands
+    }
+
+    /// Number of positions whose key, reduced by `reduce`, is shared with an
+    /// earlier position
+    fn count_collisions(
+        scheme: HashScheme,
+        sfens: &[String],
+        reduce: impl Fn(u64) -> u64,
+    ) -> usize {
+        let mut seen = std::collections::HashSet::new();
+        sfens
+            .iter()
+            .map(|sfen| PositionHasher::hash_position_with(sfen, scheme).unwrap())
+            .filter(|&hash| !seen.insert(reduce(hash)))
+            .count()
+    }
+
+    #[test]
+    fn test_hand_hash_collision_rate() {
+        let sfens: Vec<String> = hand_variations()
+            .iter()
+            .map(|hand| format!("4k4/9/9/9/9/9/9/9/4K4 b {hand} 1"))
+            .collect();
+        assert_eq!(sfens.len(), 6 * 15 * 15 * 190);
+
+        // Collisions expected from uniformly random 24-bit keys
+        let n = sfens.len() as f64;
+        let buckets = (1u64 << 24) as f64;
+        let expected = n - buckets * (1.0 - (1.0 - 1.0 / buckets).powf(n));
+
+        // Keys folded to 32 bits, as a hash table would index them: the rotated
+        // keys let one exchange of rooks, golds and pawns cancel out, so every
+        // lance split of those hands collides, while the per-count keys keep
+        // all of them apart
+        let fold = |hash: u64| (hash ^ hash >> 32) & 0xffff_ffff;
+        let mut folded = Vec::new();
+        for scheme in [HashScheme::BoardAndHands, HashScheme::PerCountHands] {
+            let full = count_collisions(scheme, &sfens, |hash| hash);
+            let truncated = count_collisions(scheme, &sfens, |hash| hash & 0xff_ffff);
+            folded.push(count_collisions(scheme, &sfens, fold));
+            println!(
+                "{scheme:?}: {full} collisions in {} positions, {truncated} on 24 bits \
+                 (random keys: {expected:.0}), {} folded to 32 bits",
+                sfens.len(),
+                folded.last().unwrap()
+            );
+
+            if scheme == HashScheme::PerCountHands {
+                assert_eq!(full, 0);
+                assert!((truncated as f64) < expected * 1.5, "{truncated} vs {expected:.0}");
+            }
+        }
+        assert!(folded[1] < folded[0], "{} vs {} folded collisions", folded[1], folded[0]);
+    }
 }
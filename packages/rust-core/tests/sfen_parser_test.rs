@@ -46,7 +46,7 @@ mod sfen_parser_integration_tests {
         }
 
         // Verify that we parsed entries
-        assert!(entries.len() > 0, "Should have parsed at least one entry");
+        assert!(!entries.is_empty(), "Should have parsed at least one entry");
 
         // Verify basic properties of parsed entries
         for entry in &entries {
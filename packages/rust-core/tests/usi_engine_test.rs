@@ -0,0 +1,252 @@
+#[cfg(test)]
+mod usi_engine_tests {
+    use shogi_core::opening_book::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::{Child, ChildStdin, Command, Stdio};
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// The engine binary driven over its stdin/stdout
+    struct Engine {
+        child: Child,
+        stdin: ChildStdin,
+        lines: Receiver<String>,
+    }
+
+    impl Engine {
+        fn start() -> Self {
+            let mut child = Command::new(env!("CARGO_BIN_EXE_usi_engine"))
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("failed to start usi_engine");
+            let stdin = child.stdin.take().unwrap();
+            let stdout = child.stdout.take().unwrap();
+
+            let (sender, lines) = mpsc::channel();
+            thread::spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    if sender.send(line.unwrap()).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Engine {
+                child,
+                stdin,
+                lines,
+            }
+        }
+
+        fn send(&mut self, command: &str) {
+            writeln!(self.stdin, "{command}").unwrap();
+            self.stdin.flush().unwrap();
+        }
+
+        /// Lines up to and including the first one starting with `prefix`
+        fn read_until(&mut self, prefix: &str) -> Vec<String> {
+            let mut lines = Vec::new();
+            loop {
+                let line = self
+                    .lines
+                    .recv_timeout(TIMEOUT)
+                    .unwrap_or_else(|_| panic!("no '{prefix}' from engine, got {lines:?}"));
+                let done = line.starts_with(prefix);
+                lines.push(line);
+                if done {
+                    return lines;
+                }
+            }
+        }
+
+        fn best_move(&mut self) -> String {
+            let line = self.read_until("bestmove").pop().unwrap();
+            line.split_whitespace().nth(1).unwrap().to_string()
+        }
+
+        fn assert_no_bestmove(&self, wait: Duration) {
+            let deadline = std::time::Instant::now() + wait;
+            while let Some(left) = deadline.checked_duration_since(std::time::Instant::now()) {
+                if let Ok(line) = self.lines.recv_timeout(left) {
+                    assert!(!line.starts_with("bestmove"), "answered early: {line}");
+                }
+            }
+        }
+
+        fn quit(mut self) {
+            self.send("quit");
+            let status = self.child.wait().unwrap();
+            assert!(status.success());
+        }
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut engine = Engine::start();
+
+        engine.send("usi");
+        let lines = engine.read_until("usiok");
+        assert!(lines[0].starts_with("id name "));
+        assert!(lines.iter().any(|l| l.starts_with("option name BookFile type string")));
+        assert!(lines.iter().any(|l| l.starts_with("option name EngineStrength type combo")));
+
+        engine.send("isready");
+        engine.read_until("readyok");
+        engine.quit();
+    }
+
+    #[test]
+    fn test_finds_mate() {
+        let mut engine = Engine::start();
+        engine.send("isready");
+        engine.read_until("readyok");
+
+        engine.send("position sfen 4k4/9/4P4/9/9/9/9/9/9 b G2r2b3g4s4n4l17p 1");
+        engine.send("go btime 0 wtime 0 byoyomi 1000");
+        let lines = engine.read_until("bestmove");
+        assert!(lines.iter().any(|l| l.contains("score mate 1")), "{lines:?}");
+        assert_eq!(lines.last().unwrap(), "bestmove G*5b");
+        engine.quit();
+    }
+
+    #[test]
+    fn test_rejects_malformed_position() {
+        let mut engine = Engine::start();
+        engine.send("position startpos moves 7g7`");
+        let lines = engine.read_until("info string invalid position");
+        assert!(lines.last().unwrap().contains("7g7`"), "{lines:?}");
+
+        // The engine survives and keeps the previous position
+        engine.send("isready");
+        engine.read_until("readyok");
+        engine.quit();
+    }
+
+    #[test]
+    fn test_searches_to_requested_depth() {
+        let mut engine = Engine::start();
+        engine.send("setoption name USI_Hash value 8");
+        engine.send("isready");
+        engine.read_until("readyok");
+
+        // The rook on 7f is free for the bishop
+        engine.send("position sfen 4k4/9/9/9/9/2r6/9/4B4/4K4 b - 1");
+        engine.send("go depth 4");
+        let lines = engine.read_until("bestmove");
+        assert!(lines.iter().any(|l| l.starts_with("info depth 4 score cp ")), "{lines:?}");
+        assert_eq!(lines.last().unwrap(), "bestmove 5h7f");
+        engine.quit();
+    }
+
+    #[test]
+    fn test_infinite_and_ponder_wait_for_command() {
+        let mut engine = Engine::start();
+        engine.send("isready");
+        engine.read_until("readyok");
+
+        engine.send("position startpos moves 7g7f 3c3d");
+        engine.send("go infinite");
+        engine.assert_no_bestmove(Duration::from_millis(200));
+        engine.send("stop");
+        let mv = engine.best_move();
+        assert_ne!(mv, "resign");
+
+        engine.send("go ponder btime 1000 wtime 1000 byoyomi 100");
+        engine.assert_no_bestmove(Duration::from_millis(200));
+        engine.send("ponderhit");
+        engine.best_move();
+        engine.quit();
+    }
+
+    #[test]
+    fn test_plays_book_move() {
+        let entry = RawSfenEntry {
+            position: "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL".to_string(),
+            turn: 'b',
+            hand: "-".to_string(),
+            move_count: 1,
+            moves: vec![
+                RawMove {
+                    move_notation: "2g2f".to_string(),
+                    move_type: "none".to_string(),
+                    evaluation: 40,
+                    depth: 20,
+                    nodes: 0,
+                    ..Default::default()
+                },
+                RawMove {
+                    move_notation: "7g7f".to_string(),
+                    move_type: "none".to_string(),
+                    evaluation: 55,
+                    depth: 20,
+                    nodes: 0,
+                    ..Default::default()
+                },
+            ],
+        };
+        let converter = BinaryConverter::new();
+        let mut data = Vec::new();
+        converter.write_binary(&[entry], &mut data).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("usi_engine_test_{}.binz", std::process::id()));
+        std::fs::write(&path, converter.compress_data(&data).unwrap()).unwrap();
+
+        let mut engine = Engine::start();
+        engine.send(&format!("setoption name BookFile value {}", path.display()));
+        engine.send("isready");
+        engine.read_until("readyok");
+
+        engine.send("position startpos");
+        engine.send("go btime 0 wtime 0 byoyomi 500");
+        assert_eq!(engine.best_move(), "7g7f");
+
+        // Beginners do not use the book at all
+        engine.send("setoption name EngineStrength value beginner");
+        engine.send("isready");
+        engine.read_until("readyok");
+        engine.send("position startpos");
+        engine.send("go btime 0 wtime 0 byoyomi 200");
+        let lines = engine.read_until("bestmove");
+        assert!(!lines.iter().any(|l| l.contains("book move")), "{lines:?}");
+        assert!(!lines.iter().any(|l| l.starts_with("info depth 3 ")), "{lines:?}");
+
+        engine.send("setoption name EngineStrength value expert");
+        engine.send("setoption name UseBook value false");
+        engine.send("isready");
+        engine.read_until("readyok");
+        engine.send("position startpos");
+        engine.send("go btime 0 wtime 0 byoyomi 200");
+        let lines = engine.read_until("bestmove");
+        assert!(!lines.iter().any(|l| l.contains("book move")), "{lines:?}");
+
+        engine.quit();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unusable_eval_file_falls_back() {
+        let path =
+            std::env::temp_dir().join(format!("usi_engine_test_{}.nn.bin", std::process::id()));
+        std::fs::write(&path, b"not a network").unwrap();
+
+        let mut engine = Engine::start();
+        engine.send(&format!("setoption name EvalFile value {}", path.display()));
+        engine.send("isready");
+        let lines = engine.read_until("readyok");
+        assert!(
+            lines.iter().any(|l| l.starts_with("info string failed to load eval file")),
+            "{lines:?}"
+        );
+
+        engine.send("position sfen 4k4/9/9/9/9/2r6/9/4B4/4K4 b - 1");
+        engine.send("go depth 3");
+        assert_eq!(engine.best_move(), "5h7f");
+
+        engine.quit();
+        std::fs::remove_file(path).unwrap();
+    }
+}
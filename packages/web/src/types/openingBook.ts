@@ -2,6 +2,12 @@ export interface BookMove {
     notation: string;
     evaluation: number;
     depth: number;
+    // Number of games the move was played in (1 for books without game statistics)
+    play_count?: number;
+    // Results for the player making the move, from books built from game records
+    wins?: number;
+    draws?: number;
+    losses?: number;
 }
 
 export interface LoadProgress {